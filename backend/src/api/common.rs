@@ -1,25 +1,42 @@
 use bincode::Options;
+use postgres_types::ToSql;
 use serde::{Deserialize, Serialize};
+use tokio_postgres::Row;
 
 use crate::{
     api::{
         Id,
         Context,
         err::{self, ApiResult},
-        model::{event::AuthorizedEvent, series::Series, realm::Realm, search::SearchSeriesExtended},
+        model::{
+            event::AuthorizedEvent,
+            playlist::AuthorizedPlaylist,
+            series::Series,
+            realm::Realm,
+            search::SearchSeriesExtended,
+        },
     },
     prelude::*,
     search::Event as SearchEvent,
     search::Realm as SearchRealm,
     search::Series as SearchSeries,
-    db::types::ExtraMetadata,
+    db::types::{ExtraMetadata, Key},
 };
 
 
 /// A node with a globally unique ID. Mostly useful for relay.
 #[juniper::graphql_interface(
     Context = Context,
-    for = [AuthorizedEvent, Realm, Series, SearchEvent, SearchRealm, SearchSeries, SearchSeriesExtended]
+    for = [
+        AuthorizedEvent,
+        AuthorizedPlaylist,
+        Realm,
+        Series,
+        SearchEvent,
+        SearchRealm,
+        SearchSeries,
+        SearchSeriesExtended,
+    ]
 )]
 pub(crate) trait Node {
     fn id(&self) -> Id;
@@ -96,6 +113,257 @@ where
 }
 
 
+/// Information about the current page of a connection (paginated list).
+#[derive(Debug, Clone, juniper::GraphQLObject)]
+pub(crate) struct PageInfo {
+    pub(crate) has_next_page: bool,
+    pub(crate) has_previous_page: bool,
+
+    // TODO: the spec says these shouldn't be optional, but that makes no sense.
+    // See: https://stackoverflow.com/q/70448483/2408867
+    pub(crate) start_cursor: Option<Cursor>,
+    pub(crate) end_cursor: Option<Cursor>,
+
+    /// The index of the first returned item.
+    pub(crate) start_index: Option<i32>,
+    /// The index of the last returned item.
+    pub(crate) end_index: Option<i32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, juniper::GraphQLEnum)]
+pub(crate) enum SortDirection {
+    Ascending,
+    Descending,
+}
+
+impl SortDirection {
+    pub(crate) fn to_sql(self) -> &'static str {
+        match self {
+            SortDirection::Ascending => "asc",
+            SortDirection::Descending => "desc",
+        }
+    }
+
+    pub(crate) fn is_ascending(&self) -> bool {
+        matches!(self, Self::Ascending)
+    }
+
+    pub(crate) fn reversed(self) -> Self {
+        match self {
+            SortDirection::Ascending => SortDirection::Descending,
+            SortDirection::Descending => SortDirection::Ascending,
+        }
+    }
+}
+
+/// Items that can be loaded page-wise with `load_connection`.
+pub(crate) trait Paginated: Sized {
+    /// The sort order passed via API, e.g. `EventSortOrder`.
+    type Order;
+
+    /// The cursor used for `after` and `before`.
+    type Cursor: PageCursor<Order = Self::Order>;
+
+    /// The SQL `from` item to load items from, e.g. a table name. Has to have
+    /// an `id` column.
+    const FROM: &'static str;
+
+    /// The columns required by `from_page_row`, without table prefix.
+    fn page_columns() -> String;
+
+    /// The SQL expression to sort by and the sort direction.
+    fn sort_sql(order: &Self::Order) -> (&'static str, SortDirection);
+
+    /// Creates the item and its cursor from a row starting with the columns
+    /// of `page_columns`.
+    fn from_page_row(row: &Row, order: &Self::Order) -> (Self, Self::Cursor);
+}
+
+/// Cursor of a `Paginated` type.
+pub(crate) trait PageCursor: Serialize + for<'de> Deserialize<'de> {
+    type Order;
+
+    fn key(&self) -> &Key;
+
+    /// Returns the value of the sort column as trait object if it matches
+    /// `order`. Returns an error otherwise.
+    fn to_sql_arg(&self, order: &Self::Order) -> ApiResult<&(dyn ToSql + Sync + '_)>;
+}
+
+/// One page of items, as returned by `load_connection`.
+pub(crate) struct Page<T> {
+    pub(crate) items: Vec<T>,
+    pub(crate) page_info: PageInfo,
+    pub(crate) total_count: i32,
+}
+
+/// Loads one page of all items matching the SQL condition `base.0`, which
+/// can refer to the arguments `base.1` via `$1`, `$2`, ... Exactly one of
+/// `first` and `last` must be set.
+pub(crate) async fn load_connection<T: Paginated>(
+    context: &Context,
+    (base_filter, base_args): (&str, &[&(dyn ToSql + Sync)]),
+    order: T::Order,
+    first: Option<i32>,
+    after: Option<Cursor>,
+    last: Option<i32>,
+    before: Option<Cursor>,
+) -> ApiResult<Page<T>> {
+    const MAX_COUNT: i32 = 100;
+
+    // Argument validation
+    let after = after.map(|c| c.deserialize::<T::Cursor>()).transpose()?;
+    let before = before.map(|c| c.deserialize::<T::Cursor>()).transpose()?;
+    if first.map_or(false, |first| first <= 0) {
+        return Err(err::invalid_input!("argument 'first' has to be > 0, but is {:?}", first));
+    }
+    if last.map_or(false, |last| last <= 0) {
+        return Err(err::invalid_input!("argument 'last' has to be > 0, but is {:?}", last));
+    }
+
+    // Make sure only one of `first` and `last` is set and figure out the
+    // limit and SQL sort order. If `last` is set, we reverse the order in
+    // the SQL query in order to use `limit` effectively. We reverse it
+    // again in Rust further below.
+    let (col, direction) = T::sort_sql(&order);
+    let (limit, sql_sort_order) = match (first, last) {
+        (Some(first), None) => (first, direction),
+        (None, Some(last)) => (last, direction.reversed()),
+        _ => return Err(err::invalid_input!("exactly one of 'first' and 'last' must be given")),
+    };
+    let limit = std::cmp::min(limit, MAX_COUNT);
+
+
+    // Assemble argument list and the "where" part of the query. This
+    // depends on `after` and `before`. The arguments for `base_filter`
+    // come first.
+    let mut args = base_args.to_vec();
+    let n = base_args.len();
+    let op_after = if direction.is_ascending() { '>' } else { '<' };
+    let op_before = if direction.is_ascending() { '<' } else { '>' };
+    let filter = match (&after, &before) {
+        (None, None) => String::new(),
+        (Some(after), None) => {
+            args.extend_from_slice(&[after.to_sql_arg(&order)?, after.key()]);
+            format!("where ({}, id) {} (${}, ${})", col, op_after, n + 1, n + 2)
+        }
+        (None, Some(before)) => {
+            args.extend_from_slice(&[before.to_sql_arg(&order)?, before.key()]);
+            format!("where ({}, id) {} (${}, ${})", col, op_before, n + 1, n + 2)
+        }
+        (Some(after), Some(before)) => {
+            args.extend_from_slice(&[
+                after.to_sql_arg(&order)?,
+                after.key(),
+                before.to_sql_arg(&order)?,
+                before.key(),
+            ]);
+            format!(
+                "where ({}, id) {} (${}, ${}) and ({}, id) {} (${}, ${})",
+                col, op_after, n + 1, n + 2, col, op_before, n + 3, n + 4,
+            )
+        },
+    };
+
+    // Assemble full query. This query is a bit involved but allows us to
+    // retrieve the total count, the absolute offsets of our window and all
+    // the item data in one go. The "over(...)" things are window functions.
+    // The last two columns are the row number and the total count.
+    let query = format!(
+        "select * \
+            from (\
+                select {cols}, \
+                    row_number() over(order by ({col}, id) {sort_order}) as row_num, \
+                    count(*) over() as total_count \
+                from {from} \
+                where {base_filter} \
+                order by ({col}, id) {sort_order} \
+            ) as tmp \
+            {filter} \
+            limit {limit}",
+        cols = T::page_columns(),
+        from = T::FROM,
+        sort_order = sql_sort_order.to_sql(),
+    );
+
+    // `first_num` and `last_num` are 1-based!
+    let mut total_count = None;
+    let mut first_num = None;
+    let mut last_num = None;
+
+    // Execute query
+    let mut items = context.db.query_mapped(&query, args, |row: Row| {
+        // Retrieve total count once
+        if total_count.is_none() {
+            total_count = Some(row.get::<_, i64>(row.len() - 1));
+        }
+
+        // Handle row numbers
+        let row_num = row.get::<_, i64>(row.len() - 2);
+        last_num = Some(row_num);
+        if first_num.is_none() {
+            first_num = Some(row_num);
+        }
+
+        // Retrieve actual item data
+        T::from_page_row(&row, &order)
+    }).await?;
+
+    // If total count is `None`, there are no items. We really do want to
+    // know the total count, so we do another query.
+    let total_count = match total_count {
+        Some(c) => c,
+        None => {
+            let query = format!("select count(*) from {} where {base_filter}", T::FROM);
+            context.db
+                .query_one(&query, base_args)
+                .await?
+                .get::<_, i64>(0)
+        }
+    };
+
+    // If `last` was given, we had to query in reverse order to make `limit`
+    // work. So now we need to reverse the result here. We also need to
+    // adjust the last and first "num".
+    if sql_sort_order != direction {
+        items.reverse();
+        let tmp = first_num;
+        first_num = last_num.map(|n| total_count - n + 1);
+        last_num = tmp.map(|n| total_count - n + 1);
+    }
+
+    // Figure out whether there is a next and/or previous page.
+    let (has_next_page, has_previous_page) = match Option::zip(first_num, last_num) {
+        Some((first, last)) => (last < total_count, first > 1),
+        None => {
+            // The DB returned 0 items. That means there are either actually 0
+            // matching items, or all of them were filtered by `after` or `before`.
+            if total_count == 0 {
+                (false, false)
+            } else if after.is_some() {
+                (false, true)
+            } else {
+                (true, false)
+            }
+        }
+    };
+
+    let cast_i32 = |x: i64| x.try_into().expect("more then 2^31 items");
+    Ok(Page {
+        total_count: cast_i32(total_count),
+        page_info: PageInfo {
+            has_next_page,
+            has_previous_page,
+            start_cursor: items.first().map(|(_, cursor)| Cursor::new(cursor)),
+            end_cursor: items.last().map(|(_, cursor)| Cursor::new(cursor)),
+            start_index: first_num.map(cast_i32),
+            end_index: last_num.map(cast_i32),
+        },
+        items: items.into_iter().map(|(item, _)| item).collect(),
+    })
+}
+
+
 #[juniper::graphql_scalar(
     name = "ExtraMetadata",
     description = "Arbitrary metadata for events/series. Serialized as JSON object.",
@@ -7,6 +7,7 @@ use crate::{
     db::Transaction,
     search,
     prelude::*,
+    sync::OcClient,
 };
 
 
@@ -17,6 +18,8 @@ pub(crate) struct Context {
     pub(crate) config: Arc<Config>,
     pub(crate) jwt: Arc<JwtContext>,
     pub(crate) search: Arc<search::Client>,
+    /// Client for the default Opencast, used to send changes to Opencast.
+    pub(crate) oc_client: Arc<OcClient>,
 }
 
 impl juniper::Context for Context {}
@@ -1,7 +1,13 @@
-use juniper::GraphQLObject;
+use std::collections::{HashMap, HashSet};
+
+use juniper::{GraphQLInputObject, GraphQLObject};
 use postgres_types::BorrowToSql;
 
-use crate::{api::{util::TranslatedString, Context, err::ApiResult}, db::util::select};
+use crate::{
+    api::{util::TranslatedString, Context, err::{ApiResult, invalid_input}},
+    auth::{HasRoles, ROLE_ADMIN},
+    db::{types::CustomActions, util::select},
+};
 
 
 
@@ -44,6 +50,100 @@ pub(crate) struct RoleInfo {
     pub large: bool,
 }
 
+/// An entry of a new ACL, i.e. one role and everything it is allowed to do.
+#[derive(Debug, GraphQLInputObject)]
+pub(crate) struct AclInputEntry {
+    /// In a list of entries, no two entries may have the same `role`.
+    pub role: String,
+
+    /// `read`, `write` or any custom action (e.g. `annotate`). Must not be
+    /// empty.
+    pub actions: Vec<String>,
+}
+
+/// A validated new ACL, split into the parts we store in the DB.
+pub(crate) struct NewAcl {
+    pub(crate) read_roles: Vec<String>,
+    pub(crate) write_roles: Vec<String>,
+    pub(crate) custom_actions: CustomActions,
+}
+
+impl NewAcl {
+    /// Validates the given ACL entries. Roles must not be empty or contain
+    /// whitespace, and must not be one of the roles granting Tobira
+    /// privileges (see `RoleConfig`). `ROLE_ADMIN` is ignored as admins
+    /// always have full access anyway. Unless the user is admin, they also
+    /// have to keep write access, as they could not change the ACL back
+    /// otherwise.
+    pub(crate) fn from_input(entries: Vec<AclInputEntry>, context: &Context) -> ApiResult<Self> {
+        let role_config = &context.config.auth.roles;
+        let is_valid_name = |s: &str| !s.is_empty() && !s.contains(char::is_whitespace);
+
+        let mut seen_roles = HashSet::new();
+        let mut read_roles = vec![];
+        let mut write_roles = vec![];
+        let mut custom_actions = HashMap::<String, Vec<String>>::new();
+        for AclInputEntry { role, actions } in entries {
+            if !is_valid_name(&role) {
+                return Err(invalid_input!("invalid role {role:?} in ACL"));
+            }
+            if role_config.is_privilege_role(&role) {
+                return Err(invalid_input!(
+                    "role '{role}' grants Tobira privileges and cannot be used in ACLs",
+                ));
+            }
+            if !seen_roles.insert(role.clone()) {
+                return Err(invalid_input!("role '{role}' appears multiple times in ACL"));
+            }
+            if actions.is_empty() {
+                return Err(invalid_input!("no actions given for role '{role}' in ACL"));
+            }
+            if role == ROLE_ADMIN {
+                continue;
+            }
+
+            for action in actions {
+                let roles = match action.as_str() {
+                    "read" => &mut read_roles,
+                    "write" => &mut write_roles,
+                    _ => {
+                        if !is_valid_name(&action) {
+                            return Err(invalid_input!("invalid action {action:?} in ACL"));
+                        }
+                        custom_actions.entry(action).or_default()
+                    }
+                };
+                if !roles.contains(&role) {
+                    roles.push(role.clone());
+                }
+            }
+        }
+
+        if !context.auth.overlaps_roles(&write_roles) {
+            return Err(invalid_input!(
+                key = "acl.no-write-access-left",
+                "new ACL does not give write access to any of your roles",
+            ));
+        }
+
+        Ok(Self {
+            read_roles,
+            write_roles,
+            custom_actions: CustomActions(custom_actions),
+        })
+    }
+
+    /// Returns all `(role, action)` pairs of this ACL, as expected by
+    /// `OcClient::update_event_acl` and `OcClient::update_series_acl`.
+    pub(crate) fn opencast_entries(&self) -> Vec<(&str, &str)> {
+        let read = self.read_roles.iter().map(|role| (role.as_str(), "read"));
+        let write = self.write_roles.iter().map(|role| (role.as_str(), "write"));
+        let custom = self.custom_actions.0.iter()
+            .flat_map(|(action, roles)| roles.iter().map(|role| (role.as_str(), action.as_str())));
+        read.chain(write).chain(custom).collect()
+    }
+}
+
 pub(crate) async fn load_for<P, I>(
     context: &Context,
     raw_roles: &str,
@@ -0,0 +1,225 @@
+use std::future::Future;
+
+use chrono::{DateTime, Utc};
+use juniper::graphql_object;
+
+use crate::{
+    api::{Context, Id, err::ApiResult},
+    auth::AuthContext,
+    db::{types::Key, util::{impl_from_db, select}},
+    prelude::*,
+};
+use super::{
+    block::{BlockValue, RemovedBlock},
+    realm::{Realm, RemovedRealm},
+};
+
+
+/// An entry of the audit log, recording one mutation that changed a realm
+/// and/or its blocks.
+pub(crate) struct AuditLogEntry {
+    pub(crate) timestamp: DateTime<Utc>,
+    pub(crate) username: Option<String>,
+    pub(crate) mutation: String,
+    pub(crate) realm_path: Option<String>,
+    pub(crate) block: Option<Key>,
+    pub(crate) before: Option<String>,
+    pub(crate) after: Option<String>,
+}
+
+impl_from_db!(
+    AuditLogEntry,
+    select: {
+        audit_log.{
+            timestamp, username, mutation, realm_path, block,
+            before: "before::text",
+            after: "after::text",
+        },
+    },
+    |row| {
+        Self {
+            timestamp: row.timestamp(),
+            username: row.username(),
+            mutation: row.mutation(),
+            realm_path: row.realm_path(),
+            block: row.block(),
+            before: row.before(),
+            after: row.after(),
+        }
+    },
+);
+
+#[graphql_object(Context = Context)]
+impl AuditLogEntry {
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    /// The user who performed the mutation. `null` if it was performed by a
+    /// trusted external application.
+    fn username(&self) -> Option<&str> {
+        self.username.as_deref()
+    }
+
+    /// Name of the GraphQL mutation, e.g. `removeBlock`.
+    fn mutation(&self) -> &str {
+        &self.mutation
+    }
+
+    /// The path of the realm at the time of the mutation.
+    fn realm_path(&self) -> Option<&str> {
+        self.realm_path.as_deref()
+    }
+
+    /// The block the mutation targeted, if any. The block might not exist
+    /// anymore.
+    fn block(&self) -> Option<Id> {
+        self.block.map(Id::block)
+    }
+
+    /// The realm including all its blocks before the mutation, as JSON.
+    /// `null` if the realm did not exist before.
+    fn before(&self) -> Option<&str> {
+        self.before.as_deref()
+    }
+
+    /// The realm including all its blocks after the mutation, as JSON.
+    /// `null` if the realm was removed.
+    fn after(&self) -> Option<&str> {
+        self.after.as_deref()
+    }
+}
+
+impl AuditLogEntry {
+    /// Loads all entries for the given realm, newest first.
+    pub(crate) async fn load_for_realm(realm: Key, context: &Context) -> ApiResult<Vec<Self>> {
+        let selection = Self::select();
+        let query = format!("select {selection} from audit_log \
+            where realm = $1 \
+            order by timestamp desc, id desc");
+        context.db
+            .query_mapped(&query, dbargs![&realm], |row| Self::from_row_start(&row))
+            .await?
+            .pipe(Ok)
+    }
+}
+
+
+/// What a mutation passed to `audited` operates on.
+pub(crate) enum AuditTarget {
+    Realm(Id),
+    /// A realm and all its descendants, e.g. when removing a realm. One entry
+    /// is added per realm.
+    RealmTree(Id),
+    Block(Id),
+    /// The mutation creates a new realm, which it returns.
+    NewRealm,
+}
+
+/// Output of an audited mutation.
+pub(crate) trait AuditedOutput {
+    /// The realm that was changed or created, if the output refers to one.
+    fn audited_realm(&self) -> Option<Key> {
+        None
+    }
+}
+
+impl AuditedOutput for Realm {
+    fn audited_realm(&self) -> Option<Key> {
+        Some(self.key)
+    }
+}
+
+impl AuditedOutput for RemovedRealm {}
+impl AuditedOutput for BlockValue {}
+impl AuditedOutput for RemovedBlock {}
+
+/// Runs the mutation `f` and, if it succeeds, adds an entry to the audit log,
+/// containing snapshots of the affected realm from before and after the
+/// mutation. For `AuditTarget::RealmTree`, one such entry is added for each
+/// affected realm.
+pub(crate) async fn audited<T: AuditedOutput>(
+    mutation: &'static str,
+    target: AuditTarget,
+    context: &Context,
+    f: impl Future<Output = ApiResult<T>>,
+) -> ApiResult<T> {
+    let mut with_descendants = false;
+    let (realm, block) = match target {
+        AuditTarget::Realm(id) => (id.key_for(Id::REALM_KIND), None),
+        AuditTarget::RealmTree(id) => {
+            with_descendants = true;
+            (id.key_for(Id::REALM_KIND), None)
+        }
+        AuditTarget::Block(id) => {
+            let block = id.key_for(Id::BLOCK_KIND);
+            let realm = match block {
+                None => None,
+                // Blocks being restored are still in the trash, and blocks
+                // added to a draft only exist in `draft_blocks`.
+                Some(key) => context.db
+                    .query_one(
+                        "select coalesce(\
+                            (select realm from blocks where id = $1), \
+                            (select realm from draft_blocks where id = $1), \
+                            (select parent from trash where block = $1)\
+                        )",
+                        &[&key],
+                    )
+                    .await?
+                    .get::<_, Option<Key>>(0),
+            };
+            (realm, block)
+        }
+        AuditTarget::NewRealm => (None, None),
+    };
+
+    // Snapshots of all affected realms: their key, path and JSON. If the IDs
+    // are invalid, `f` will fail below anyway.
+    let before = match realm {
+        None => vec![],
+        Some(key) => {
+            let descendants_cond = if with_descendants {
+                "or starts_with(full_path, (select full_path from realms where id = $1) || '/')"
+            } else {
+                ""
+            };
+            let query = format!("select id, full_path, realm_snapshot(id)::text from realms \
+                where id = $1 {descendants_cond} \
+                order by full_path");
+            context.db
+                .query_mapped(&query, dbargs![&key], |row| {
+                    (row.get::<_, Key>(0), row.get::<_, String>(1), row.get::<_, String>(2))
+                })
+                .await?
+        }
+    };
+
+    let out = f.await?;
+
+    let entries = if before.is_empty() {
+        vec![(out.audited_realm().or(realm), None, None)]
+    } else {
+        before.into_iter()
+            .map(|(key, path, snapshot)| (Some(key), Some(path), Some(snapshot)))
+            .collect()
+    };
+    let username = match &context.auth {
+        AuthContext::User(user) => Some(&user.username),
+        _ => None,
+    };
+    for (realm, before_path, before) in entries {
+        context.db
+            .execute(
+                "insert into audit_log \
+                    (username, mutation, realm, realm_path, block, before, after) \
+                    values ($1, $2, $3, \
+                        coalesce((select full_path from realms where id = $3), $4), \
+                        $5, $6::text::jsonb, realm_snapshot($3))",
+                &[&username, &mutation, &realm, &before_path, &block, &before],
+            )
+            .await?;
+    }
+
+    Ok(out)
+}
@@ -1,16 +1,18 @@
 //! Blocks that make up the content of realm pages.
 
+use chrono::{DateTime, Utc};
 use juniper::{graphql_interface, graphql_object, GraphQLEnum};
 use postgres_types::{FromSql, ToSql};
 
 use crate::{
     api::{
-        Context, Id,
+        Context, Cursor, Id,
         err::{ApiError, ApiResult},
         model::{
-            event::{AuthorizedEvent, Event},
+            event::{AuthorizedEvent, Event, EventConnection},
+            playlist::{AuthorizedPlaylist, Playlist},
             series::Series,
-            realm::{Realm, RealmNameSourceBlockValue},
+            realm::{BlockTable, Realm, RealmNameSourceBlockValue},
         },
     },
     db::{types::Key, util::impl_from_db},
@@ -24,17 +26,23 @@ pub(crate) use mutations::{
     NewTitleBlock,
     NewTextBlock,
     NewSeriesBlock,
+    NewPlaylistBlock,
     NewVideoBlock,
+    NewRealmListBlock,
+    NewEmbedBlock,
     UpdateTitleBlock,
     UpdateTextBlock,
     UpdateSeriesBlock,
+    UpdatePlaylistBlock,
     UpdateVideoBlock,
+    UpdateRealmListBlock,
+    UpdateEmbedBlock,
     RemovedBlock,
 };
 
 
 /// A `Block`: a UI element that belongs to a realm.
-#[graphql_interface(Context = Context, for = [TitleBlock, TextBlock, SeriesBlock, VideoBlock])]
+#[graphql_interface(Context = Context, for = [TitleBlock, TextBlock, SeriesBlock, PlaylistBlock, VideoBlock, RealmListBlock, EmbedBlock])]
 pub(crate) trait Block {
     // To avoid code duplication, all the shared data is stored in `SharedData`
     // and only a `shared` method is mandatory. All other method (in particular,
@@ -54,6 +62,16 @@ pub(crate) trait Block {
             // Foreign key constraints guarantee the realm exists
             .map(Option::unwrap)
     }
+    /// Start of the time window in which this block is visible. `null` means
+    /// the block is visible from the start.
+    fn visible_from(&self) -> Option<DateTime<Utc>> {
+        self.shared().visible_from
+    }
+    /// End of the time window in which this block is visible. `null` means
+    /// the block stays visible.
+    fn visible_until(&self) -> Option<DateTime<Utc>> {
+        self.shared().visible_until
+    }
 }
 
 #[derive(Debug, Clone, Copy, FromSql)]
@@ -67,6 +85,12 @@ pub(crate) enum BlockType {
     Series,
     #[postgres(name = "video")]
     Video,
+    #[postgres(name = "playlist")]
+    Playlist,
+    #[postgres(name = "realm_list")]
+    RealmList,
+    #[postgres(name = "embed")]
+    Embed,
 }
 
 #[derive(Debug, Clone, Copy, FromSql, ToSql, GraphQLEnum)]
@@ -93,12 +117,32 @@ pub(crate) enum VideoListLayout {
     List,
 }
 
+#[derive(Debug, Clone, Copy, FromSql, ToSql, GraphQLEnum)]
+#[postgres(name = "realm_list_layout")]
+pub(crate) enum RealmListLayout {
+    #[postgres(name = "gallery")]
+    Gallery,
+    #[postgres(name = "list")]
+    List,
+}
+
 /// Data shared by all blocks.
 #[derive(Debug)]
 pub(crate) struct SharedData {
     pub(crate) id: Id,
     pub(crate) index: i32,
     pub(crate) realm_key: Key,
+    pub(crate) visible_from: Option<DateTime<Utc>>,
+    pub(crate) visible_until: Option<DateTime<Utc>>,
+}
+
+impl SharedData {
+    /// Whether the current time is inside the visibility window of this block.
+    pub(crate) fn is_visible_now(&self) -> bool {
+        let now = Utc::now();
+        self.visible_from.map_or(true, |from| from <= now)
+            && self.visible_until.map_or(true, |until| until > now)
+    }
 }
 
 #[derive(Debug)]
@@ -131,6 +175,14 @@ impl TitleBlock {
     async fn realm(&self, context: &Context) -> ApiResult<Realm> {
         Block::realm(self, context).await
     }
+
+    fn visible_from(&self) -> Option<DateTime<Utc>> {
+        Block::visible_from(self)
+    }
+
+    fn visible_until(&self) -> Option<DateTime<Utc>> {
+        Block::visible_until(self)
+    }
 }
 
 #[derive(Debug)]
@@ -163,6 +215,14 @@ impl TextBlock {
     async fn realm(&self, context: &Context) -> ApiResult<Realm> {
         Block::realm(self, context).await
     }
+
+    fn visible_from(&self) -> Option<DateTime<Utc>> {
+        Block::visible_from(self)
+    }
+
+    fn visible_until(&self) -> Option<DateTime<Utc>> {
+        Block::visible_until(self)
+    }
 }
 
 #[derive(Debug)]
@@ -208,6 +268,92 @@ impl SeriesBlock {
         self.layout
     }
 
+    /// One page of the series' events, sorted by the `order` of this block.
+    /// `null` if the block has no series. Exactly one of `first` and `last`
+    /// must be set!
+    async fn events(
+        &self,
+        first: Option<i32>,
+        after: Option<Cursor>,
+        last: Option<i32>,
+        before: Option<Cursor>,
+        context: &Context,
+    ) -> ApiResult<Option<EventConnection>> {
+        let Some(series_key) = self.series.and_then(|id| id.key_for(Id::SERIES_KIND)) else {
+            return Ok(None);
+        };
+
+        AuthorizedEvent::load_connection_for_series(
+            series_key, context, self.order.into(), first, after, last, before,
+        ).await.map(Some)
+    }
+
+    fn id(&self) -> Id {
+        Block::id(self)
+    }
+
+    fn index(&self) -> i32 {
+        Block::index(self)
+    }
+
+    async fn realm(&self, context: &Context) -> ApiResult<Realm> {
+        Block::realm(self, context).await
+    }
+
+    fn visible_from(&self) -> Option<DateTime<Utc>> {
+        Block::visible_from(self)
+    }
+
+    fn visible_until(&self) -> Option<DateTime<Utc>> {
+        Block::visible_until(self)
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct PlaylistBlock {
+    pub(crate) shared: SharedData,
+    pub(crate) playlist: Option<Id>,
+    pub(crate) show_title: bool,
+    pub(crate) show_metadata: bool,
+    pub(crate) order: VideoListOrder,
+    pub(crate) layout: VideoListLayout,
+}
+
+impl Block for PlaylistBlock {
+    fn shared(&self) -> &SharedData {
+        &self.shared
+    }
+}
+
+/// A block showing the list of videos in an Opencast playlist
+#[graphql_object(Context = Context, impl = BlockValue)]
+impl PlaylistBlock {
+    async fn playlist(&self, context: &Context) -> ApiResult<Option<Playlist>> {
+        match self.playlist {
+            None => Ok(None),
+            // `unwrap` is okay here because of our foreign key constraint
+            Some(playlist_id) => Ok(Some(
+                AuthorizedPlaylist::load_by_id(playlist_id, context).await?.unwrap()
+            )),
+        }
+    }
+
+    fn show_title(&self) -> bool {
+        self.show_title
+    }
+
+    fn show_metadata(&self) -> bool {
+        self.show_metadata
+    }
+
+    fn order(&self) -> VideoListOrder {
+        self.order
+    }
+
+    fn layout(&self) -> VideoListLayout {
+        self.layout
+    }
+
     fn id(&self) -> Id {
         Block::id(self)
     }
@@ -219,6 +365,14 @@ impl SeriesBlock {
     async fn realm(&self, context: &Context) -> ApiResult<Realm> {
         Block::realm(self, context).await
     }
+
+    fn visible_from(&self) -> Option<DateTime<Utc>> {
+        Block::visible_from(self)
+    }
+
+    fn visible_until(&self) -> Option<DateTime<Utc>> {
+        Block::visible_until(self)
+    }
 }
 
 #[derive(Debug)]
@@ -243,7 +397,14 @@ impl VideoBlock {
         match self.event {
             None => Ok(None),
             // `unwrap` is okay here because of our foreign key constraint
-            Some(event_id) => Ok(Some(AuthorizedEvent::load_by_id(event_id, context).await?.unwrap())),
+            Some(event_id) => {
+                let event = AuthorizedEvent::load_by_id(event_id, context).await?.unwrap();
+                // Events pending deletion are treated as already deleted.
+                match event {
+                    Event::Event(e) if e.is_deletion_pending() => Ok(None),
+                    event => Ok(Some(event)),
+                }
+            }
         }
     }
 
@@ -266,6 +427,136 @@ impl VideoBlock {
     async fn realm(&self, context: &Context) -> ApiResult<Realm> {
         Block::realm(self, context).await
     }
+
+    fn visible_from(&self) -> Option<DateTime<Utc>> {
+        Block::visible_from(self)
+    }
+
+    fn visible_until(&self) -> Option<DateTime<Utc>> {
+        Block::visible_until(self)
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct RealmListBlock {
+    pub(crate) shared: SharedData,
+    /// `None` if all children of the block's realm are listed.
+    pub(crate) realms: Option<Vec<Key>>,
+    pub(crate) layout: RealmListLayout,
+}
+
+impl Block for RealmListBlock {
+    fn shared(&self) -> &SharedData {
+        &self.shared
+    }
+}
+
+/// A block showing a list of realms: either selected ones or all children of
+/// the block's realm.
+#[graphql_object(Context = Context, impl = BlockValue)]
+impl RealmListBlock {
+    /// Whether all children of the block's realm are listed instead of
+    /// selected realms.
+    fn all_children(&self) -> bool {
+        self.realms.is_none()
+    }
+
+    /// The listed realms. Selected realms are returned in the order they were
+    /// selected in, skipping ones that were removed. Children are returned
+    /// ordered by their index, just like `Realm.children`: the frontend is
+    /// supposed to sort them according to the `childOrder` of the realm.
+    async fn realms(&self, context: &Context) -> ApiResult<Vec<Realm>> {
+        let selection = Realm::select();
+        let (query, arg) = match &self.realms {
+            None => (
+                format!("select {selection} from realms where parent = $1 order by index"),
+                &self.shared.realm_key as &(dyn postgres_types::ToSql + Sync),
+            ),
+            Some(keys) => (
+                format!("select {selection} \
+                    from unnest($1::bigint[]) with ordinality as listed(id, position) \
+                    inner join realms on realms.id = listed.id \
+                    order by listed.position"),
+                keys as &(dyn postgres_types::ToSql + Sync),
+            ),
+        };
+        context.db
+            .query_mapped(&query, dbargs![arg], |row| Realm::from_row_start(&row))
+            .await?
+            .pipe(Ok)
+    }
+
+    fn layout(&self) -> RealmListLayout {
+        self.layout
+    }
+
+    fn id(&self) -> Id {
+        Block::id(self)
+    }
+
+    fn index(&self) -> i32 {
+        Block::index(self)
+    }
+
+    async fn realm(&self, context: &Context) -> ApiResult<Realm> {
+        Block::realm(self, context).await
+    }
+
+    fn visible_from(&self) -> Option<DateTime<Utc>> {
+        Block::visible_from(self)
+    }
+
+    fn visible_until(&self) -> Option<DateTime<Utc>> {
+        Block::visible_until(self)
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct EmbedBlock {
+    pub(crate) shared: SharedData,
+    pub(crate) url: String,
+}
+
+impl Block for EmbedBlock {
+    fn shared(&self) -> &SharedData {
+        &self.shared
+    }
+}
+
+/// A block embedding external content, e.g. a quiz or a slide deck.
+#[graphql_object(Context = Context, impl = BlockValue)]
+impl EmbedBlock {
+    /// URL of the embedded content.
+    fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Whether `url` belongs to one of the configured `general.embed_origins`.
+    /// If not (e.g. because the configuration changed), browsers refuse to
+    /// load the content.
+    fn allowed(&self, context: &Context) -> bool {
+        self.url.parse().is_ok_and(|url| context.config.general.is_embed_allowed(&url))
+    }
+
+    fn id(&self) -> Id {
+        Block::id(self)
+    }
+
+    fn index(&self) -> i32 {
+        Block::index(self)
+    }
+
+    async fn realm(&self, context: &Context) -> ApiResult<Realm> {
+        Block::realm(self, context).await
+    }
+
+    fn visible_from(&self) -> Option<DateTime<Utc>> {
+        Block::visible_from(self)
+    }
+
+    fn visible_until(&self) -> Option<DateTime<Utc>> {
+        Block::visible_until(self)
+    }
 }
 
 impl_from_db!(
@@ -277,6 +568,7 @@ impl_from_db!(
             index,
             text_content,
             series,
+            playlist,
             videolist_order,
             videolist_layout,
             video,
@@ -284,6 +576,11 @@ impl_from_db!(
             show_link,
             show_metadata,
             realm,
+            visible_from,
+            visible_until,
+            realms,
+            realmlist_layout,
+            embed_url,
         },
     },
     |row| {
@@ -292,6 +589,8 @@ impl_from_db!(
             id: Id::block(row.id()),
             index: row.index::<i16>().into(),
             realm_key: row.realm(),
+            visible_from: row.visible_from(),
+            visible_until: row.visible_until(),
         };
 
         match ty {
@@ -314,12 +613,32 @@ impl_from_db!(
                 show_metadata: unwrap_type_dep(row.show_metadata(), "series", "show_metadata"),
             }.into(),
 
+            BlockType::Playlist => PlaylistBlock {
+                shared,
+                playlist: row.playlist::<Option<Key>>().map(Id::playlist),
+                order: unwrap_type_dep(row.videolist_order(), "playlist", "videolist_order"),
+                layout: unwrap_type_dep(row.videolist_layout(), "playlist", "videolist_layout"),
+                show_title: unwrap_type_dep(row.show_title(), "playlist", "show_title"),
+                show_metadata: unwrap_type_dep(row.show_metadata(), "playlist", "show_metadata"),
+            }.into(),
+
             BlockType::Video => VideoBlock {
                 shared,
                 event: row.video::<Option<Key>>().map(Id::event),
                 show_title: unwrap_type_dep(row.show_title(), "event", "show_title"),
                 show_link: unwrap_type_dep(row.show_link(), "event", "show_link"),
             }.into(),
+
+            BlockType::RealmList => RealmListBlock {
+                shared,
+                realms: row.realms(),
+                layout: unwrap_type_dep(row.realmlist_layout(), "realm_list", "realmlist_layout"),
+            }.into(),
+
+            BlockType::Embed => EmbedBlock {
+                shared,
+                url: unwrap_type_dep(row.embed_url(), "embed", "embed_url"),
+            }.into(),
         }
     }
 );
@@ -337,10 +656,19 @@ fn unwrap_type_dep<T>(value: Option<T>, type_name: &str, field_name: &str) -> T
 impl BlockValue {
     /// Fetches all blocks for the given realm from the database.
     pub(crate) async fn load_for_realm(realm_key: Key, context: &Context) -> ApiResult<Vec<Self>> {
+        Self::load_for_realm_from(realm_key, BlockTable::Live, context).await
+    }
+
+    /// Like `load_for_realm`, but loads the blocks from the given table.
+    pub(crate) async fn load_for_realm_from(
+        realm_key: Key,
+        table: BlockTable,
+        context: &Context,
+    ) -> ApiResult<Vec<Self>> {
         let selection = Self::select();
         let query = format!(
             "select {selection} \
-                from blocks \
+                from {table} as blocks \
                 where realm = $1 \
                 order by index asc",
         );
@@ -365,11 +693,18 @@ impl BlockValue {
     }
 
     pub(crate) fn id(&self) -> Id {
+        self.shared_data().id
+    }
+
+    pub(crate) fn shared_data(&self) -> &SharedData {
         match self {
-            BlockValue::TitleBlock(block) => block.shared.id,
-            BlockValue::TextBlock(block) => block.shared.id,
-            BlockValue::VideoBlock(block) => block.shared.id,
-            BlockValue::SeriesBlock(block) => block.shared.id,
+            BlockValue::TitleBlock(block) => &block.shared,
+            BlockValue::TextBlock(block) => &block.shared,
+            BlockValue::VideoBlock(block) => &block.shared,
+            BlockValue::SeriesBlock(block) => &block.shared,
+            BlockValue::PlaylistBlock(block) => &block.shared,
+            BlockValue::RealmListBlock(block) => &block.shared,
+            BlockValue::EmbedBlock(block) => &block.shared,
         }
     }
 }
@@ -1,11 +1,18 @@
+use chrono::{DateTime, Utc};
+use hyper::Uri;
 use juniper::{GraphQLInputObject, GraphQLObject};
 
 use crate::{
-    api::{Context, Id, err::{ApiResult, invalid_input}, model::realm::Realm},
+    api::{
+        Context,
+        Id,
+        err::{ApiResult, invalid_input},
+        model::{realm::{BlockTable, Realm}, trash::TrashEntry},
+    },
     db::{types::Key, util::select},
     prelude::*,
 };
-use super::{BlockValue, VideoListOrder, VideoListLayout};
+use super::{BlockValue, RealmListLayout, VideoListOrder, VideoListLayout};
 
 
 impl BlockValue {
@@ -15,11 +22,11 @@ impl BlockValue {
         block: NewTitleBlock,
         context: &Context,
     ) -> ApiResult<Realm> {
-        let (realm, index) = Self::prepare_realm_for_block(realm, index, context).await?;
+        let (realm, index, table) = Self::prepare_realm_for_block(realm, index, context).await?;
         context.db
             .execute(
-                "insert into blocks (realm, index, type, text_content) \
-                    values ($1, $2, 'title', $3)",
+                &format!("insert into {table} (realm, index, type, text_content) \
+                    values ($1, $2, 'title', $3)"),
                 &[&realm.key, &index, &block.content],
             )
             .await?;
@@ -35,11 +42,11 @@ impl BlockValue {
         block: NewTextBlock,
         context: &Context,
     ) -> ApiResult<Realm> {
-        let (realm, index) = Self::prepare_realm_for_block(realm, index, context).await?;
+        let (realm, index, table) = Self::prepare_realm_for_block(realm, index, context).await?;
         context.db
             .execute(
-                "insert into blocks (realm, index, type, text_content) \
-                    values ($1, $2, 'text', $3)",
+                &format!("insert into {table} (realm, index, type, text_content) \
+                    values ($1, $2, 'text', $3)"),
                 &[&realm.key, &index, &block.content],
             )
             .await?;
@@ -53,15 +60,15 @@ impl BlockValue {
         block: NewSeriesBlock,
         context: &Context,
     ) -> ApiResult<Realm> {
-        let (realm, index) = Self::prepare_realm_for_block(realm, index, context).await?;
+        let (realm, index, table) = Self::prepare_realm_for_block(realm, index, context).await?;
         let series = block.series.key_for(Id::SERIES_KIND)
             .ok_or_else(|| invalid_input!("`block.series` does not refer to a series"))?;
 
         context.db
             .execute(
-                "insert into blocks \
+                &format!("insert into {table} \
                     (realm, index, type, series, videolist_order, videolist_layout, show_title, show_metadata) \
-                    values ($1, $2, 'series', $3, $4, $5, $6, $7)",
+                    values ($1, $2, 'series', $3, $4, $5, $6, $7)"),
                 &[&realm.key, &index, &series,
                     &block.order, &block.layout, &block.show_title, &block.show_metadata],
             )
@@ -70,20 +77,43 @@ impl BlockValue {
         Ok(realm)
     }
 
+    pub(crate) async fn add_playlist(
+        realm: Id,
+        index: i32,
+        block: NewPlaylistBlock,
+        context: &Context,
+    ) -> ApiResult<Realm> {
+        let (realm, index, table) = Self::prepare_realm_for_block(realm, index, context).await?;
+        let playlist = block.playlist.key_for(Id::PLAYLIST_KIND)
+            .ok_or_else(|| invalid_input!("`block.playlist` does not refer to a playlist"))?;
+
+        context.db
+            .execute(
+                &format!("insert into {table} \
+                    (realm, index, type, playlist, videolist_order, videolist_layout, show_title, show_metadata) \
+                    values ($1, $2, 'playlist', $3, $4, $5, $6, $7)"),
+                &[&realm.key, &index, &playlist,
+                    &block.order, &block.layout, &block.show_title, &block.show_metadata],
+            )
+            .await?;
+
+        Ok(realm)
+    }
+
     pub(crate) async fn add_video(
         realm: Id,
         index: i32,
         block: NewVideoBlock,
         context: &Context,
     ) -> ApiResult<Realm> {
-        let (realm, index) = Self::prepare_realm_for_block(realm, index, context).await?;
+        let (realm, index, table) = Self::prepare_realm_for_block(realm, index, context).await?;
         let event = block.event.key_for(Id::EVENT_KIND)
             .ok_or_else(|| invalid_input!("`block.event` does not refer to an event"))?;
 
         context.db
             .execute(
-                "insert into blocks (realm, index, type, video, show_title, show_link) \
-                    values ($1, $2, 'video', $3, $4, $5)",
+                &format!("insert into {table} (realm, index, type, video, show_title, show_link) \
+                    values ($1, $2, 'video', $3, $4, $5)"),
                 &[&realm.key, &index, &event, &block.show_title, &block.show_link],
             )
             .await?;
@@ -91,8 +121,52 @@ impl BlockValue {
         Ok(realm)
     }
 
+    pub(crate) async fn add_realm_list(
+        realm: Id,
+        index: i32,
+        block: NewRealmListBlock,
+        context: &Context,
+    ) -> ApiResult<Realm> {
+        let (realm, index, table) = Self::prepare_realm_for_block(realm, index, context).await?;
+        let realms = match block.realms {
+            None => None,
+            Some(ids) => Some(Self::listed_realm_keys(ids, "block.realms", context).await?),
+        };
+
+        context.db
+            .execute(
+                &format!("insert into {table} (realm, index, type, realms, realmlist_layout) \
+                    values ($1, $2, 'realm_list', $3, $4)"),
+                &[&realm.key, &index, &realms, &block.layout],
+            )
+            .await?;
+
+        Ok(realm)
+    }
+
+    pub(crate) async fn add_embed(
+        realm: Id,
+        index: i32,
+        block: NewEmbedBlock,
+        context: &Context,
+    ) -> ApiResult<Realm> {
+        Self::validate_embed_url(&block.url, "block.url", context)?;
+        let (realm, index, table) = Self::prepare_realm_for_block(realm, index, context).await?;
+
+        context.db
+            .execute(
+                &format!("insert into {table} (realm, index, type, embed_url) \
+                    values ($1, $2, 'embed', $3)"),
+                &[&realm.key, &index, &block.url],
+            )
+            .await?;
+
+        Ok(realm)
+    }
+
     /// Makes sure the given realm exists, the user has moderating (i.e. editing) access
     /// to it and moves blocks around such that a new one can be inserted at `index`.
+    /// If the realm has a draft, this operates on the draft blocks.
     ///
     /// For all blocks in `realm` with an index `>= index`, increase their index
     /// by `1`. This basically moves all the blocks after the `index`-th one
@@ -103,15 +177,16 @@ impl BlockValue {
         realm: Id,
         index: i32,
         context: &Context,
-    ) -> ApiResult<(Realm, i16)> {
+    ) -> ApiResult<(Realm, i16, BlockTable)> {
         let Some(realm) = Realm::load_by_id(realm, context).await? else {
             return Err(invalid_input!("`realm` does not refer to a realm"));
         };
         realm.require_moderator_rights(context)?;
+        let table = BlockTable::for_realm(realm.key, context).await?;
 
         let num_blocks: i64 = context.db
             .query_one(
-                "select count(*) from blocks where realm = $1",
+                &format!("select count(*) from {table} where realm = $1"),
                 &[&realm.key],
             )
             .await?
@@ -126,15 +201,15 @@ impl BlockValue {
 
         context.db
             .execute(
-                "update blocks \
+                &format!("update {table} \
                     set index = index + 1 \
                     where realm = $1 \
-                    and index >= $2",
+                    and index >= $2"),
                 &[&realm.key, &index],
             )
             .await?;
 
-        Ok((realm, index))
+        Ok((realm, index, table))
     }
 
     pub(crate) async fn swap_by_index(
@@ -147,6 +222,7 @@ impl BlockValue {
             return Err(invalid_input!("`realm` does not exist"));
         };
         realm.require_moderator_rights(context)?;
+        let table = BlockTable::for_realm(realm.key, context).await?;
 
         if index_a == index_b {
             return Realm::load_by_key(realm.key, context)
@@ -160,7 +236,8 @@ impl BlockValue {
         // during the execution of that statement a moment will exist
         // in which two blocks of a realm have the same index.
         // Since this violates one of our constraints, we defer it.
-        db.execute("set constraints index_unique_in_realm deferred", &[]).await?;
+        let constraint = table.index_constraint();
+        db.execute(&format!("set constraints {constraint} deferred"), &[]).await?;
 
         // This query is a bit involved, but this allows us to do the full swap in one
         // go, including "bound checking".
@@ -176,13 +253,13 @@ impl BlockValue {
         // `updates.new_index < count` and `updates.new_index >= 0` are only to make
         // sure the new index is in bounds.
         let query = format!(
-            "update blocks \
+            "update {table} as blocks \
                 set index = updates.new_index \
                 from (values \
                     ($1::smallint, $2::smallint), \
                     ($2::smallint, $1::smallint) \
                 ) as updates(old_index, new_index), ( \
-                    select count(*) as count from blocks \
+                    select count(*) as count from {table} \
                     where realm = $3 \
                 ) as count \
                 where realm = $3 \
@@ -204,7 +281,7 @@ impl BlockValue {
             .await?;
 
         // TODO Actually reset to whatever it was before, but that needs nested transactions
-        db.execute("set constraints index_unique_in_realm immediate", &[]).await?;
+        db.execute(&format!("set constraints {constraint} immediate"), &[]).await?;
 
         // We will get the block id twice for two updated rows if everything
         // goes according to plan.
@@ -220,11 +297,11 @@ impl BlockValue {
         set: UpdateTitleBlock,
         context: &Context,
     ) -> ApiResult<Self> {
-        Self::require_realm_moderator_rights(id, context).await?;
+        let (_, table) = Self::require_realm_moderator_rights(id, context).await?;
 
         let selection = Self::select();
         let query = format!(
-            "update blocks set \
+            "update {table} as blocks set \
                 text_content = coalesce($2, text_content) \
                 where id = $1 \
                 and type = 'title' \
@@ -241,11 +318,11 @@ impl BlockValue {
         set: UpdateTextBlock,
         context: &Context,
     ) -> ApiResult<Self> {
-        Self::require_realm_moderator_rights(id, context).await?;
+        let (_, table) = Self::require_realm_moderator_rights(id, context).await?;
 
         let selection = Self::select();
         let query = format!(
-            "update blocks set \
+            "update {table} as blocks set \
                 text_content = coalesce($2, text_content) \
                 where id = $1 \
                 and type = 'text' \
@@ -262,7 +339,7 @@ impl BlockValue {
         set: UpdateSeriesBlock,
         context: &Context,
     ) -> ApiResult<BlockValue> {
-        Self::require_realm_moderator_rights(id, context).await?;
+        let (_, table) = Self::require_realm_moderator_rights(id, context).await?;
 
         let series_id = set.series.map(
             |series| series.key_for(Id::SERIES_KIND)
@@ -271,7 +348,7 @@ impl BlockValue {
 
         let selection = Self::select();
         let query = format!(
-            "update blocks set \
+            "update {table} as blocks set \
                 series = coalesce($2, series), \
                 videolist_order = coalesce($3, videolist_order), \
                 videolist_layout = coalesce($4, videolist_layout), \
@@ -295,12 +372,50 @@ impl BlockValue {
             .pipe(|row| Ok(Self::from_row_start(&row)))
     }
 
+    pub(crate) async fn update_playlist(
+        id: Id,
+        set: UpdatePlaylistBlock,
+        context: &Context,
+    ) -> ApiResult<BlockValue> {
+        let (_, table) = Self::require_realm_moderator_rights(id, context).await?;
+
+        let playlist_id = set.playlist.map(
+            |playlist| playlist.key_for(Id::PLAYLIST_KIND)
+                .ok_or_else(|| invalid_input!("`set.playlist` does not refer to a playlist"))
+        ).transpose()?;
+
+        let selection = Self::select();
+        let query = format!(
+            "update {table} as blocks set \
+                playlist = coalesce($2, playlist), \
+                videolist_order = coalesce($3, videolist_order), \
+                videolist_layout = coalesce($4, videolist_layout), \
+                show_title = coalesce($5, show_title), \
+                show_metadata = coalesce($6, show_metadata) \
+                where id = $1 \
+                and type = 'playlist' \
+                returning {selection}",
+        );
+        let args = [
+            (&Self::key_for(id)?) as &(dyn postgres_types::ToSql + Sync),
+            &playlist_id,
+            &set.order,
+            &set.layout,
+            &set.show_title,
+            &set.show_metadata,
+        ];
+        context.db
+            .query_one(&query, &args)
+            .await?
+            .pipe(|row| Ok(Self::from_row_start(&row)))
+    }
+
     pub(crate) async fn update_video(
         id: Id,
         set: UpdateVideoBlock,
         context: &Context,
     ) -> ApiResult<BlockValue> {
-        Self::require_realm_moderator_rights(id, context).await?;
+        let (_, table) = Self::require_realm_moderator_rights(id, context).await?;
 
         let video_id = set.event.map(
             |series| series.key_for(Id::EVENT_KIND)
@@ -309,7 +424,7 @@ impl BlockValue {
 
         let selection = Self::select();
         let query = format!(
-            "update blocks set \
+            "update {table} as blocks set \
                 video = coalesce($2, video), \
                 show_title = coalesce($3, show_title), \
                 show_link = coalesce($4, show_link) \
@@ -323,14 +438,120 @@ impl BlockValue {
             .pipe(|row| Ok(Self::from_row_start(&row)))
     }
 
+    pub(crate) async fn update_realm_list(
+        id: Id,
+        set: UpdateRealmListBlock,
+        context: &Context,
+    ) -> ApiResult<BlockValue> {
+        let (_, table) = Self::require_realm_moderator_rights(id, context).await?;
+
+        // `None` if the listed realms are not changed, `Some(None)` if all
+        // children should be listed.
+        let realms = match (set.all_children, set.realms) {
+            (None, None) => None,
+            (Some(true), None) => Some(None),
+            (None | Some(false), Some(ids)) => {
+                Some(Some(Self::listed_realm_keys(ids, "set.realms", context).await?))
+            }
+            (Some(true), Some(_)) => return Err(invalid_input!(
+                "`set.realms` must not be specified when `set.allChildren` is true"
+            )),
+            (Some(false), None) => return Err(invalid_input!(
+                "`set.realms` has to be specified when `set.allChildren` is false"
+            )),
+        };
+
+        let selection = Self::select();
+        let query = format!(
+            "update {table} as blocks set \
+                realms = case when $2 then $3 else realms end, \
+                realmlist_layout = coalesce($4, realmlist_layout) \
+                where id = $1 \
+                and type = 'realm_list' \
+                returning {selection}",
+        );
+        let args = [
+            (&Self::key_for(id)?) as &(dyn postgres_types::ToSql + Sync),
+            &realms.is_some(),
+            &realms.flatten(),
+            &set.layout,
+        ];
+        context.db
+            .query_one(&query, &args)
+            .await?
+            .pipe(|row| Ok(Self::from_row_start(&row)))
+    }
+
+    pub(crate) async fn update_embed(
+        id: Id,
+        set: UpdateEmbedBlock,
+        context: &Context,
+    ) -> ApiResult<BlockValue> {
+        let (_, table) = Self::require_realm_moderator_rights(id, context).await?;
+        if let Some(url) = &set.url {
+            Self::validate_embed_url(url, "set.url", context)?;
+        }
+
+        let selection = Self::select();
+        let query = format!(
+            "update {table} as blocks set \
+                embed_url = coalesce($2, embed_url) \
+                where id = $1 \
+                and type = 'embed' \
+                returning {selection}",
+        );
+        context.db
+            .query_one(&query, &[&Self::key_for(id)?, &set.url])
+            .await?
+            .pipe(|row| Ok(Self::from_row_start(&row)))
+    }
+
+    /// Sets the visibility window of the given block. Unlike the other
+    /// updates, `null` values are not ignored but remove the respective bound.
+    pub(crate) async fn update_visibility(
+        id: Id,
+        visible_from: Option<DateTime<Utc>>,
+        visible_until: Option<DateTime<Utc>>,
+        context: &Context,
+    ) -> ApiResult<BlockValue> {
+        let (_, table) = Self::require_realm_moderator_rights(id, context).await?;
+
+        if let (Some(from), Some(until)) = (visible_from, visible_until) {
+            if from >= until {
+                return Err(invalid_input!(
+                    key = "block.empty-visibility-window",
+                    "`visibleFrom` has to be before `visibleUntil`",
+                ));
+            }
+        }
+
+        let selection = Self::select();
+        let query = format!(
+            "update {table} as blocks set \
+                visible_from = $2, \
+                visible_until = $3 \
+                where id = $1 \
+                returning {selection}",
+        );
+        context.db
+            .query_one(&query, &[&Self::key_for(id)?, &visible_from, &visible_until])
+            .await?
+            .pipe(|row| Ok(Self::from_row_start(&row)))
+    }
+
     pub(crate) async fn remove(id: Id, context: &Context) -> ApiResult<RemovedBlock> {
-        let realm = Self::require_realm_moderator_rights(id, context).await?;
+        let (realm, table) = Self::require_realm_moderator_rights(id, context).await?;
         let db = &context.db;
         let block_id = id.key_for(Id::BLOCK_KIND)
             .ok_or_else(|| invalid_input!("`id` does not refer to a block"))?;
 
+        // Blocks removed from a draft are only moved to the trash once the
+        // draft is published.
+        if table == BlockTable::Live {
+            TrashEntry::add_block(block_id, context).await?;
+        }
         let (selection, mapping) = select!(index);
-        let query = format!("delete from blocks where id = $1 returning {selection}");
+        let query = format!("delete from {table} where id = $1 returning {selection}");
         let result = db.query_one(&query, &[&block_id]).await?;
 
         let index: i16 = mapping.index.of(&result);
@@ -338,10 +559,10 @@ impl BlockValue {
         // Fix indices after removed block
         db
             .execute(
-                "update blocks \
+                &format!("update {table} \
                     set index = index - 1 \
                     where realm = $1 \
-                    and index > $2",
+                    and index > $2"),
                 &[&realm.key, &index],
             )
             .await?;
@@ -350,29 +571,145 @@ impl BlockValue {
         Ok(RemovedBlock { id, realm })
     }
 
+    /// Restores a block from the trash, at its previous index (or at the end
+    /// if the realm has fewer blocks now).
+    pub(crate) async fn restore(id: Id, context: &Context) -> ApiResult<Realm> {
+        let Some(entry) = TrashEntry::load_for_block_item(Self::key_for(id)?, context).await? else {
+            return Err(invalid_input!(
+                key = "trash.not-found",
+                "`id` does not refer to a block in the trash",
+            ));
+        };
+        let Some(realm_key) = entry.parent else {
+            return Err(invalid_input!("block in trash has no realm"));
+        };
+        match Realm::load_by_key(realm_key, context).await? {
+            Some(realm) => realm.require_admin_rights(context)?,
+            None => return Err(invalid_input!(
+                key = "trash.realm-removed",
+                "the realm of the block was removed: restore it first",
+            )),
+        }
+        if BlockTable::for_realm(realm_key, context).await? == BlockTable::Draft {
+            return Err(invalid_input!(
+                key = "realm.draft-active",
+                "blocks cannot be restored while the realm has a draft",
+            ));
+        }
+
+        let num_blocks: i64 = context.db
+            .query_one("select count(*) from blocks where realm = $1", &[&realm_key])
+            .await?
+            .get(0);
+        let index = entry.data.get("index")
+            .and_then(|index| index.as_i64())
+            .unwrap_or(num_blocks)
+            .min(num_blocks);
+        let (realm, index, _) = Self::prepare_realm_for_block(
+            Id::realm(realm_key),
+            index as i32,
+            context,
+        ).await?;
+
+        context.db
+            .execute(
+                "select restore_blocks(jsonb_build_array(\
+                    $1::jsonb || jsonb_build_object('index', $2::smallint)\
+                ))",
+                &[&entry.data, &index],
+            )
+            .await?;
+        entry.remove(context).await?;
+        info!(%id, realm.path = realm.full_path, "Restored block");
+
+        Ok(realm)
+    }
+
+    /// Converts the realms selected for a realm list block to keys, making sure
+    /// all of them exist.
+    async fn listed_realm_keys(
+        ids: Vec<Id>,
+        field: &str,
+        context: &Context,
+    ) -> ApiResult<Vec<Key>> {
+        let keys = ids.into_iter()
+            .map(|id| id.key_for(Id::REALM_KIND).ok_or_else(|| invalid_input!(
+                "`{field}` contains an ID that does not refer to a realm",
+            )))
+            .collect::<ApiResult<Vec<_>>>()?;
+
+        let all_exist = context.db
+            .query_one(
+                "select count(*) = cardinality($1) from realms where id = any($1)",
+                &[&keys],
+            )
+            .await?
+            .get::<_, bool>(0);
+        if !all_exist {
+            return Err(invalid_input!(
+                "`{field}` contains duplicates or realms that do not exist",
+            ));
+        }
+
+        Ok(keys)
+    }
+
+    /// Makes sure the given URL of an embed block belongs to one of the
+    /// configured `general.embed_origins`.
+    fn validate_embed_url(url: &str, field: &str, context: &Context) -> ApiResult<()> {
+        let uri = url.parse::<Uri>()
+            .map_err(|_| invalid_input!("`{field}` is not a valid URL"))?;
+        if !context.config.general.is_embed_allowed(&uri) {
+            return Err(invalid_input!(
+                key = "block.embed-origin-not-allowed",
+                "`{field}` does not belong to an allowed origin (see `general.embed_origins`)",
+            ));
+        }
+        Ok(())
+    }
+
     fn key_for(id: Id) -> ApiResult<Key> {
         id.key_for(Id::BLOCK_KIND)
             .ok_or_else(|| invalid_input!("`id` does not refer to a block"))
     }
 
     /// Loads the realm associated with the given block and makes sure the user
-    /// has moderating/editing access.
-    async fn require_realm_moderator_rights(block_id: Id, context: &Context) -> ApiResult<Realm> {
+    /// has moderating/editing access. Also returns the table that mutations of
+    /// the block have to operate on.
+    async fn require_realm_moderator_rights(
+        block_id: Id,
+        context: &Context,
+    ) -> ApiResult<(Realm, BlockTable)> {
         let key = block_id.key_for(Id::BLOCK_KIND).ok_or_else(|| {
             invalid_input!("id does not refer to a block")
         })?;
 
+        // Blocks added to a draft only exist in `draft_blocks`.
         let selection = Realm::select();
         let query = format!("select {selection} from realms \
-            where realms.id = (select realm from blocks where id = $1)");
+            where realms.id = coalesce(\
+                (select realm from draft_blocks where id = $1), \
+                (select realm from blocks where id = $1)\
+            )");
         let realm = context.db
             .query_opt(&query, &[&key])
             .await?
             .map(|row| Realm::from_row_start(&row))
             .ok_or_else(|| invalid_input!("no block with the given ID exists"))?;
         realm.require_moderator_rights(context)?;
+        let table = BlockTable::for_realm(realm.key, context).await?;
 
-        Ok(realm)
+        // While a draft exists, blocks removed from it are still live, but
+        // cannot be changed anymore.
+        let exists = context.db
+            .query_one(&format!("select exists(select from {table} where id = $1)"), &[&key])
+            .await?
+            .get::<_, bool>(0);
+        if !exists {
+            return Err(invalid_input!("block was removed from the draft of its realm"));
+        }
+
+        Ok((realm, table))
     }
 }
 
@@ -396,6 +733,15 @@ pub(crate) struct NewSeriesBlock {
     pub(crate) layout: VideoListLayout,
 }
 
+#[derive(GraphQLInputObject)]
+pub(crate) struct NewPlaylistBlock {
+    playlist: Id,
+    show_title: bool,
+    show_metadata: bool,
+    order: VideoListOrder,
+    layout: VideoListLayout,
+}
+
 #[derive(GraphQLInputObject)]
 pub(crate) struct NewVideoBlock {
     event: Id,
@@ -403,6 +749,21 @@ pub(crate) struct NewVideoBlock {
     show_link: bool,
 }
 
+#[derive(GraphQLInputObject)]
+pub(crate) struct NewRealmListBlock {
+    /// The realms to list, in order. If `null`, all children of the block's
+    /// realm are listed.
+    realms: Option<Vec<Id>>,
+    layout: RealmListLayout,
+}
+
+#[derive(GraphQLInputObject)]
+pub(crate) struct NewEmbedBlock {
+    /// URL of the content to embed. Has to belong to one of the configured
+    /// `general.embed_origins`.
+    url: String,
+}
+
 
 #[derive(GraphQLInputObject)]
 pub(crate) struct UpdateTitleBlock {
@@ -423,6 +784,15 @@ pub(crate) struct UpdateSeriesBlock {
     layout: Option<VideoListLayout>,
 }
 
+#[derive(GraphQLInputObject)]
+pub(crate) struct UpdatePlaylistBlock {
+    playlist: Option<Id>,
+    show_title: Option<bool>,
+    show_metadata: Option<bool>,
+    order: Option<VideoListOrder>,
+    layout: Option<VideoListLayout>,
+}
+
 #[derive(GraphQLInputObject)]
 pub(crate) struct UpdateVideoBlock {
     event: Option<Id>,
@@ -430,6 +800,21 @@ pub(crate) struct UpdateVideoBlock {
     show_link: Option<bool>,
 }
 
+#[derive(GraphQLInputObject)]
+pub(crate) struct UpdateRealmListBlock {
+    /// Selects the realms to list, in order.
+    realms: Option<Vec<Id>>,
+    /// Set to `true` to list all children of the block's realm instead of
+    /// selected realms. Cannot be combined with `realms`.
+    all_children: Option<bool>,
+    layout: Option<RealmListLayout>,
+}
+
+#[derive(GraphQLInputObject)]
+pub(crate) struct UpdateEmbedBlock {
+    url: Option<String>,
+}
+
 
 #[derive(GraphQLObject)]
 #[graphql(Context = Context)]
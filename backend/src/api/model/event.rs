@@ -9,15 +9,23 @@ use juniper::{GraphQLObject, graphql_object};
 use crate::{
     api::{
         Context, Cursor, Id, Node, NodeValue,
-        common::NotAllowed,
+        common::{load_connection, NotAllowed, PageCursor, PageInfo, Paginated, SortDirection},
         err::{self, ApiResult, invalid_input},
-        model::{series::Series, realm::Realm, acl::{Acl, self}},
+        model::{
+            series::Series,
+            realm::Realm,
+            acl::{self, Acl, AclInputEntry, NewAcl},
+            block::VideoListOrder,
+        },
     },
     db::{
-        types::{EventTrack, EventState, Key, ExtraMetadata, EventCaption},
-        util::{impl_from_db, select},
+        types::{
+            EventTrack, EventState, Key, ExtraMetadata, EventCaption, EventSegment, EventChapter,
+        },
+        util::impl_from_db,
     },
     prelude::*,
+    sync::DEFAULT_SOURCE,
     util::lazy_format,
 };
 
@@ -35,9 +43,12 @@ pub(crate) struct AuthorizedEvent {
     creators: Vec<String>,
 
     metadata: ExtraMetadata,
-    read_roles: Vec<String>,
+    pub(crate) read_roles: Vec<String>,
     write_roles: Vec<String>,
 
+    sync_source: String,
+    pending_write_since: Option<DateTime<Utc>>,
+    deletion_pending_since: Option<DateTime<Utc>>,
     synced_data: Option<SyncedEventData>,
 }
 
@@ -52,6 +63,8 @@ pub(crate) struct SyncedEventData {
     tracks: Vec<Track>,
     thumbnail: Option<String>,
     captions: Vec<Caption>,
+    segments: Vec<Segment>,
+    chapters: Vec<Chapter>,
 }
 
 impl_from_db!(
@@ -61,8 +74,9 @@ impl_from_db!(
             id, state, series, opencast_id, is_live,
             title, description, duration, creators, thumbnail, metadata,
             created, updated, start_time, end_time,
-            tracks, captions,
-            read_roles, write_roles,
+            tracks, captions, segments, chapters,
+            read_roles, write_roles, sync_source, pending_write_since,
+            deletion_pending_since,
         },
     },
     |row| {
@@ -78,6 +92,9 @@ impl_from_db!(
             metadata: row.metadata(),
             read_roles: row.read_roles::<Vec<String>>(),
             write_roles: row.write_roles::<Vec<String>>(),
+            sync_source: row.sync_source(),
+            pending_write_since: row.pending_write_since(),
+            deletion_pending_since: row.deletion_pending_since(),
             synced_data: match row.state::<EventState>() {
                 EventState::Ready => Some(SyncedEventData {
                     updated: row.updated(),
@@ -90,6 +107,14 @@ impl_from_db!(
                         .into_iter()
                         .map(Caption::from)
                         .collect(),
+                    segments: row.segments::<Vec<EventSegment>>()
+                        .into_iter()
+                        .map(Segment::from)
+                        .collect(),
+                    chapters: row.chapters::<Vec<EventChapter>>()
+                        .into_iter()
+                        .map(Chapter::from)
+                        .collect(),
                 }),
                 EventState::Waiting => None,
             },
@@ -115,6 +140,53 @@ pub(crate) struct Caption {
     lang: Option<String>,
 }
 
+#[derive(Debug)]
+pub(crate) struct Segment {
+    start_time: i64,
+    duration: i64,
+    thumbnail: Option<String>,
+    text: Option<String>,
+}
+
+/// A segment of an event, e.g. the time one slide is shown.
+#[graphql_object(Context = Context)]
+impl Segment {
+    /// Start of the segment in ms.
+    fn start_time(&self) -> f64 {
+        self.start_time as f64
+    }
+    /// Duration of the segment in ms.
+    fn duration(&self) -> f64 {
+        self.duration as f64
+    }
+    /// URI of a preview image of this segment.
+    fn thumbnail(&self) -> Option<&str> {
+        self.thumbnail.as_deref()
+    }
+    /// Text detected in this segment, e.g. the text on the slide.
+    fn text(&self) -> Option<&str> {
+        self.text.as_deref()
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Chapter {
+    start_time: i64,
+    title: String,
+}
+
+/// A chapter of an event.
+#[graphql_object(Context = Context)]
+impl Chapter {
+    /// Start of the chapter in ms.
+    fn start_time(&self) -> f64 {
+        self.start_time as f64
+    }
+    fn title(&self) -> &str {
+        &self.title
+    }
+}
+
 impl Node for AuthorizedEvent {
     fn id(&self) -> Id {
         Id::event(self.key)
@@ -145,6 +217,14 @@ impl SyncedEventData {
     fn captions(&self) -> &[Caption] {
         &self.captions
     }
+    /// Segments of this event, ordered by start time.
+    fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+    /// Chapters of this event, ordered by start time.
+    fn chapters(&self) -> &[Chapter] {
+        &self.chapters
+    }
 }
 
 #[graphql_object(Context = Context, impl = NodeValue)]
@@ -191,6 +271,20 @@ impl AuthorizedEvent {
         context.auth.overlaps_roles(&self.write_roles)
     }
 
+    /// When changes to this event were sent to Opencast, if the harvest has
+    /// not confirmed them yet. `null` if there are no pending changes.
+    fn pending_write_since(&self) -> Option<DateTime<Utc>> {
+        self.pending_write_since
+    }
+
+    /// When the deletion of this event was requested in Tobira. The event is
+    /// removed once Opencast reports it as deleted, or shown again if that
+    /// does not happen within `sync.deletion_timeout`. `null` if no deletion
+    /// is pending.
+    fn deletion_pending_since(&self) -> Option<DateTime<Utc>> {
+        self.deletion_pending_since
+    }
+
     async fn series(&self, context: &Context) -> ApiResult<Option<Series>> {
         if let Some(series) = self.series {
             Ok(Series::load_by_key(series, context).await?)
@@ -255,23 +349,28 @@ impl AuthorizedEvent {
     pub(crate) async fn load_by_id(id: Id, context: &Context) -> ApiResult<Option<Event>> {
         match id.key_for(Id::EVENT_KIND) {
             None => return Ok(None),
-            Some(key) => Self::load_by_any_id_impl("id", &key, context).await,
+            Some(key) => Self::load_by_any_id_impl("id = $1", &[&key], context).await,
         }
     }
 
-    pub(crate) async fn load_by_opencast_id(oc_id: String, context: &Context) -> ApiResult<Option<Event>> {
-        Self::load_by_any_id_impl("opencast_id", &oc_id, context).await
+    pub(crate) async fn load_by_opencast_id(
+        oc_id: String,
+        sync_source: &str,
+        context: &Context,
+    ) -> ApiResult<Option<Event>> {
+        let cond = "sync_source = $1 and opencast_id = $2";
+        Self::load_by_any_id_impl(cond, &[&sync_source, &oc_id], context).await
     }
 
     pub(crate) async fn load_by_any_id_impl(
-        col: &str,
-        id: &(dyn ToSql + Sync),
+        cond: &str,
+        args: &[&(dyn ToSql + Sync)],
         context: &Context,
     ) -> ApiResult<Option<Event>> {
         let selection = Self::select();
-        let query = format!("select {selection} from events where {col} = $1");
+        let query = format!("select {selection} from events where {cond}");
         context.db
-            .query_opt(&query, &[id])
+            .query_opt(&query, args)
             .await?
             .map(|row| {
                 let event = Self::from_row_start(&row);
@@ -292,7 +391,8 @@ impl AuthorizedEvent {
         let selection = Self::select();
         let query = format!(
             "select {selection} from events \
-                where series = $2 and (read_roles || 'ROLE_ADMIN'::text) && $1 {}",
+                where series = $2 and deletion_pending_since is null \
+                and (read_roles || 'ROLE_ADMIN'::text) && $1 {}",
             order.to_sql(),
         );
         context.db
@@ -305,7 +405,177 @@ impl AuthorizedEvent {
             .pipe(Ok)
     }
 
-    pub(crate) async fn load_writable_for_user(
+    /// Loads the event with the given ID for changing it in Opencast. Fails if
+    /// the event does not exist, the user has no write access or the event
+    /// cannot be changed (yet).
+    async fn load_for_opencast_write(id: Id, context: &Context) -> ApiResult<Self> {
+        let event = Self::load_by_id(id, context)
+            .await?
+            .ok_or_else(|| invalid_input!("`id` does not refer to an existing event"))?
+            .into_result()?;
+
+        if !context.auth.overlaps_roles(&event.write_roles) {
+            return Err(context.access_error("event.no-write-access", |user| format!(
+                "write access for event '{}' required, but '{user}' is ineligible",
+                event.opencast_id,
+            )));
+        }
+        if event.synced_data.is_none() {
+            return Err(invalid_input!("event '{}' is not synced yet", event.opencast_id));
+        }
+        if event.is_deletion_pending() {
+            return Err(invalid_input!("event '{}' is being deleted", event.opencast_id));
+        }
+        if event.sync_source != DEFAULT_SOURCE {
+            return Err(invalid_input!(
+                "event '{}' is synced from '{}', but only events of the default \
+                    Opencast can be changed",
+                event.opencast_id,
+                event.sync_source,
+            ));
+        }
+
+        Ok(event)
+    }
+
+    /// Changes the metadata of an event in Opencast. The changes are applied
+    /// to our DB immediately and the event is marked as having a pending
+    /// write until the harvest returns a version of it that includes the
+    /// changes. To get there, the configured republish workflow is started.
+    pub(crate) async fn update_metadata(
+        id: Id,
+        metadata: EventMetadataUpdate,
+        context: &Context,
+    ) -> ApiResult<Self> {
+        let event = Self::load_for_opencast_write(id, context).await?;
+
+        let mut fields: Vec<(&str, serde_json::Value)> = vec![];
+        if let Some(title) = &metadata.title {
+            if title.trim().is_empty() {
+                return Err(invalid_input!("title must not be empty"));
+            }
+            fields.push(("title", title.as_str().into()));
+        }
+        if let Some(description) = &metadata.description {
+            fields.push(("description", description.as_str().into()));
+        }
+        if fields.is_empty() {
+            return Err(invalid_input!("no metadata fields to change were given"));
+        }
+
+        context.oc_client.update_event_metadata(&event.opencast_id, &fields)
+            .await
+            .map_err(|e| opencast_error(&event.opencast_id, e))?;
+
+        event.start_republish_workflow(context).await;
+
+        let selection = Self::select();
+        let query = format!("update events set \
+                title = coalesce($2, title), \
+                description = case when $3::text is null then description else nullif($3, '') end, \
+                pending_write_since = now(), \
+                updated_before_pending_write = $4 \
+            where id = $1 \
+            returning {selection}");
+        let args = dbargs![
+            &event.key,
+            &metadata.title,
+            &metadata.description,
+            &event.updated_before_write(),
+        ];
+        context.db
+            .query_one(&query, &args)
+            .await?
+            .pipe(|row| Self::from_row_start(&row))
+            .pipe(Ok)
+    }
+
+    /// Changes the ACL of an event in Opencast. Like with `update_metadata`,
+    /// the new ACL is applied to our DB immediately and the event is
+    /// republished.
+    pub(crate) async fn update_acl(
+        id: Id,
+        acl: Vec<AclInputEntry>,
+        context: &Context,
+    ) -> ApiResult<Self> {
+        let event = Self::load_for_opencast_write(id, context).await?;
+        let acl = NewAcl::from_input(acl, context)?;
+
+        context.oc_client.update_event_acl(&event.opencast_id, &acl.opencast_entries())
+            .await
+            .map_err(|e| opencast_error(&event.opencast_id, e))?;
+        event.start_republish_workflow(context).await;
+
+        let selection = Self::select();
+        let query = format!("update events set \
+                read_roles = $2, \
+                write_roles = $3, \
+                custom_action_roles = $4, \
+                pending_write_since = now(), \
+                updated_before_pending_write = $5 \
+            where id = $1 \
+            returning {selection}");
+        let args = dbargs![
+            &event.key,
+            &acl.read_roles,
+            &acl.write_roles,
+            &acl.custom_actions,
+            &event.updated_before_write(),
+        ];
+        context.db
+            .query_one(&query, &args)
+            .await?
+            .pipe(|row| Self::from_row_start(&row))
+            .pipe(Ok)
+    }
+
+    /// The `updated` timestamp of this event as loaded before writing to
+    /// Opencast. Only versions harvested later that are newer than this
+    /// include the write (see `check_pending_write` in the migrations).
+    fn updated_before_write(&self) -> Option<DateTime<Utc>> {
+        self.synced_data.as_ref().map(|data| data.updated)
+    }
+
+    /// Starts the configured republish workflow on this event, which is
+    /// required for changes sent to Opencast to show up in the harvest. The
+    /// changes are already applied in Opencast when this is called, so
+    /// failing the mutation would be wrong. Without a republish, the event
+    /// just stays marked as pending until it is republished some other way.
+    async fn start_republish_workflow(&self, context: &Context) {
+        let workflow = &context.config.sync.republish_workflow;
+        if let Err(e) = context.oc_client.start_workflow(&self.opencast_id, workflow).await {
+            warn!("Failed to start workflow '{workflow}' on event '{}': {e:#}", self.opencast_id);
+        }
+    }
+
+    /// Deletes an event in Opencast. Our DB row is only removed once the
+    /// harvest reports the event as deleted. Until then, the event is marked
+    /// as pending deletion and hidden from blocks and the search.
+    pub(crate) async fn delete(id: Id, context: &Context) -> ApiResult<Self> {
+        let event = Self::load_for_opencast_write(id, context).await?;
+
+        context.oc_client.delete_event(&event.opencast_id)
+            .await
+            .map_err(|e| opencast_error(&event.opencast_id, e))?;
+
+        let selection = Self::select();
+        let query = format!("update events set deletion_pending_since = now() \
+            where id = $1 \
+            returning {selection}");
+        context.db
+            .query_one(&query, &[&event.key])
+            .await?
+            .pipe(|row| Self::from_row_start(&row))
+            .pipe(Ok)
+    }
+
+    pub(crate) fn is_deletion_pending(&self) -> bool {
+        self.deletion_pending_since.is_some()
+    }
+
+    /// Like `load_for_series`, but paginated.
+    pub(crate) async fn load_connection_for_series(
+        series_key: Key,
         context: &Context,
         order: EventSortOrder,
         first: Option<i32>,
@@ -313,177 +583,130 @@ impl AuthorizedEvent {
         last: Option<i32>,
         before: Option<Cursor>,
     ) -> ApiResult<EventConnection> {
-        const MAX_COUNT: i32 = 100;
+        let roles = context.auth.roles_vec();
+        let base_filter = "series = $1 and deletion_pending_since is null \
+            and (read_roles || 'ROLE_ADMIN'::text) && $2";
+        let base_args: [&(dyn ToSql + Sync); 2] = [&series_key, &roles];
+        let base = (base_filter, &base_args[..]);
+        Self::load_connection(context, base, order, first, after, last, before).await
+    }
 
-        // Argument validation
-        let after = after.map(|c| c.deserialize::<EventCursor>()).transpose()?;
-        let before = before.map(|c| c.deserialize::<EventCursor>()).transpose()?;
-        if first.map_or(false, |first| first <= 0) {
-            return Err(invalid_input!("argument 'first' has to be > 0, but is {:?}", first));
+    pub(crate) async fn load_writable_for_user(
+        context: &Context,
+        order: EventSortOrder,
+        filter: EventFilter,
+        first: Option<i32>,
+        after: Option<Cursor>,
+        last: Option<i32>,
+        before: Option<Cursor>,
+    ) -> ApiResult<EventConnection> {
+        let roles = context.auth.roles_vec();
+        let title_pattern = filter.title.as_deref().map(|title| {
+            let escaped = title.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+            format!("%{escaped}%")
+        });
+        let series_key = filter.series
+            .map(|id| id.key_for(Id::SERIES_KIND)
+                .ok_or_else(|| invalid_input!("'series' filter is not a valid series ID")))
+            .transpose()?;
+
+        // All conditions are applied in the inner query of `load_connection`,
+        // so that row numbers and the total count only consider matching
+        // events.
+        let mut conditions = vec![];
+        let mut args: Vec<&(dyn ToSql + Sync)> = vec![];
+        if !context.auth.is_admin() {
+            args.push(&roles);
+            conditions.push(format!("write_roles && ${0} and read_roles && ${0}", args.len()));
         }
-        if last.map_or(false, |last| last <= 0) {
-            return Err(invalid_input!("argument 'last' has to be > 0, but is {:?}", last));
+        if let Some(pattern) = &title_pattern {
+            args.push(pattern);
+            conditions.push(format!("title ilike ${}", args.len()));
+        }
+        if let Some(series_key) = &series_key {
+            args.push(series_key);
+            conditions.push(format!("series = ${}", args.len()));
+        }
+        if let Some(created_after) = &filter.created_after {
+            args.push(created_after);
+            conditions.push(format!("created >= ${}", args.len()));
+        }
+        if let Some(created_before) = &filter.created_before {
+            args.push(created_before);
+            conditions.push(format!("created < ${}", args.len()));
+        }
+        if let Some(is_live) = &filter.is_live {
+            args.push(is_live);
+            conditions.push(format!("is_live = ${}", args.len()));
+        }
+        match filter.is_waiting {
+            Some(true) => conditions.push("state = 'waiting'".into()),
+            Some(false) => conditions.push("state <> 'waiting'".into()),
+            None => {}
         }
 
-        // Make sure only one of `first` and `last` is set and figure out the
-        // limit and SQL sort order. If `last` is set, we reverse the order in
-        // the SQL query in order to use `limit` effectively. We reverse it
-        // again in Rust further below.
-        let (limit, sql_sort_order) = match (first, last) {
-            (Some(first), None) => (first, order.direction),
-            (None, Some(last)) => (last, order.direction.reversed()),
-            _ => return Err(invalid_input!("exactly one of 'first' and 'last' must be given")),
-        };
-        let limit = std::cmp::min(limit, MAX_COUNT);
-
-
-        // Assemble argument list and the "where" part of the query. This
-        // depends on `after` and `before`.
-        let mut args = vec![];
-        let col = order.column.to_sql();
-        let op_after = if order.direction.is_ascending() { '>' } else { '<' };
-        let op_before = if order.direction.is_ascending() { '<' } else { '>' };
-        let filter = match (&after, &before) {
-            (None, None) => String::new(),
-            (Some(after), None) => {
-                args.extend_from_slice(&[after.to_sql_arg(&order)?, &after.key]);
-                format!("where ({}, id) {} ($1, $2)", col, op_after)
-            }
-            (None, Some(before)) => {
-                args.extend_from_slice(&[before.to_sql_arg(&order)?, &before.key]);
-                format!("where ({}, id) {} ($1, $2)", col, op_before)
-            }
-            (Some(after), Some(before)) => {
-                args.extend_from_slice(&[
-                    after.to_sql_arg(&order)?,
-                    &after.key,
-                    before.to_sql_arg(&order)?,
-                    &before.key,
-                ]);
-                format!(
-                    "where ({}, id) {} ($1, $2) and ({}, id) {} ($3, $4)",
-                    col, op_after, col, op_before,
-                )
-            },
-        };
-
-        // Assemble full query. This query is a bit involved but allows us to
-        // retrieve the total count, the absolute offsets of our window and all
-        // the event data in one go. The "over(...)" things are window
-        // functions.
-        let arg_user_roles = &context.auth.roles_vec() as &(dyn ToSql + Sync);
-        let acl_filter = if context.auth.is_admin() {
-            String::new()
+        let base_filter = if conditions.is_empty() {
+            "true".to_owned()
         } else {
-            args.push(arg_user_roles);
-            let arg_index = args.len();
-
-            format!("where write_roles && ${arg_index} and read_roles && ${arg_index}")
+            conditions.join(" and ")
         };
-        let (selection, mapping) = select!(
-            event: AuthorizedEvent from
-                AuthorizedEvent::select().with_omitted_table_prefix("events"),
-            row_num,
-            total_count,
-        );
-        let query = format!(
-            "select {selection} \
-                from (\
-                    select {event_cols}, \
-                        row_number() over(order by ({sort_col}, id) {sort_order}) as row_num, \
-                        count(*) over() as total_count \
-                    from events \
-                    {acl_filter} \
-                    order by ({sort_col}, id) {sort_order} \
-                ) as tmp \
-                {filter} \
-                limit {limit}",
-            event_cols = Self::select().with_omitted_table_prefix("events"),
-            sort_col = order.column.to_sql(),
-            sort_order = sql_sort_order.to_sql(),
-            limit = limit,
-            acl_filter = acl_filter,
-            filter = filter,
-        );
-
-        // `first_num` and `last_num` are 1-based!
-        let mut total_count = None;
-        let mut first_num = None;
-        let mut last_num = None;
+        let base = (base_filter.as_str(), &args[..]);
+        Self::load_connection(context, base, order, first, after, last, before).await
+    }
 
-        // Execute query
-        let mut events = context.db.query_mapped(&query, args, |row: Row| {
-            // Retrieve total count once
-            if total_count.is_none() {
-                total_count = Some(mapping.total_count.of(&row));
-            }
+    /// Loads one page of all events matching the SQL condition `base.0`,
+    /// which can refer to the arguments `base.1` via `$1`, `$2`, ...
+    async fn load_connection(
+        context: &Context,
+        base: (&str, &[&(dyn ToSql + Sync)]),
+        order: EventSortOrder,
+        first: Option<i32>,
+        after: Option<Cursor>,
+        last: Option<i32>,
+        before: Option<Cursor>,
+    ) -> ApiResult<EventConnection> {
+        let page = load_connection::<Self>(context, base, order, first, after, last, before)
+            .await?;
+        Ok(EventConnection {
+            page_info: page.page_info.into(),
+            items: page.items,
+            total_count: page.total_count,
+        })
+    }
+}
 
-            // Handle row numbers
-            let row_num = mapping.row_num.of(&row);
-            last_num = Some(row_num);
-            if first_num.is_none() {
-                first_num = Some(row_num);
-            }
+impl Paginated for AuthorizedEvent {
+    type Order = EventSortOrder;
+    type Cursor = EventCursor;
 
-            // Retrieve actual event data
-            Self::from_row(&row, mapping.event)
-        }).await?;
-
-        // If total count is `None`, there are no events. We really do want to
-        // know the total count, so we do another query.
-        let total_count = match total_count {
-            Some(c) => c,
-            None => {
-                let query = format!("select count(*) from events {}", acl_filter);
-                context.db
-                    .query_one(&query, &[&context.auth.roles_vec()])
-                    .await?
-                    .get::<_, i64>(0)
-            }
-        };
+    const FROM: &'static str = "events";
 
-        // If `last` was given, we had to query in reverse order to make `limit`
-        // work. So now we need to reverse the result here. We also need to
-        // adjust the last and first "num".
-        if sql_sort_order != order.direction {
-            events.reverse();
-            let tmp = first_num;
-            first_num = last_num.map(|n| total_count - n + 1);
-            last_num = tmp.map(|n| total_count - n + 1);
-        }
+    fn page_columns() -> String {
+        Self::select().with_omitted_table_prefix("events").to_string()
+    }
 
-        // Figure out whether there is a next and/or previous page.
-        let (has_next_page, has_previous_page) = match Option::zip(first_num, last_num) {
-            Some((first, last)) => (last < total_count, first > 1),
-            None => {
-                // The DB returned 0 events. That means there are either actually 0 writable
-                // events for that user, or all of them were filtered by `after` or `before`.
-                if total_count == 0 {
-                    (false, false)
-                } else if after.is_some() {
-                    (false, true)
-                } else {
-                    (true, false)
-                }
-            }
-        };
+    fn sort_sql(order: &EventSortOrder) -> (&'static str, SortDirection) {
+        (order.column.to_sql(), order.direction)
+    }
 
-        let cast_i32 = |x: i64| x.try_into().expect("more then 2^31 events");
-        Ok(EventConnection {
-            total_count: cast_i32(total_count),
-            page_info: EventPageInfo {
-                has_next_page,
-                has_previous_page,
-                start_cursor: events.first().map(|e| Cursor::new(EventCursor::new(e, &order))),
-                end_cursor: events.last().map(|e| Cursor::new(EventCursor::new(e, &order))),
-                start_index: first_num.map(cast_i32),
-                end_index: last_num.map(cast_i32),
-            },
-            items: events,
-        })
+    fn from_page_row(row: &Row, order: &EventSortOrder) -> (Self, EventCursor) {
+        let event = Self::from_row_start(row);
+        let cursor = EventCursor::new(&event, order);
+        (event, cursor)
     }
 }
 
+/// Logs the given error of a request to Opencast and converts it into an API
+/// error.
+fn opencast_error(opencast_id: &str, e: anyhow::Error) -> err::ApiError {
+    error!("Failed to send changes of event '{opencast_id}' to Opencast: {e:#}");
+    err::api_err!(
+        InternalServerError,
+        key = "event.opencast-write-failed",
+        "failed to send changes to Opencast",
+    )
+}
+
 impl From<EventTrack> for Track {
     fn from(src: EventTrack) -> Self {
         Self {
@@ -505,6 +728,26 @@ impl From<EventCaption> for Caption {
     }
 }
 
+impl From<EventSegment> for Segment {
+    fn from(src: EventSegment) -> Self {
+        Self {
+            start_time: src.start_time,
+            duration: src.duration,
+            thumbnail: src.thumbnail,
+            text: src.text,
+        }
+    }
+}
+
+impl From<EventChapter> for Chapter {
+    fn from(src: EventChapter) -> Self {
+        Self {
+            start_time: src.start_time,
+            title: src.title,
+        }
+    }
+}
+
 /// Defines the sort order for events.
 #[derive(Debug, Clone, Copy, juniper::GraphQLInputObject)]
 pub(crate) struct EventSortOrder {
@@ -519,10 +762,42 @@ enum EventSortColumn {
     Updated,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, juniper::GraphQLEnum)]
-enum SortDirection {
-    Ascending,
-    Descending,
+/// New metadata for an event. Fields that are `null` are left unchanged.
+#[derive(Debug, juniper::GraphQLInputObject)]
+pub(crate) struct EventMetadataUpdate {
+    title: Option<String>,
+    /// An empty string removes the description.
+    description: Option<String>,
+}
+
+/// Restricts which events are returned. All given conditions have to hold.
+#[derive(Debug, Default, juniper::GraphQLInputObject)]
+pub(crate) struct EventFilter {
+    /// Only events whose title contains this string (case-insensitive).
+    title: Option<String>,
+    /// Only events of this series.
+    series: Option<Id>,
+    /// Only events created at or after this point in time.
+    created_after: Option<DateTime<Utc>>,
+    /// Only events created before this point in time.
+    created_before: Option<DateTime<Utc>>,
+    /// Only live events (`true`) or only non-live events (`false`).
+    is_live: Option<bool>,
+    /// Only events that are still waiting for their data to be synced from
+    /// Opencast (`true`) or only fully synced events (`false`).
+    is_waiting: Option<bool>,
+}
+
+impl From<VideoListOrder> for EventSortOrder {
+    fn from(order: VideoListOrder) -> Self {
+        let (column, direction) = match order {
+            VideoListOrder::NewToOld => (EventSortColumn::Created, SortDirection::Descending),
+            VideoListOrder::OldToNew => (EventSortColumn::Created, SortDirection::Ascending),
+            VideoListOrder::AZ => (EventSortColumn::Title, SortDirection::Ascending),
+            VideoListOrder::ZA => (EventSortColumn::Title, SortDirection::Descending),
+        };
+        Self { column, direction }
+    }
 }
 
 impl Default for EventSortOrder {
@@ -552,26 +827,6 @@ impl EventSortColumn {
     }
 }
 
-impl SortDirection {
-    fn to_sql(self) -> &'static str {
-        match self {
-            SortDirection::Ascending => "asc",
-            SortDirection::Descending => "desc",
-        }
-    }
-
-    fn is_ascending(&self) -> bool {
-        matches!(self, Self::Ascending)
-    }
-
-    fn reversed(self) -> Self {
-        match self {
-            SortDirection::Ascending => SortDirection::Descending,
-            SortDirection::Descending => SortDirection::Ascending,
-        }
-    }
-}
-
 
 #[derive(Debug, juniper::GraphQLObject)]
 #[graphql(Context = Context)]
@@ -610,10 +865,15 @@ impl EventCursor {
             key: event.key,
         }
     }
+}
+
+impl PageCursor for EventCursor {
+    type Order = EventSortOrder;
+
+    fn key(&self) -> &Key {
+        &self.key
+    }
 
-    /// Returns the actual filter value as trait object if `self.sort_filter`
-    /// matches `order.column` (both about the same column). Returns an error
-    /// otherwise.
     fn to_sql_arg(&self, order: &EventSortOrder) -> ApiResult<&(dyn ToSql + Sync + '_)> {
         match (&self.sort_filter, order.column) {
             (CursorSortFilter::Title(title), EventSortColumn::Title) => Ok(title),
@@ -629,8 +889,8 @@ impl EventCursor {
     }
 }
 
-// TODO: when we add more `PageInfo` structs it might make sense to extract the
-// common fields somehow.
+/// Same as `PageInfo`, which was only introduced later. This type is kept for
+/// API compatibility.
 #[derive(Debug, Clone, juniper::GraphQLObject)]
 pub(crate) struct EventPageInfo {
     pub(crate) has_next_page: bool,
@@ -646,3 +906,16 @@ pub(crate) struct EventPageInfo {
     /// The index of the last returned event.
     pub(crate) end_index: Option<i32>,
 }
+
+impl From<PageInfo> for EventPageInfo {
+    fn from(src: PageInfo) -> Self {
+        Self {
+            has_next_page: src.has_next_page,
+            has_previous_page: src.has_previous_page,
+            start_cursor: src.start_cursor,
+            end_cursor: src.end_cursor,
+            start_index: src.start_index,
+            end_index: src.end_index,
+        }
+    }
+}
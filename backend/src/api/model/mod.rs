@@ -2,10 +2,14 @@
 //! API.
 
 pub(crate) mod acl;
+pub(crate) mod audit_log;
 pub(crate) mod block;
 pub(crate) mod event;
 pub(crate) mod known_roles;
+pub(crate) mod playlist;
 pub(crate) mod realm;
 pub(crate) mod search;
 pub(crate) mod series;
+pub(crate) mod sync;
+pub(crate) mod trash;
 pub(crate) mod user;
@@ -0,0 +1,228 @@
+use chrono::{DateTime, Utc};
+use juniper::graphql_object;
+use postgres_types::ToSql;
+
+use crate::{
+    api::{
+        Context, Id, Node, NodeValue,
+        common::NotAllowed,
+        err::{self, ApiResult},
+        model::{event::AuthorizedEvent, realm::Realm},
+        util::impl_object_with_dummy_field,
+    },
+    db::{types::Key, util::{impl_from_db, select}},
+    prelude::*,
+};
+
+
+#[derive(Debug)]
+pub(crate) struct AuthorizedPlaylist {
+    pub(crate) key: Key,
+    opencast_id: String,
+    title: String,
+    description: Option<String>,
+    creator: Option<String>,
+    updated: DateTime<Utc>,
+
+    read_roles: Vec<String>,
+    write_roles: Vec<String>,
+}
+
+impl_from_db!(
+    AuthorizedPlaylist,
+    select: {
+        playlists.{ id, opencast_id, title, description, creator, updated, read_roles, write_roles },
+    },
+    |row| {
+        Self {
+            key: row.id(),
+            opencast_id: row.opencast_id(),
+            title: row.title(),
+            description: row.description(),
+            creator: row.creator(),
+            updated: row.updated(),
+            read_roles: row.read_roles(),
+            write_roles: row.write_roles(),
+        }
+    },
+);
+
+#[derive(juniper::GraphQLUnion)]
+#[graphql(Context = Context)]
+pub(crate) enum Playlist {
+    Playlist(AuthorizedPlaylist),
+    NotAllowed(NotAllowed),
+}
+
+impl Playlist {
+    pub(crate) fn into_result(self) -> ApiResult<AuthorizedPlaylist> {
+        match self {
+            Self::Playlist(p) => Ok(p),
+            Self::NotAllowed(_) => Err(err::not_authorized!(
+                key = "view.playlist",
+                "you cannot view this playlist",
+            )),
+        }
+    }
+}
+
+/// A single entry of a playlist. Entries referring to events that Tobira does
+/// not know (yet) are represented by `Missing`.
+#[derive(juniper::GraphQLUnion)]
+#[graphql(Context = Context)]
+pub(crate) enum PlaylistEntry {
+    Event(AuthorizedEvent),
+    NotAllowed(NotAllowed),
+    Missing(Missing),
+}
+
+/// Marker type to signal that a playlist entry refers to an item that does
+/// not exist in Tobira's database.
+pub(crate) struct Missing;
+
+impl_object_with_dummy_field!(Missing);
+
+
+impl AuthorizedPlaylist {
+    pub(crate) async fn load_by_id(id: Id, context: &Context) -> ApiResult<Option<Playlist>> {
+        match id.key_for(Id::PLAYLIST_KIND) {
+            None => Ok(None),
+            Some(key) => Self::load_by_key(key, context).await,
+        }
+    }
+
+    pub(crate) async fn load_by_key(key: Key, context: &Context) -> ApiResult<Option<Playlist>> {
+        Self::load_by_any_id("id = $1", &[&key], context).await
+    }
+
+    pub(crate) async fn load_by_opencast_id(
+        oc_id: String,
+        sync_source: &str,
+        context: &Context,
+    ) -> ApiResult<Option<Playlist>> {
+        let cond = "sync_source = $1 and opencast_id = $2";
+        Self::load_by_any_id(cond, &[&sync_source, &oc_id], context).await
+    }
+
+    async fn load_by_any_id(
+        cond: &str,
+        args: &[&(dyn ToSql + Sync)],
+        context: &Context,
+    ) -> ApiResult<Option<Playlist>> {
+        let selection = Self::select();
+        let query = format!("select {selection} from playlists where {cond}");
+        context.db
+            .query_opt(&query, args)
+            .await?
+            .map(|row| {
+                let playlist = Self::from_row_start(&row);
+                if context.auth.overlaps_roles(&playlist.read_roles) {
+                    Playlist::Playlist(playlist)
+                } else {
+                    Playlist::NotAllowed(NotAllowed)
+                }
+            })
+            .pipe(Ok)
+    }
+}
+
+impl Node for AuthorizedPlaylist {
+    fn id(&self) -> Id {
+        Id::playlist(self.key)
+    }
+}
+
+/// Represents an Opencast playlist.
+#[graphql_object(Context = Context, impl = NodeValue)]
+impl AuthorizedPlaylist {
+    fn id(&self) -> Id {
+        Node::id(self)
+    }
+
+    fn opencast_id(&self) -> &str {
+        &self.opencast_id
+    }
+
+    fn title(&self) -> &str {
+        &self.title
+    }
+
+    fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    fn creator(&self) -> Option<&str> {
+        self.creator.as_deref()
+    }
+
+    fn updated(&self) -> DateTime<Utc> {
+        self.updated
+    }
+
+    /// This doesn't contain `ROLE_ADMIN` as that is included implicitly.
+    fn read_roles(&self) -> &[String] {
+        &self.read_roles
+    }
+
+    /// This doesn't contain `ROLE_ADMIN` as that is included implicitly.
+    fn write_roles(&self) -> &[String] {
+        &self.write_roles
+    }
+
+    /// Whether the current user has write access to this playlist.
+    fn can_write(&self, context: &Context) -> bool {
+        context.auth.overlaps_roles(&self.write_roles)
+    }
+
+    /// The entries of this playlist, in the order defined in Opencast.
+    async fn entries(&self, context: &Context) -> ApiResult<Vec<PlaylistEntry>> {
+        let (selection, mapping) = select!(
+            found: "events.id is not null and events.deletion_pending_since is null",
+            event: AuthorizedEvent,
+        );
+        let query = format!("\
+            select {selection} \
+            from playlists \
+            cross join unnest(playlists.entries) with ordinality as entries \
+            left join events on events.opencast_id = entries.content_id \
+                and events.sync_source = playlists.sync_source \
+            where playlists.id = $1 \
+            order by entries.ordinality \
+        ");
+        context.db
+            .query_mapped(&query, dbargs![&self.key], |row| {
+                if !mapping.found.of::<bool>(&row) {
+                    return PlaylistEntry::Missing(Missing);
+                }
+
+                let event = AuthorizedEvent::from_row(&row, mapping.event);
+                if context.auth.overlaps_roles(&event.read_roles) {
+                    PlaylistEntry::Event(event)
+                } else {
+                    PlaylistEntry::NotAllowed(NotAllowed)
+                }
+            })
+            .await?
+            .pipe(Ok)
+    }
+
+    /// Returns a list of realms where this playlist is referenced via a
+    /// playlist block.
+    async fn host_realms(&self, context: &Context) -> ApiResult<Vec<Realm>> {
+        let selection = Realm::select();
+        let query = format!("\
+            select {selection} \
+            from realms \
+            where exists ( \
+                select 1 as contains \
+                from blocks \
+                where realm = realms.id \
+                and type = 'playlist' \
+                and playlist = $1 \
+            ) \
+        ");
+        context.db.query_mapped(&query, dbargs![&self.key], |row| Realm::from_row_start(&row))
+            .await?
+            .pipe(Ok)
+    }
+}
@@ -0,0 +1,237 @@
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use juniper::graphql_object;
+
+use crate::{
+    api::{
+        Context,
+        Id,
+        err::{ApiResult, invalid_input, map_db_err},
+        model::{block::BlockValue, trash::TrashEntry},
+    },
+    auth::AuthContext,
+    db::{types::Key, util::{impl_from_db, select}},
+    prelude::*,
+};
+use super::Realm;
+
+
+/// All columns of `blocks` and `draft_blocks`, used to copy blocks between
+/// both tables. Listed explicitly so that copying does not depend on the
+/// column order of the two tables. Columns added to `blocks` have to be added
+/// here as well (this is checked by a DB test).
+pub(crate) const BLOCK_COLUMNS: &str = "id, realm, type, index, text_content, series, \
+    videolist_order, video, show_title, show_metadata, show_link, videolist_layout, playlist, \
+    visible_from, visible_until, realms, realmlist_layout, embed_url";
+
+/// The table block mutations operate on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BlockTable {
+    Live,
+    Draft,
+}
+
+impl BlockTable {
+    /// Returns `Draft` if the given realm currently has a draft, `Live`
+    /// otherwise.
+    pub(crate) async fn for_realm(realm: Key, context: &Context) -> ApiResult<Self> {
+        let has_draft = context.db
+            .query_one("select exists(select from realm_drafts where realm = $1)", &[&realm])
+            .await?
+            .get::<_, bool>(0);
+        Ok(if has_draft { Self::Draft } else { Self::Live })
+    }
+
+    /// Name of the `unique(realm, index)` constraint of this table.
+    pub(crate) fn index_constraint(self) -> &'static str {
+        match self {
+            Self::Live => "index_unique_in_realm",
+            Self::Draft => "draft_index_unique_in_realm",
+        }
+    }
+}
+
+impl fmt::Display for BlockTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Live => f.write_str("blocks"),
+            Self::Draft => f.write_str("draft_blocks"),
+        }
+    }
+}
+
+
+/// A draft of a realm's blocks. While it exists, all block mutations of the
+/// realm only change the draft.
+pub(crate) struct RealmDraft {
+    pub(crate) realm: Key,
+    pub(crate) created: DateTime<Utc>,
+    pub(crate) created_by: Option<String>,
+}
+
+impl_from_db!(
+    RealmDraft,
+    select: {
+        realm_drafts.{ realm, created, created_by },
+    },
+    |row| {
+        Self {
+            realm: row.realm(),
+            created: row.created(),
+            created_by: row.created_by(),
+        }
+    },
+);
+
+#[graphql_object(Context = Context)]
+impl RealmDraft {
+    fn created(&self) -> DateTime<Utc> {
+        self.created
+    }
+
+    /// The user who started the draft. `null` if it was started by a trusted
+    /// external application.
+    fn created_by(&self) -> Option<&str> {
+        self.created_by.as_deref()
+    }
+
+    /// The blocks of the draft, i.e. how the realm will look like after
+    /// publishing.
+    async fn blocks(&self, context: &Context) -> ApiResult<Vec<BlockValue>> {
+        BlockValue::load_for_realm_from(self.realm, BlockTable::Draft, context).await
+    }
+}
+
+impl RealmDraft {
+    pub(crate) async fn load_for_realm(realm: Key, context: &Context) -> ApiResult<Option<Self>> {
+        let selection = Self::select();
+        let query = format!("select {selection} from realm_drafts where realm = $1");
+        context.db
+            .query_opt(&query, &[&realm])
+            .await?
+            .map(|row| Self::from_row_start(&row))
+            .pipe(Ok)
+    }
+
+    /// Starts a draft for the given realm, initially containing all its blocks.
+    pub(crate) async fn start(realm: Id, context: &Context) -> ApiResult<Realm> {
+        let realm = load_realm_for_draft(realm, context).await?;
+        let username = match &context.auth {
+            AuthContext::User(user) => Some(&user.username),
+            _ => None,
+        };
+
+        let res = context.db
+            .execute(
+                "insert into realm_drafts (realm, created_by) values ($1, $2)",
+                &[&realm.key, &username],
+            )
+            .await;
+        map_db_err!(res, {
+            if constraint == "realm_drafts_pkey" => invalid_input!(
+                key = "realm.draft-already-exists",
+                "realm already has a draft",
+            ),
+        })?;
+        context.db
+            .execute(
+                &format!("insert into draft_blocks ({BLOCK_COLUMNS}) \
+                    select {BLOCK_COLUMNS} from blocks where realm = $1"),
+                &[&realm.key],
+            )
+            .await?;
+
+        info!(path = realm.full_path, "Started realm draft");
+        Ok(realm)
+    }
+
+    /// Replaces the blocks of the given realm with the blocks of its draft and
+    /// removes the draft. Removed blocks are moved to the trash.
+    pub(crate) async fn publish(realm: Id, context: &Context) -> ApiResult<Realm> {
+        let realm = load_realm_for_draft(realm, context).await?;
+        require_draft(&realm, context).await?;
+        let db = &context.db;
+
+        // The name source block must not be removed, just like when removing
+        // it directly.
+        if let Some(block) = realm.name_from_block {
+            let exists = db
+                .query_one("select exists(select from draft_blocks where id = $1)", &[&block])
+                .await?
+                .get::<_, bool>(0);
+            if !exists {
+                return Err(invalid_input!(
+                    key = "realm.draft-removes-name-source",
+                    "the draft removes the block the realm name is derived from",
+                ));
+            }
+        }
+
+        let removed_blocks = db
+            .query_mapped(
+                "select id from blocks \
+                    where realm = $1 \
+                    and id not in (select id from draft_blocks where realm = $1)",
+                dbargs![&realm.key],
+                |row| row.get::<_, Key>(0),
+            )
+            .await?;
+        for block in removed_blocks {
+            TrashEntry::add_block(block, context).await?;
+        }
+
+        // The name source block is deleted and inserted again below, which is
+        // not allowed while the realm references it. So we temporarily use a
+        // plain name.
+        if realm.name_from_block.is_some() {
+            db.execute(
+                "update realms set name = path_segment, name_from_block = null where id = $1",
+                &[&realm.key],
+            ).await?;
+        }
+        db.execute("delete from blocks where realm = $1", &[&realm.key]).await?;
+        db.execute(
+            &format!("insert into blocks ({BLOCK_COLUMNS}) \
+                select {BLOCK_COLUMNS} from draft_blocks where realm = $1"),
+            &[&realm.key],
+        ).await?;
+        if let Some(block) = realm.name_from_block {
+            db.execute(
+                "update realms set name = null, name_from_block = $2 where id = $1",
+                &[&realm.key, &block],
+            ).await?;
+        }
+        db.execute("delete from realm_drafts where realm = $1", &[&realm.key]).await?;
+
+        info!(path = realm.full_path, "Published realm draft");
+        Ok(realm)
+    }
+
+    /// Removes the draft of the given realm without changing its blocks.
+    pub(crate) async fn discard(realm: Id, context: &Context) -> ApiResult<Realm> {
+        let realm = load_realm_for_draft(realm, context).await?;
+        require_draft(&realm, context).await?;
+        context.db
+            .execute("delete from realm_drafts where realm = $1", &[&realm.key])
+            .await?;
+
+        info!(path = realm.full_path, "Discarded realm draft");
+        Ok(realm)
+    }
+}
+
+async fn load_realm_for_draft(realm: Id, context: &Context) -> ApiResult<Realm> {
+    let Some(realm) = Realm::load_by_id(realm, context).await? else {
+        return Err(invalid_input!("`realm` does not refer to a realm"));
+    };
+    realm.require_moderator_rights(context)?;
+    Ok(realm)
+}
+
+async fn require_draft(realm: &Realm, context: &Context) -> ApiResult<()> {
+    if BlockTable::for_realm(realm.key, context).await? != BlockTable::Draft {
+        return Err(invalid_input!(key = "realm.no-draft", "realm has no draft"));
+    }
+    Ok(())
+}
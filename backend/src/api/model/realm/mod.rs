@@ -1,17 +1,27 @@
+use std::time::Duration;
+
 use juniper::{graphql_object, GraphQLEnum, GraphQLObject, GraphQLUnion, graphql_interface};
 use postgres_types::{FromSql, ToSql};
 
 use crate::{
     api::{Context, Id, err::ApiResult, Node, NodeValue, model::acl::{Acl, self}},
     auth::AuthContext,
-    db::{types::Key, util::{select, impl_from_db}},
+    config::Config,
+    db::{DbConnection, types::Key, util::{select, impl_from_db}},
     prelude::*,
+    util::Never,
+};
+use super::{
+    audit_log::AuditLogEntry,
+    block::{Block, BlockValue, SeriesBlock, VideoBlock},
+    trash::TrashEntry,
 };
-use super::block::{Block, BlockValue, SeriesBlock, VideoBlock};
 
 
+mod draft;
 mod mutations;
 
+pub(crate) use draft::{BlockTable, RealmDraft, BLOCK_COLUMNS};
 pub(crate) use mutations::{
     ChildIndex, NewRealm, RemovedRealm, UpdateRealm, UpdatedPermissions, UpdatedRealmName, RealmSpecifier,
 };
@@ -183,7 +193,25 @@ impl Realm {
             .pipe(Ok)
     }
 
-    pub(crate) async fn load_by_path(mut path: String, context: &Context) -> ApiResult<Option<Self>> {
+    pub(crate) async fn load_by_path(path: String, context: &Context) -> ApiResult<Option<Self>> {
+        Self::load_by_path_impl(path, false, context).await
+    }
+
+    /// Like `load_by_path`, but also resolves old paths of realms that were
+    /// moved or whose path segment changed. In that case, the `full_path` of
+    /// the returned realm differs from `path`.
+    pub(crate) async fn load_by_current_or_old_path(
+        path: String,
+        context: &Context,
+    ) -> ApiResult<Option<Self>> {
+        Self::load_by_path_impl(path, true, context).await
+    }
+
+    async fn load_by_path_impl(
+        mut path: String,
+        include_old_paths: bool,
+        context: &Context,
+    ) -> ApiResult<Option<Self>> {
         // Normalize path: strip optional trailing slash.
         if path.ends_with('/') {
             path.pop();
@@ -194,8 +222,16 @@ impl Realm {
             return Ok(Some(Self::root(context).await?));
         }
 
+        // Old paths are only stored as long as no realm has that path, so
+        // there is at most one match.
         let selection = Self::select();
-        let query = format!("select {selection} from realms where realms.full_path = $1");
+        let old_path_cond = if include_old_paths {
+            "or realms.id = (select realm from realm_path_history where old_path = $1)"
+        } else {
+            ""
+        };
+        let query = format!("select {selection} from realms \
+            where realms.full_path = $1 {old_path_cond}");
         context.db
             .query_opt(&query, &[&path])
             .await?
@@ -413,13 +449,39 @@ impl Realm {
             .pipe(Ok)
     }
 
-    /// Returns the (content) blocks of this realm.
+    /// Returns the (content) blocks of this realm. Blocks outside of their
+    /// visibility window are only returned for users with moderator rights.
     async fn blocks(&self, context: &Context) -> ApiResult<Vec<BlockValue>> {
         // TODO: this method can very easily lead to an N+1 query problem.
         // However, it is unlikely that we ever have that problem: the frontend
         // will only show one realm at a time, so the query will also only
         // request the blocks of one realm.
-        BlockValue::load_for_realm(self.key, context).await
+        let mut blocks = BlockValue::load_for_realm(self.key, context).await?;
+        if !self.can_current_user_moderate(context) {
+            blocks.retain(|block| block.shared_data().is_visible_now());
+        }
+        Ok(blocks)
+    }
+
+    /// Returns the audit log of this realm (all mutations that changed the
+    /// realm or its blocks), newest first. Requires page admin rights.
+    async fn audit_log(&self, context: &Context) -> ApiResult<Vec<AuditLogEntry>> {
+        self.require_admin_rights(context)?;
+        AuditLogEntry::load_for_realm(self.key, context).await
+    }
+
+    /// Returns the draft of this realm, or `null` if it has none. Requires
+    /// moderator rights.
+    async fn draft(&self, context: &Context) -> ApiResult<Option<RealmDraft>> {
+        self.require_moderator_rights(context)?;
+        RealmDraft::load_for_realm(self.key, context).await
+    }
+
+    /// Returns the removed child realms and blocks of this realm that can still
+    /// be restored, newest first. Requires page admin rights.
+    async fn trash(&self, context: &Context) -> ApiResult<Vec<TrashEntry>> {
+        self.require_admin_rights(context)?;
+        TrashEntry::load_for_realm(self.key, context).await
     }
 
     /// Returns the number of realms that are descendants of this one
@@ -485,3 +547,27 @@ impl Realm {
         }
     }
 }
+
+/// Long running task that removes old realm paths (see `realm_path_history`)
+/// after `general.realm_path_history_retention`.
+pub(crate) async fn run_path_history_purge_daemon(
+    db: DbConnection,
+    config: &Config,
+) -> Result<Never> {
+    /// How often to check for expired entries. The retention period is usually
+    /// long, so this does not need to be precise.
+    const RUN_PERIOD: Duration = Duration::from_secs(60 * 60);
+
+    let retention = config.general.realm_path_history_retention.as_secs_f64();
+    loop {
+        let sql = "delete from realm_path_history \
+            where extract(epoch from now() - changed) > $1::double precision";
+        match db.execute(sql, &[&retention]).await {
+            Err(e) => error!("Error purging expired realm path history entries: {e}"),
+            Ok(0) => debug!("No expired realm path history entries found in DB"),
+            Ok(num) => info!("Purged {num} expired realm path history entries from DB"),
+        }
+
+        tokio::time::sleep(RUN_PERIOD).await;
+    }
+}
@@ -1,7 +1,12 @@
 use std::collections::{HashMap, HashSet};
 
 use crate::{
-    api::{Context, Id, err::{ApiResult, invalid_input, map_db_err}},
+    api::{
+        Context,
+        Id,
+        err::{self, ApiResult, invalid_input, map_db_err},
+        model::trash::TrashEntry,
+    },
     db::types::Key,
     prelude::*, auth::AuthContext,
 };
@@ -318,6 +323,115 @@ impl Realm {
         })
     }
 
+    /// Moves a realm (with all its descendants) to a new parent, placing it
+    /// at position `index` among the new siblings. The `full_path` of all
+    /// descendants is fixed by DB triggers, which also queue all affected
+    /// realms, series and events for the search index.
+    pub(crate) async fn move_to(
+        id: Id,
+        new_parent: Id,
+        index: i32,
+        context: &Context,
+    ) -> ApiResult<Realm> {
+        let Some(realm) = Self::load_by_id(id, context).await? else {
+            return Err(invalid_input!("`id` does not refer to an existing realm"));
+        };
+        let Some(new_parent) = Self::load_by_id(new_parent, context).await? else {
+            return Err(invalid_input!("`newParent` does not refer to an existing realm"));
+        };
+        realm.require_admin_rights(context)?;
+        new_parent.require_admin_rights(context)?;
+        let db = &context.db;
+
+        if realm.is_main_root() || realm.is_user_root() {
+            return Err(invalid_input!("cannot move root realms"));
+        }
+        if index < 0 {
+            return Err(invalid_input!("`index` has to be >= 0, but is {}", index));
+        }
+
+        // Moving a realm below itself would create a cycle.
+        let is_descendant = new_parent.full_path.starts_with(&format!("{}/", realm.full_path));
+        if new_parent.key == realm.key || is_descendant {
+            return Err(invalid_input!(
+                key = "realm.move-into-own-subtree",
+                "cannot move realm into itself or one of its descendants",
+            ));
+        }
+
+        // User realms stay in the tree of their user and other realms cannot
+        // be moved into user realm trees.
+        if realm.owning_user() != new_parent.owning_user() {
+            return Err(invalid_input!("cannot move realm out of or into a user realm tree"));
+        }
+
+        // Like when adding realms, make sure the path is not a reserved one
+        // and not used by any of the new siblings.
+        let path_is_reserved = context.config.general.reserved_paths()
+            .any(|r| realm.path_segment == r);
+        if new_parent.is_main_root() && path_is_reserved {
+            return Err(invalid_input!(key = "realm.path-is-reserved", "path is reserved and cannot be used"));
+        }
+        let path_collision = db
+            .query_one(
+                "select exists(select from realms \
+                    where parent = $1 and path_segment = $2 and id <> $3)",
+                &[&new_parent.key, &realm.path_segment, &realm.key],
+            )
+            .await?
+            .get::<_, bool>(0);
+        if path_collision {
+            return Err(invalid_input!(
+                key = "realm.path-collision",
+                "realm with that path already exists",
+            ));
+        }
+
+        // Retrieve the new siblings in their current order.
+        let mut siblings = db
+            .query_mapped(
+                "select id from realms where parent = $1 and id <> $2 order by index, id",
+                dbargs![&new_parent.key, &realm.key],
+                |row| row.get::<_, Key>(0),
+            )
+            .await?;
+
+        let res = db.execute(
+            "update realms set parent = $2 where id = $1",
+            &[&realm.key, &new_parent.key],
+        ).await;
+        map_db_err!(res, {
+            if constraint == "idx_realm_path" => invalid_input!(
+                key = "realm.path-collision",
+                "realm with that path already exists",
+            ),
+        })?;
+
+        // Indices only matter if the children of the new parent are ordered
+        // by index. In that case we renumber all of them.
+        if new_parent.child_order == RealmOrder::ByIndex {
+            let index = std::cmp::min(index as usize, siblings.len());
+            siblings.insert(index, realm.key);
+            for (index, key) in siblings.into_iter().enumerate() {
+                db.execute(
+                    "update realms set index = $1 where id = $2",
+                    &[&(index as i32), &key],
+                ).await?;
+            }
+        } else {
+            db.execute("update realms set index = default where id = $1", &[&realm.key]).await?;
+        }
+
+        Self::load_by_key(realm.key, context).await.map(Option::unwrap).inspect_(|new| {
+            info!(
+                "Moved realm {:?} from '{}' to '{}'",
+                realm.key,
+                realm.full_path,
+                new.full_path,
+            );
+        })
+    }
+
     pub(crate) async fn remove(id: Id, context: &Context) -> ApiResult<RemovedRealm> {
         let Some(realm) = Self::load_by_id(id, context).await? else {
             return Err(invalid_input!("`id` does not refer to an existing realm"));
@@ -329,6 +443,7 @@ impl Realm {
             return Err(invalid_input!("Cannot remove the root realm"));
         }
 
+        TrashEntry::add_realm(realm.key, context).await?;
         db.execute("delete from realms where id = $1", &[&realm.key]).await?;
 
         let parent = match realm.parent_key {
@@ -339,6 +454,95 @@ impl Realm {
         info!(%id, path = realm.full_path, "Removed realm");
         Ok(RemovedRealm { parent })
     }
+
+    /// Restores a realm and all its descendants from the trash, as they were
+    /// when the realm was removed.
+    pub(crate) async fn restore(id: Id, context: &Context) -> ApiResult<Realm> {
+        let key = id_to_key(id, "`id`")?;
+        let Some(entry) = TrashEntry::load_for_realm_item(key, context).await? else {
+            return Err(invalid_input!(
+                key = "trash.not-found",
+                "`id` does not refer to a realm in the trash",
+            ));
+        };
+
+        match entry.parent {
+            Some(parent) => {
+                let Some(parent) = Self::load_by_key(parent, context).await? else {
+                    return Err(invalid_input!(
+                        key = "trash.realm-removed",
+                        "the parent realm was removed: restore it first",
+                    ));
+                };
+                parent.require_admin_rights(context)?;
+            }
+            // User root realms can only be restored by their owner.
+            None => {
+                let is_owner = matches!(
+                    &context.auth,
+                    AuthContext::User(user) if entry.path == format!("/@{}", user.username),
+                );
+                if !is_owner && !context.auth.is_global_page_admin(&context.config.auth) {
+                    return Err(context.access_error("realm.no-page-admin-rights", |user| format!(
+                        "'{user}' is not allowed to restore user realm '{}'",
+                        entry.path,
+                    )));
+                }
+            }
+        }
+
+        // The realms are ordered by path length, so parents come before their
+        // children. Realms are inserted with their old IDs (which are never
+        // reused) and a temporary name, as the block used as name source does
+        // not exist yet.
+        let realms = entry.data.as_array().ok_or_else(|| err::api_err!(
+            InternalServerError,
+            "invalid trash data for realm '{}'",
+            entry.path,
+        ))?;
+        let db = &context.db;
+        for realm in realms {
+            let res = db.execute(
+                "insert into realms \
+                    select r.* from jsonb_populate_record(null::realms, \
+                        ($1::jsonb - 'blocks') || jsonb_build_object( \
+                            'full_path', null, \
+                            'name', $1::jsonb->>'path_segment', \
+                            'name_from_block', null \
+                        ) \
+                    ) as r",
+                &[realm],
+            ).await;
+            map_db_err!(res, {
+                if constraint == "idx_realm_path" => invalid_input!(
+                    key = "realm.path-collision",
+                    "realm with that path already exists",
+                ),
+            })?;
+            db.execute("select restore_blocks($1::jsonb->'blocks')", &[realm]).await?;
+            db.execute(
+                "select restore_draft($1::jsonb->'draft') \
+                    where jsonb_typeof($1::jsonb->'draft') = 'object'",
+                &[realm],
+            ).await?;
+        }
+        for realm in realms {
+            db.execute(
+                "update realms set \
+                    name = $1::jsonb->>'name', \
+                    name_from_block = ($1::jsonb->>'name_from_block')::bigint \
+                    where id = ($1::jsonb->>'id')::bigint",
+                &[realm],
+            ).await?;
+        }
+        entry.remove(context).await?;
+
+        let realm = Self::load_by_id(id, context)
+            .await?
+            .ok_or_else(|| invalid_input!("restored realm does not exist"))?;
+        info!(%id, path = realm.full_path, "Restored realm");
+        Ok(realm)
+    }
 }
 
 /// Makes sure the ID refers to a realm and returns its key.
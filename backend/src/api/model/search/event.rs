@@ -1,11 +1,19 @@
+use std::{collections::HashMap, future};
+
 use chrono::{DateTime, Utc};
 
 use crate::{
-    api::{Context, Node, Id, NodeValue},
+    api::{Context, Node, Id, NodeValue, err::ApiResult},
+    db::{types::Key, util::select},
+    prelude::*,
     search,
 };
 
 
+/// Maximum number of text matches returned per event.
+const MAX_TEXT_MATCHES: i64 = 10;
+
+
 impl Node for search::Event {
     fn id(&self) -> Id {
         Id::search_event(self.id.0)
@@ -69,4 +77,99 @@ impl search::Event {
     fn host_realms(&self) -> &[search::Realm] {
         &self.host_realms
     }
+
+    /// Caption cues and segments of this event that match the search query,
+    /// ordered by time. Only filled for results of the main search.
+    fn text_matches(&self) -> &[search::TextMatch] {
+        &self.text_matches
+    }
+}
+
+#[juniper::graphql_object(Context = Context, name = "TextMatch")]
+impl search::TextMatch {
+    /// Start of the matching cue or segment in ms.
+    fn start(&self) -> f64 {
+        self.start as f64
+    }
+
+    /// Duration of the matching cue or segment in ms.
+    fn duration(&self) -> f64 {
+        self.duration as f64
+    }
+
+    fn text(&self) -> &str {
+        &self.text
+    }
+
+    fn ty(&self) -> search::TextMatchType {
+        self.ty
+    }
+}
+
+/// Fills `text_matches` of the given events with all caption cues and
+/// segments containing any of the words in `user_query`.
+pub(super) async fn load_text_matches(
+    events: &mut [search::Event],
+    user_query: &str,
+    context: &Context,
+) -> ApiResult<()> {
+    if events.is_empty() {
+        return Ok(());
+    }
+
+    let ids = events.iter().map(|e| e.id.0).collect::<Vec<_>>();
+    let patterns = user_query.split_whitespace()
+        .map(|word| {
+            let escaped = word.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+            format!("%{escaped}%")
+        })
+        .collect::<Vec<_>>();
+
+    let (selection, mapping) = select!(event_id, span_start, span_end, t, is_slide);
+    let query = format!("\
+        select {selection} \
+        from ( \
+            select *, row_number() over ( \
+                partition by event_id order by span_start \
+            ) as row_num \
+            from ( \
+                select event_id, texts.span_start, texts.span_end, texts.t, false as is_slide \
+                from event_texts, unnest(event_texts.texts) as texts \
+                where event_id = any($1) and texts.t ilike any($2) \
+                union all \
+                select events.id, segments.start_time, \
+                    segments.start_time + segments.duration, segments.text, true \
+                from events, unnest(events.segments) as segments \
+                where events.id = any($1) and segments.text ilike any($2) \
+            ) as all_matches \
+        ) as matches \
+        where row_num <= {MAX_TEXT_MATCHES} \
+        order by span_start");
+
+    let mut matches = HashMap::new();
+    context.db
+        .query_raw(&query, dbargs![&ids, &patterns])
+        .await?
+        .try_for_each(|row| {
+            let start = mapping.span_start.of::<i64>(&row);
+            let text_match = search::TextMatch {
+                start,
+                duration: mapping.span_end.of::<i64>(&row) - start,
+                text: mapping.t.of(&row),
+                ty: match mapping.is_slide.of::<bool>(&row) {
+                    true => search::TextMatchType::SlideText,
+                    false => search::TextMatchType::Caption,
+                },
+            };
+            let key = mapping.event_id.of::<Key>(&row);
+            matches.entry(key).or_insert(vec![]).push(text_match);
+            future::ready(Ok(()))
+        })
+        .await?;
+
+    for event in events {
+        event.text_matches = matches.remove(&event.id.0).unwrap_or_default();
+    }
+
+    Ok(())
 }
@@ -17,6 +17,7 @@ use crate::{
     db::{types::Key, util::select},
     prelude::*,
     search,
+    sync::DEFAULT_SOURCE,
 };
 
 
@@ -162,19 +163,22 @@ pub(crate) async fn perform(
         return Ok(SearchOutcome::EmptyQuery(EmptyQuery));
     }
 
-    // Search for opencastId if applicable
+    // Search for opencastId if applicable. Like `eventByOpencastId`, this only
+    // considers the default sync source.
     let uuid_query = user_query.trim();
     if looks_like_opencast_uuid(&uuid_query) {
         let selection = search::Event::select();
         let query = format!("select {selection} from search_events \
-            where id = (select id from events where opencast_id = $1) \
+            where id = (select id from events where sync_source = $3 and opencast_id = $1) \
+            and deletion_pending_since is null \
             and (read_roles || 'ROLE_ADMIN'::text) && $2");
         let items: Vec<NodeValue> = context.db
-            .query_opt(&query, &[&uuid_query, &context.auth.roles_vec()])
-            .await?
-            .map(|row| search::Event::from_row_start(&row).into())
-            .into_iter()
-            .collect();
+            .query_mapped(
+                &query,
+                dbargs![&uuid_query, &context.auth.roles_vec(), &DEFAULT_SOURCE],
+                |row| search::Event::from_row_start(&row).into(),
+            )
+            .await?;
         let total_hits = items.len();
         return Ok(SearchOutcome::Results(SearchResults { items, total_hits }));
     }
@@ -249,6 +253,7 @@ pub(crate) async fn perform(
             from events e \
             where e.series = any($1) and (read_roles || 'ROLE_ADMIN'::text) && $2 \
             and (e.start_time <= current_timestamp or e.start_time is null) \
+            and e.deletion_pending_since is null \
         ) as ranked \
         where row_num <= 3");
 
@@ -274,8 +279,13 @@ pub(crate) async fn perform(
     // We can either use score details or adding dummy searchable fields to the
     // realm index. See this discussion for more info:
     // https://github.com/orgs/meilisearch/discussions/489#discussioncomment-6160361
-    let events = event_results.hits.into_iter()
-        .map(|result| (NodeValue::from(result.result), result.ranking_score));
+    let (mut events, event_scores): (Vec<_>, Vec<_>) = event_results.hits.into_iter()
+        .map(|result| (result.result, result.ranking_score))
+        .unzip();
+    event::load_text_matches(&mut events, user_query, context).await?;
+    let events = events.into_iter()
+        .zip(event_scores)
+        .map(|(event, score)| (NodeValue::from(event), score));
     let series = series_results.hits.into_iter()
         .map(|result| {
             let series = SearchSeriesExtended {
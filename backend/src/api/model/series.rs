@@ -1,30 +1,39 @@
 use chrono::{DateTime, Utc};
 use juniper::{graphql_object, GraphQLObject, GraphQLInputObject};
-use postgres_types::ToSql;
+use postgres_types::{Timestamp, ToSql};
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Row;
 
 use crate::{
     api::{
         Context,
-        err::ApiResult,
+        Cursor,
+        common::{load_connection, PageCursor, PageInfo, Paginated, SortDirection},
+        err::{self, ApiResult, invalid_input},
         Id,
         model::{
+            acl::{self, Acl, AclInputEntry, NewAcl},
             realm::Realm,
-            event::{AuthorizedEvent, EventSortOrder}
+            event::{AuthorizedEvent, EventConnection, EventSortOrder}
         },
         Node,
     },
     db::{types::{ExtraMetadata, Key, SeriesState as State}, util::impl_from_db},
     prelude::*,
+    sync::DEFAULT_SOURCE,
 };
 
 
 pub(crate) struct Series {
     pub(crate) key: Key,
     opencast_id: String,
+    sync_source: String,
     synced_data: Option<SyncedSeriesData>,
     title: String,
     created: Option<DateTime<Utc>>,
     metadata: Option<ExtraMetadata>,
+    write_roles: Option<Vec<String>>,
+    pending_write_since: Option<DateTime<Utc>>,
 }
 
 #[derive(GraphQLObject)]
@@ -35,15 +44,21 @@ struct SyncedSeriesData {
 impl_from_db!(
     Series,
     select: {
-        series.{ id, opencast_id, state, title, description, created, metadata },
+        series.{
+            id, opencast_id, sync_source, state, title, description, created, metadata,
+            write_roles, pending_write_since,
+        },
     },
     |row| {
         Series {
             key: row.id(),
             opencast_id: row.opencast_id(),
+            sync_source: row.sync_source(),
             title: row.title(),
             created: row.created(),
             metadata: row.metadata(),
+            write_roles: row.write_roles(),
+            pending_write_since: row.pending_write_since(),
             synced_data: (State::Ready == row.state()).then(
                 || SyncedSeriesData {
                     description: row.description(),
@@ -63,22 +78,27 @@ impl Series {
     }
 
     pub(crate) async fn load_by_key(key: Key, context: &Context) -> ApiResult<Option<Self>> {
-        Self::load_by_any_id("id", &key, context).await
+        Self::load_by_any_id("id = $1", &[&key], context).await
     }
 
-    pub(crate) async fn load_by_opencast_id(id: String, context: &Context) -> ApiResult<Option<Self>> {
-        Self::load_by_any_id("opencast_id", &id, context).await
+    pub(crate) async fn load_by_opencast_id(
+        id: String,
+        sync_source: &str,
+        context: &Context,
+    ) -> ApiResult<Option<Self>> {
+        let cond = "sync_source = $1 and opencast_id = $2";
+        Self::load_by_any_id(cond, &[&sync_source, &id], context).await
     }
 
     async fn load_by_any_id(
-        col: &str,
-        id: &(dyn ToSql + Sync),
+        cond: &str,
+        args: &[&(dyn ToSql + Sync)],
         context: &Context,
     ) -> ApiResult<Option<Self>> {
         let selection = Self::select();
-        let query = format!("select {selection} from series where {col} = $1");
+        let query = format!("select {selection} from series where {cond}");
         context.db
-            .query_opt(&query, &[id])
+            .query_opt(&query, args)
             .await?
             .map(|row| Self::from_row_start(&row))
             .pipe(Ok)
@@ -97,6 +117,130 @@ impl Series {
             .pipe(|row| Self::from_row_start(&row))
             .pipe(Ok)
     }
+
+    /// Changes the ACL of a series in Opencast. As for events, the new ACL is
+    /// applied to our DB immediately and the series is marked as having a
+    /// pending write until the harvest returns a newer version of it.
+    pub(crate) async fn update_acl(
+        id: Id,
+        acl: Vec<AclInputEntry>,
+        context: &Context,
+    ) -> ApiResult<Self> {
+        let series = Self::load_by_id(id, context)
+            .await?
+            .ok_or_else(|| invalid_input!("`id` does not refer to an existing series"))?;
+
+        let Some(write_roles) = &series.write_roles else {
+            return Err(invalid_input!("series '{}' is not synced yet", series.opencast_id));
+        };
+        if !context.auth.overlaps_roles(write_roles) {
+            return Err(context.access_error("series.no-write-access", |user| format!(
+                "write access for series '{}' required, but '{user}' is ineligible",
+                series.opencast_id,
+            )));
+        }
+        if series.sync_source != DEFAULT_SOURCE {
+            return Err(invalid_input!(
+                "series '{}' is synced from '{}', but only series of the default \
+                    Opencast can be changed",
+                series.opencast_id,
+                series.sync_source,
+            ));
+        }
+
+        let acl = NewAcl::from_input(acl, context)?;
+
+        // Only versions harvested later that are newer than this include the
+        // write (see `check_pending_write` in the migrations).
+        let updated_before_write: DateTime<Utc> = context.db
+            .query_one("select updated from series where id = $1", &[&series.key])
+            .await?
+            .get(0);
+        context.oc_client.update_series_acl(&series.opencast_id, &acl.opencast_entries())
+            .await
+            .map_err(|e| opencast_error(&series.opencast_id, e))?;
+
+        let selection = Self::select();
+        let query = format!("update series set \
+                read_roles = $2, \
+                write_roles = $3, \
+                custom_action_roles = $4, \
+                pending_write_since = now(), \
+                updated_before_pending_write = $5 \
+            where id = $1 \
+            returning {selection}");
+        let args = dbargs![
+            &series.key,
+            &acl.read_roles,
+            &acl.write_roles,
+            &acl.custom_actions,
+            &updated_before_write,
+        ];
+        context.db
+            .query_one(&query, &args)
+            .await?
+            .pipe(|row| Self::from_row_start(&row))
+            .pipe(Ok)
+    }
+
+    /// Loads one page of all series the user has write access to.
+    pub(crate) async fn load_writable_for_user(
+        context: &Context,
+        order: SeriesSortOrder,
+        first: Option<i32>,
+        after: Option<Cursor>,
+        last: Option<i32>,
+        before: Option<Cursor>,
+    ) -> ApiResult<SeriesConnection> {
+        let roles = context.auth.roles_vec();
+        let roles_arg: [&(dyn ToSql + Sync); 1] = [&roles];
+        let base = if context.auth.is_admin() {
+            ("true", &[][..])
+        } else {
+            ("write_roles && $1 and read_roles && $1", &roles_arg[..])
+        };
+
+        let page = load_connection::<Self>(context, base, order, first, after, last, before)
+            .await?;
+        Ok(SeriesConnection {
+            page_info: page.page_info,
+            items: page.items,
+            total_count: page.total_count,
+        })
+    }
+}
+
+impl Paginated for Series {
+    type Order = SeriesSortOrder;
+    type Cursor = SeriesCursor;
+
+    // The subquery is required to be able to sort by the number of events.
+    const FROM: &'static str = "(\
+        select series.*, \
+            (select count(*) from events where events.series = series.id) as num_events \
+        from series\
+    ) as series";
+
+    fn page_columns() -> String {
+        format!("{}, updated, num_events", Self::select().with_omitted_table_prefix("series"))
+    }
+
+    fn sort_sql(order: &SeriesSortOrder) -> (&'static str, SortDirection) {
+        (order.column.to_sql(), order.direction)
+    }
+
+    fn from_page_row(row: &Row, order: &SeriesSortOrder) -> (Self, SeriesCursor) {
+        let series = Self::from_row_start(row);
+
+        // Waiting series have an `updated` of `-infinity`.
+        let n = Self::COLUMNS.len();
+        let updated = match row.get::<_, Timestamp<DateTime<Utc>>>(n) {
+            Timestamp::Value(updated) => Some(updated),
+            _ => None,
+        };
+        let cursor = SeriesCursor::new(&series, updated, row.get(n + 1), order);
+        (series, cursor)
+    }
 }
 
 /// Represents an Opencast series.
@@ -126,6 +270,21 @@ impl Series {
         &self.synced_data
     }
 
+    /// When changes to this series were sent to Opencast, if the harvest has
+    /// not confirmed them yet. `null` if there are no pending changes.
+    fn pending_write_since(&self) -> Option<DateTime<Utc>> {
+        self.pending_write_since
+    }
+
+    async fn acl(&self, context: &Context) -> ApiResult<Acl> {
+        let raw_roles_sql = "\
+            select unnest(read_roles) as role, 'read' as action from series where id = $1
+            union
+            select unnest(write_roles) as role, 'write' as action from series where id = $1
+        ";
+        acl::load_for(context, raw_roles_sql, dbargs![&self.key]).await
+    }
+
     async fn host_realms(&self, context: &Context) -> ApiResult<Vec<Realm>> {
         let selection = Realm::select();
         let query = format!("\
@@ -149,6 +308,23 @@ impl Series {
     async fn events(&self, order: EventSortOrder, context: &Context) -> ApiResult<Vec<AuthorizedEvent>> {
         AuthorizedEvent::load_for_series(self.key, order, context).await
     }
+
+    /// Like `events`, but paginated. Exactly one of `first` and `last` must
+    /// be set!
+    #[graphql(arguments(order(default = Default::default())))]
+    async fn paginated_events(
+        &self,
+        order: EventSortOrder,
+        first: Option<i32>,
+        after: Option<Cursor>,
+        last: Option<i32>,
+        before: Option<Cursor>,
+        context: &Context,
+    ) -> ApiResult<EventConnection> {
+        AuthorizedEvent::load_connection_for_series(
+            self.key, context, order, first, after, last, before,
+        ).await
+    }
 }
 
 impl Node for Series {
@@ -168,3 +344,121 @@ pub(crate) struct NewSeries {
     // in some way, and since passing stuff like metadata isn't trivial either
     // I think it's okay to leave it at that for now.
 }
+
+
+/// Defines the sort order for series.
+#[derive(Debug, Clone, Copy, juniper::GraphQLInputObject)]
+pub(crate) struct SeriesSortOrder {
+    column: SeriesSortColumn,
+    direction: SortDirection,
+}
+
+#[derive(Debug, Clone, Copy, juniper::GraphQLEnum)]
+enum SeriesSortColumn {
+    Title,
+    Created,
+    Updated,
+    EventCount,
+}
+
+impl Default for SeriesSortOrder {
+    fn default() -> Self {
+        Self {
+            column: SeriesSortColumn::Created,
+            direction: SortDirection::Descending,
+        }
+    }
+}
+
+impl SeriesSortColumn {
+    fn to_sql(self) -> &'static str {
+        match self {
+            SeriesSortColumn::Title => "title",
+            // Waiting series might not have a creation date yet.
+            SeriesSortColumn::Created => "coalesce(created, '-infinity')",
+            SeriesSortColumn::Updated => "updated",
+            SeriesSortColumn::EventCount => "num_events",
+        }
+    }
+}
+
+#[derive(GraphQLObject)]
+#[graphql(Context = Context)]
+pub(crate) struct SeriesConnection {
+    page_info: PageInfo,
+    items: Vec<Series>,
+    total_count: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct SeriesCursor {
+    key: Key,
+    sort_filter: SeriesCursorSortFilter,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum SeriesCursorSortFilter {
+    Title(String),
+    Created(Option<DateTime<Utc>>),
+    Updated(Option<DateTime<Utc>>),
+    EventCount(i64),
+}
+
+impl SeriesCursor {
+    fn new(
+        series: &Series,
+        updated: Option<DateTime<Utc>>,
+        num_events: i64,
+        order: &SeriesSortOrder,
+    ) -> Self {
+        let sort_filter = match order.column {
+            SeriesSortColumn::Title => SeriesCursorSortFilter::Title(series.title.clone()),
+            SeriesSortColumn::Created => SeriesCursorSortFilter::Created(series.created),
+            SeriesSortColumn::Updated => SeriesCursorSortFilter::Updated(updated),
+            SeriesSortColumn::EventCount => SeriesCursorSortFilter::EventCount(num_events),
+        };
+
+        Self {
+            sort_filter,
+            key: series.key,
+        }
+    }
+}
+
+impl PageCursor for SeriesCursor {
+    type Order = SeriesSortOrder;
+
+    fn key(&self) -> &Key {
+        &self.key
+    }
+
+    fn to_sql_arg(&self, order: &SeriesSortOrder) -> ApiResult<&(dyn ToSql + Sync + '_)> {
+        match (&self.sort_filter, order.column) {
+            (SeriesCursorSortFilter::Title(title), SeriesSortColumn::Title) => Ok(title),
+            (SeriesCursorSortFilter::Created(created), SeriesSortColumn::Created) => {
+                match created {
+                    Some(created) => Ok(created),
+                    None => Ok(&Timestamp::<DateTime<Utc>>::NegInfinity),
+                }
+            },
+            (SeriesCursorSortFilter::Updated(updated), SeriesSortColumn::Updated) => {
+                match updated {
+                    Some(updated) => Ok(updated),
+                    None => Ok(&Timestamp::<DateTime<Utc>>::NegInfinity),
+                }
+            },
+            (SeriesCursorSortFilter::EventCount(n), SeriesSortColumn::EventCount) => Ok(n),
+            _ => Err(invalid_input!("sort order does not match 'before'/'after' argument")),
+        }
+    }
+}
+
+/// Like `event::opencast_error`, but for series.
+fn opencast_error(opencast_id: &str, e: anyhow::Error) -> err::ApiError {
+    error!("Failed to send changes of series '{opencast_id}' to Opencast: {e:#}");
+    err::api_err!(
+        InternalServerError,
+        key = "series.opencast-write-failed",
+        "failed to send changes to Opencast",
+    )
+}
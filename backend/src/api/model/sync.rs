@@ -0,0 +1,141 @@
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use juniper::graphql_object;
+
+use crate::{
+    api::{Context, err::ApiResult},
+    db::util::impl_from_db,
+    prelude::*,
+    sync::DEFAULT_SOURCE,
+};
+
+
+/// Information about the synchronization with one Opencast instance (sync
+/// source).
+pub(crate) struct SyncStatus {
+    source: String,
+    harvested_until: DateTime<Utc>,
+}
+
+impl SyncStatus {
+    /// Loads the sync status of all sources that have been harvested at least
+    /// once, the default source first. Only Tobira admins are allowed to do
+    /// that.
+    pub(crate) async fn load_all(context: &Context) -> ApiResult<Vec<Self>> {
+        let query = "select source, harvested_until from sync_status \
+            order by source <> $1, source";
+        context.db(context.require_tobira_admin()?)
+            .query_mapped(query, dbargs![&DEFAULT_SOURCE], |row| Self {
+                source: row.get(0),
+                harvested_until: Utc.from_utc_datetime(&row.get::<_, NaiveDateTime>(1)),
+            })
+            .await?
+            .pipe(Ok)
+    }
+}
+
+#[graphql_object(Context = Context)]
+impl SyncStatus {
+    /// Name of the sync source, as configured in `sync.additional_sources`.
+    /// The source configured via `opencast.sync_node` is called `default`.
+    fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Everything changed in Opencast before this point in time has been
+    /// synced to Tobira.
+    fn harvested_until(&self) -> DateTime<Utc> {
+        self.harvested_until
+    }
+
+    /// How far Tobira's data is behind Opencast, in ms. Note that this is
+    /// never zero, as Opencast only returns changes that are a few seconds
+    /// old.
+    fn lag(&self) -> f64 {
+        (Utc::now() - self.harvested_until).num_milliseconds() as f64
+    }
+
+    /// The last harvest run that did not fail.
+    async fn last_successful_run(&self, context: &Context) -> ApiResult<Option<HarvestRun>> {
+        let selection = HarvestRun::select();
+        let query = format!("select {selection} from harvest_runs \
+            where error is null and source = $1 \
+            order by started desc \
+            limit 1");
+        context.db.query_opt(&query, &[&self.source])
+            .await?
+            .map(|row| HarvestRun::from_row_start(&row))
+            .pipe(Ok)
+    }
+
+    /// The most recent harvest runs, newest first. At most 100 runs are
+    /// returned.
+    async fn recent_runs(
+        &self,
+        #[graphql(default = 20)]
+        limit: i32,
+        context: &Context,
+    ) -> ApiResult<Vec<HarvestRun>> {
+        let limit = limit.clamp(0, 100) as i64;
+        let selection = HarvestRun::select();
+        let query = format!("select {selection} from harvest_runs \
+            where source = $1 \
+            order by started desc \
+            limit $2");
+        context.db
+            .query_mapped(&query, dbargs![&self.source, &limit], |row| {
+                HarvestRun::from_row_start(&row)
+            })
+            .await?
+            .pipe(Ok)
+    }
+}
+
+/// A single request to Opencast's harvest API and its outcome.
+#[derive(Debug, juniper::GraphQLObject)]
+pub(crate) struct HarvestRun {
+    started: DateTime<Utc>,
+    /// Duration in ms.
+    duration: f64,
+    /// The value of `harvestedUntil` at the start of this run.
+    since: DateTime<Utc>,
+    /// Opencast returned all changes before this point in time. `null` if the
+    /// run failed.
+    includes_items_until: Option<DateTime<Utc>>,
+    upserted_events: i32,
+    removed_events: i32,
+    upserted_series: i32,
+    removed_series: i32,
+    upserted_playlists: i32,
+    removed_playlists: i32,
+    /// The error message if the run failed.
+    error: Option<String>,
+    /// How long Tobira waited before retrying after this run failed, in ms.
+    backoff: Option<f64>,
+}
+
+impl_from_db!(
+    HarvestRun,
+    select: {
+        harvest_runs.{
+            started, duration_ms, since, includes_items_until,
+            upserted_events, removed_events, upserted_series, removed_series,
+            upserted_playlists, removed_playlists, error, backoff_ms,
+        },
+    },
+    |row| {
+        Self {
+            started: row.started(),
+            duration: row.duration_ms::<i64>() as f64,
+            since: row.since(),
+            includes_items_until: row.includes_items_until(),
+            upserted_events: row.upserted_events(),
+            removed_events: row.removed_events(),
+            upserted_series: row.upserted_series(),
+            removed_series: row.removed_series(),
+            upserted_playlists: row.upserted_playlists(),
+            removed_playlists: row.removed_playlists(),
+            error: row.error(),
+            backoff: row.backoff_ms::<Option<i64>>().map(|ms| ms as f64),
+        }
+    },
+);
@@ -0,0 +1,203 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use juniper::graphql_object;
+
+use crate::{
+    api::{Context, Id, err::ApiResult},
+    auth::AuthContext,
+    config::Config,
+    db::{DbConnection, types::Key, util::{impl_from_db, select}},
+    prelude::*,
+    util::Never,
+};
+
+
+/// A removed realm (including all its descendants) or block that can still be
+/// restored. Entries are purged after `general.trash_retention`.
+pub(crate) struct TrashEntry {
+    pub(crate) realm: Option<Key>,
+    pub(crate) block: Option<Key>,
+    pub(crate) parent: Option<Key>,
+    pub(crate) path: String,
+    pub(crate) data: serde_json::Value,
+    pub(crate) removed: DateTime<Utc>,
+    pub(crate) removed_by: Option<String>,
+}
+
+impl_from_db!(
+    TrashEntry,
+    select: {
+        trash.{ realm, block, parent, path, data, removed, removed_by },
+    },
+    |row| {
+        Self {
+            realm: row.realm(),
+            block: row.block(),
+            parent: row.parent(),
+            path: row.path(),
+            data: row.data(),
+            removed: row.removed(),
+            removed_by: row.removed_by(),
+        }
+    },
+);
+
+#[graphql_object(Context = Context)]
+impl TrashEntry {
+    /// ID of the removed realm or block. Pass it to `restoreRealm` or
+    /// `restoreBlock`, respectively.
+    fn id(&self) -> Id {
+        match (self.realm, self.block) {
+            (Some(realm), _) => Id::realm(realm),
+            (None, Some(block)) => Id::block(block),
+            (None, None) => unreachable!("trash entry without realm and block"),
+        }
+    }
+
+    /// The path of the removed realm, or of the realm the removed block was in.
+    fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// For removed realms: the name (`null` if derived from a block). For
+    /// removed title and text blocks: their content.
+    fn name(&self) -> Option<&str> {
+        let field = if self.realm.is_some() {
+            self.data.get(0).and_then(|realm| realm.get("name"))
+        } else {
+            self.data.get("text_content")
+        };
+        field.and_then(|v| v.as_str())
+    }
+
+    fn removed(&self) -> DateTime<Utc> {
+        self.removed
+    }
+
+    /// The user who removed the realm or block. `null` if it was removed by a
+    /// trusted external application.
+    fn removed_by(&self) -> Option<&str> {
+        self.removed_by.as_deref()
+    }
+
+    /// When this entry will be purged and can no longer be restored. `null`
+    /// if the retention period is so long that this cannot be represented.
+    fn purged_after(&self, context: &Context) -> Option<DateTime<Utc>> {
+        chrono::Duration::from_std(context.config.general.trash_retention)
+            .ok()
+            .and_then(|retention| self.removed.checked_add_signed(retention))
+    }
+}
+
+impl TrashEntry {
+    /// Loads all entries (removed child realms and removed blocks) of the given
+    /// realm, newest first.
+    pub(crate) async fn load_for_realm(realm: Key, context: &Context) -> ApiResult<Vec<Self>> {
+        let selection = Self::select();
+        let query = format!("select {selection} from trash \
+            where parent = $1 \
+            order by removed desc");
+        context.db
+            .query_mapped(&query, dbargs![&realm], |row| Self::from_row_start(&row))
+            .await?
+            .pipe(Ok)
+    }
+
+    pub(crate) async fn load_for_realm_item(
+        realm: Key,
+        context: &Context,
+    ) -> ApiResult<Option<Self>> {
+        Self::load_by("realm", realm, context).await
+    }
+
+    pub(crate) async fn load_for_block_item(
+        block: Key,
+        context: &Context,
+    ) -> ApiResult<Option<Self>> {
+        Self::load_by("block", block, context).await
+    }
+
+    async fn load_by(column: &str, key: Key, context: &Context) -> ApiResult<Option<Self>> {
+        let selection = Self::select();
+        let query = format!("select {selection} from trash where {column} = $1");
+        context.db
+            .query_opt(&query, &[&key])
+            .await?
+            .map(|row| Self::from_row_start(&row))
+            .pipe(Ok)
+    }
+
+    /// Moves the given realm and all its descendants into the trash. The realm
+    /// itself is not deleted by this function.
+    pub(crate) async fn add_realm(realm: Key, context: &Context) -> ApiResult<()> {
+        context.db
+            .execute(
+                "insert into trash (realm, parent, path, data, removed_by) \
+                    select id, parent, full_path, \
+                        (select jsonb_agg(realm_snapshot(r.id) order by length(r.full_path)) \
+                            from realms r \
+                            where r.id = realms.id \
+                                or starts_with(r.full_path, realms.full_path || '/')), \
+                        $2 \
+                    from realms \
+                    where id = $1",
+                &[&realm, &username(context)],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Moves the given block into the trash. The block itself is not deleted
+    /// by this function.
+    pub(crate) async fn add_block(block: Key, context: &Context) -> ApiResult<()> {
+        context.db
+            .execute(
+                "insert into trash (block, parent, path, data, removed_by) \
+                    select blocks.id, realms.id, realms.full_path, to_jsonb(blocks), $2 \
+                    from blocks \
+                    inner join realms on realms.id = blocks.realm \
+                    where blocks.id = $1",
+                &[&block, &username(context)],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Removes this entry from the trash, after it was restored.
+    pub(crate) async fn remove(&self, context: &Context) -> ApiResult<()> {
+        context.db
+            .execute(
+                "delete from trash where realm = $1 or block = $2",
+                &[&self.realm, &self.block],
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+fn username(context: &Context) -> Option<&str> {
+    match &context.auth {
+        AuthContext::User(user) => Some(&user.username),
+        _ => None,
+    }
+}
+
+/// Long running task that purges expired trash entries.
+pub(crate) async fn run_purge_daemon(db: DbConnection, config: &Config) -> Result<Never> {
+    /// How often to check for expired entries. The retention period is usually
+    /// days, so this does not need to be precise.
+    const RUN_PERIOD: Duration = Duration::from_secs(60 * 60);
+
+    loop {
+        let sql = "delete from trash \
+            where extract(epoch from now() - removed) > $1::double precision";
+        match db.execute(sql, &[&config.general.trash_retention.as_secs_f64()]).await {
+            Err(e) => error!("Error purging expired trash entries: {e}"),
+            Ok(0) => debug!("No expired trash entries found in DB"),
+            Ok(num) => info!("Purged {num} expired trash entries from DB"),
+        }
+
+        tokio::time::sleep(RUN_PERIOD).await;
+    }
+}
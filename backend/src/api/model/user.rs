@@ -3,7 +3,10 @@ use crate::{
         Context,
         common::Cursor,
         err::ApiResult,
-        model::event::{AuthorizedEvent, EventConnection, EventSortOrder},
+        model::{
+            event::{AuthorizedEvent, EventConnection, EventFilter, EventSortOrder},
+            series::{Series, SeriesConnection, SeriesSortOrder},
+        },
     },
     auth::User,
     prelude::*,
@@ -72,12 +75,31 @@ impl User {
     async fn my_videos(
         &self,
         order: EventSortOrder,
+        filter: Option<EventFilter>,
         first: Option<i32>,
         after: Option<Cursor>,
         last: Option<i32>,
         before: Option<Cursor>,
         context: &Context,
     ) -> ApiResult<EventConnection> {
-        AuthorizedEvent::load_writable_for_user(context, order, first, after, last, before).await
+        let filter = filter.unwrap_or_default();
+        AuthorizedEvent::load_writable_for_user(context, order, filter, first, after, last, before)
+            .await
+    }
+
+    /// Returns all series the user has write access to.
+    ///
+    /// Exactly one of `first` and `last` must be set!
+    #[graphql(arguments(order(default = Default::default())))]
+    async fn writable_series(
+        &self,
+        order: SeriesSortOrder,
+        first: Option<i32>,
+        after: Option<Cursor>,
+        last: Option<i32>,
+        before: Option<Cursor>,
+        context: &Context,
+    ) -> ApiResult<SeriesConnection> {
+        Series::load_writable_for_user(context, order, first, after, last, before).await
     }
 }
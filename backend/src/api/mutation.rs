@@ -1,3 +1,4 @@
+use chrono::{DateTime, Utc};
 use juniper::graphql_object;
 
 use crate::auth::AuthContext;
@@ -7,12 +8,17 @@ use super::{
     id::Id,
     Node,
     model::{
+        acl::AclInputEntry,
+        audit_log::{audited, AuditTarget},
+        event::{AuthorizedEvent, EventMetadataUpdate},
         series::{Series, NewSeries},
         realm::{
+            BlockTable,
             ChildIndex,
             NewRealm,
             Realm,
             RealmOrder,
+            RealmDraft,
             RemovedRealm,
             UpdatedPermissions,
             UpdatedRealmName,
@@ -24,11 +30,17 @@ use super::{
             NewTitleBlock,
             NewTextBlock,
             NewSeriesBlock,
+            NewPlaylistBlock,
             NewVideoBlock,
+            NewRealmListBlock,
+            NewEmbedBlock,
             UpdateTitleBlock,
             UpdateTextBlock,
             UpdateSeriesBlock,
+            UpdatePlaylistBlock,
             UpdateVideoBlock,
+            UpdateRealmListBlock,
+            UpdateEmbedBlock,
             RemovedBlock,
             VideoListOrder,
             VideoListLayout,
@@ -44,12 +56,17 @@ pub(crate) struct Mutation;
 impl Mutation {
     /// Adds a new realm.
     async fn add_realm(realm: NewRealm, context: &Context) -> ApiResult<Realm> {
-        Realm::add(realm, context).await
+        audited("addRealm", AuditTarget::NewRealm, context, Realm::add(realm, context)).await
     }
 
     /// Creates the current users realm. Errors if it already exists.
     async fn create_my_user_realm(context: &Context) -> ApiResult<Realm> {
-        Realm::create_user_realm(context).await
+        audited(
+            "createMyUserRealm",
+            AuditTarget::NewRealm,
+            context,
+            Realm::create_user_realm(context),
+        ).await
     }
 
     /// Sets the order of all children of a specific realm.
@@ -67,27 +84,65 @@ impl Mutation {
         child_indices: Option<Vec<ChildIndex>>,
         context: &Context,
     ) -> ApiResult<Realm> {
-        Realm::set_child_order(parent, child_order, child_indices, context).await
+        audited(
+            "setChildOrder",
+            AuditTarget::Realm(parent),
+            context,
+            Realm::set_child_order(parent, child_order, child_indices, context),
+        ).await
     }
 
     /// Changes the name of a realm.
     async fn rename_realm(id: Id, name: UpdatedRealmName, context: &Context) -> ApiResult<Realm> {
-        Realm::rename(id, name, context).await
+        audited("renameRealm", AuditTarget::Realm(id), context, Realm::rename(id, name, context))
+            .await
     }
 
     /// Changes the moderator and/or admin roles of a realm.
     async fn update_permissions(id: Id, permissions: UpdatedPermissions, context: &Context) -> ApiResult<Realm> {
-        Realm::update_permissions(id, permissions, context).await
+        audited(
+            "updatePermissions",
+            AuditTarget::Realm(id),
+            context,
+            Realm::update_permissions(id, permissions, context),
+        ).await
     }
 
     /// Updates a realm's data.
     async fn update_realm(id: Id, set: UpdateRealm, context: &Context) -> ApiResult<Realm> {
-        Realm::update(id, set, context).await
+        audited("updateRealm", AuditTarget::Realm(id), context, Realm::update(id, set, context))
+            .await
+    }
+
+    /// Moves a realm and all its descendants to a new parent. The realm is
+    /// placed at position `index` among the children of the new parent (if
+    /// those are ordered by index). Requires page admin rights for both, the
+    /// realm and the new parent.
+    async fn move_realm(
+        id: Id,
+        new_parent: Id,
+        index: i32,
+        context: &Context,
+    ) -> ApiResult<Realm> {
+        audited(
+            "moveRealm",
+            AuditTarget::Realm(id),
+            context,
+            Realm::move_to(id, new_parent, index, context),
+        ).await
     }
 
-    /// Remove a realm from the tree.
+    /// Remove a realm from the tree. The realm and all its descendants are
+    /// moved to the trash, from where they can be restored with `restoreRealm`.
     async fn remove_realm(id: Id, context: &Context) -> ApiResult<RemovedRealm> {
-        Realm::remove(id, context).await
+        let removal = Realm::remove(id, context);
+        audited("removeRealm", AuditTarget::RealmTree(id), context, removal).await
+    }
+
+    /// Restores a removed realm (including all its descendants, blocks and
+    /// drafts) from the trash. Requires page admin rights for the parent realm.
+    async fn restore_realm(id: Id, context: &Context) -> ApiResult<Realm> {
+        audited("restoreRealm", AuditTarget::NewRealm, context, Realm::restore(id, context)).await
     }
 
     /// Adds a title block to a realm.
@@ -103,7 +158,12 @@ impl Mutation {
         block: NewTitleBlock,
         context: &Context,
     ) -> ApiResult<Realm> {
-        BlockValue::add_title(realm, index, block, context).await
+        audited(
+            "addTitleBlock",
+            AuditTarget::Realm(realm),
+            context,
+            BlockValue::add_title(realm, index, block, context),
+        ).await
     }
 
     /// Adds a text block to a realm.
@@ -115,7 +175,12 @@ impl Mutation {
         block: NewTextBlock,
         context: &Context,
     ) -> ApiResult<Realm> {
-        BlockValue::add_text(realm, index, block, context).await
+        audited(
+            "addTextBlock",
+            AuditTarget::Realm(realm),
+            context,
+            BlockValue::add_text(realm, index, block, context),
+        ).await
     }
 
     /// Adds a series block to a realm.
@@ -127,7 +192,29 @@ impl Mutation {
         block: NewSeriesBlock,
         context: &Context,
     ) -> ApiResult<Realm> {
-        BlockValue::add_series(realm, index, block, context).await
+        audited(
+            "addSeriesBlock",
+            AuditTarget::Realm(realm),
+            context,
+            BlockValue::add_series(realm, index, block, context),
+        ).await
+    }
+
+    /// Adds a playlist block to a realm.
+    ///
+    /// See `addTitleBlock` for more details.
+    async fn add_playlist_block(
+        realm: Id,
+        index: i32,
+        block: NewPlaylistBlock,
+        context: &Context,
+    ) -> ApiResult<Realm> {
+        audited(
+            "addPlaylistBlock",
+            AuditTarget::Realm(realm),
+            context,
+            BlockValue::add_playlist(realm, index, block, context),
+        ).await
     }
 
     /// Adds a video block to a realm.
@@ -139,7 +226,47 @@ impl Mutation {
         block: NewVideoBlock,
         context: &Context,
     ) -> ApiResult<Realm> {
-        BlockValue::add_video(realm, index, block, context).await
+        audited(
+            "addVideoBlock",
+            AuditTarget::Realm(realm),
+            context,
+            BlockValue::add_video(realm, index, block, context),
+        ).await
+    }
+
+    /// Adds a realm list block to a realm.
+    ///
+    /// See `addTitleBlock` for more details.
+    async fn add_realm_list_block(
+        realm: Id,
+        index: i32,
+        block: NewRealmListBlock,
+        context: &Context,
+    ) -> ApiResult<Realm> {
+        audited(
+            "addRealmListBlock",
+            AuditTarget::Realm(realm),
+            context,
+            BlockValue::add_realm_list(realm, index, block, context),
+        ).await
+    }
+
+    /// Adds an embed block to a realm. The URL has to belong to one of the
+    /// configured `general.embed_origins`.
+    ///
+    /// See `addTitleBlock` for more details.
+    async fn add_embed_block(
+        realm: Id,
+        index: i32,
+        block: NewEmbedBlock,
+        context: &Context,
+    ) -> ApiResult<Realm> {
+        audited(
+            "addEmbedBlock",
+            AuditTarget::Realm(realm),
+            context,
+            BlockValue::add_embed(realm, index, block, context),
+        ).await
     }
 
     /// Swap two blocks.
@@ -149,7 +276,12 @@ impl Mutation {
         index_b: i32,
         context: &Context,
     ) -> ApiResult<Realm> {
-        BlockValue::swap_by_index(realm, index_a, index_b, context).await
+        audited(
+            "swapBlocksByIndex",
+            AuditTarget::Realm(realm),
+            context,
+            BlockValue::swap_by_index(realm, index_a, index_b, context),
+        ).await
     }
 
     /// Update a title block's data.
@@ -158,7 +290,12 @@ impl Mutation {
         set: UpdateTitleBlock,
         context: &Context,
     ) -> ApiResult<BlockValue> {
-        BlockValue::update_title(id, set, context).await
+        audited(
+            "updateTitleBlock",
+            AuditTarget::Block(id),
+            context,
+            BlockValue::update_title(id, set, context),
+        ).await
     }
 
     /// Update a text block's data.
@@ -167,7 +304,12 @@ impl Mutation {
         set: UpdateTextBlock,
         context: &Context,
     ) -> ApiResult<BlockValue> {
-        BlockValue::update_text(id, set, context).await
+        audited(
+            "updateTextBlock",
+            AuditTarget::Block(id),
+            context,
+            BlockValue::update_text(id, set, context),
+        ).await
     }
 
     /// Update a series block's data.
@@ -176,7 +318,26 @@ impl Mutation {
         set: UpdateSeriesBlock,
         context: &Context,
     ) -> ApiResult<BlockValue> {
-        BlockValue::update_series(id, set, context).await
+        audited(
+            "updateSeriesBlock",
+            AuditTarget::Block(id),
+            context,
+            BlockValue::update_series(id, set, context),
+        ).await
+    }
+
+    /// Update a playlist block's data.
+    async fn update_playlist_block(
+        id: Id,
+        set: UpdatePlaylistBlock,
+        context: &Context,
+    ) -> ApiResult<BlockValue> {
+        audited(
+            "updatePlaylistBlock",
+            AuditTarget::Block(id),
+            context,
+            BlockValue::update_playlist(id, set, context),
+        ).await
     }
 
     /// Update a video block's data.
@@ -185,12 +346,142 @@ impl Mutation {
         set: UpdateVideoBlock,
         context: &Context,
     ) -> ApiResult<BlockValue> {
-        BlockValue::update_video(id, set, context).await
+        audited(
+            "updateVideoBlock",
+            AuditTarget::Block(id),
+            context,
+            BlockValue::update_video(id, set, context),
+        ).await
+    }
+
+    /// Update a realm list block's data.
+    async fn update_realm_list_block(
+        id: Id,
+        set: UpdateRealmListBlock,
+        context: &Context,
+    ) -> ApiResult<BlockValue> {
+        audited(
+            "updateRealmListBlock",
+            AuditTarget::Block(id),
+            context,
+            BlockValue::update_realm_list(id, set, context),
+        ).await
+    }
+
+    /// Update an embed block's data.
+    async fn update_embed_block(
+        id: Id,
+        set: UpdateEmbedBlock,
+        context: &Context,
+    ) -> ApiResult<BlockValue> {
+        audited(
+            "updateEmbedBlock",
+            AuditTarget::Block(id),
+            context,
+            BlockValue::update_embed(id, set, context),
+        ).await
     }
 
-    /// Remove a block from a realm.
+    /// Sets the time window in which a block is visible to users without
+    /// moderator rights. Both bounds are optional: `null` removes the
+    /// respective bound.
+    async fn update_block_visibility(
+        id: Id,
+        visible_from: Option<DateTime<Utc>>,
+        visible_until: Option<DateTime<Utc>>,
+        context: &Context,
+    ) -> ApiResult<BlockValue> {
+        audited(
+            "updateBlockVisibility",
+            AuditTarget::Block(id),
+            context,
+            BlockValue::update_visibility(id, visible_from, visible_until, context),
+        ).await
+    }
+
+    /// Remove a block from a realm. The block is moved to the trash, from
+    /// where it can be restored with `restoreBlock`.
     async fn remove_block(id: Id, context: &Context) -> ApiResult<RemovedBlock> {
-        BlockValue::remove(id, context).await
+        audited("removeBlock", AuditTarget::Block(id), context, BlockValue::remove(id, context))
+            .await
+    }
+
+    /// Restores a removed block from the trash, at its previous position.
+    /// Requires page admin rights for the realm.
+    async fn restore_block(id: Id, context: &Context) -> ApiResult<Realm> {
+        audited("restoreBlock", AuditTarget::Block(id), context, BlockValue::restore(id, context))
+            .await
+    }
+
+    /// Starts a draft for the given realm. Until the draft is published or
+    /// discarded, all block mutations of the realm only change the draft,
+    /// which moderators can preview via `Realm.draft`.
+    async fn start_realm_draft(realm: Id, context: &Context) -> ApiResult<Realm> {
+        audited(
+            "startRealmDraft",
+            AuditTarget::Realm(realm),
+            context,
+            RealmDraft::start(realm, context),
+        ).await
+    }
+
+    /// Atomically replaces the blocks of the given realm with those of its
+    /// draft, and removes the draft.
+    async fn publish_realm_draft(realm: Id, context: &Context) -> ApiResult<Realm> {
+        audited(
+            "publishRealmDraft",
+            AuditTarget::Realm(realm),
+            context,
+            RealmDraft::publish(realm, context),
+        ).await
+    }
+
+    /// Removes the draft of the given realm without publishing it.
+    async fn discard_realm_draft(realm: Id, context: &Context) -> ApiResult<Realm> {
+        audited(
+            "discardRealmDraft",
+            AuditTarget::Realm(realm),
+            context,
+            RealmDraft::discard(realm, context),
+        ).await
+    }
+
+    /// Changes the metadata of an event in Opencast (via its External API).
+    /// The changes are visible in Tobira immediately, but the event is marked
+    /// as having a pending write until the next harvest confirms them.
+    async fn update_event_metadata(
+        id: Id,
+        metadata: EventMetadataUpdate,
+        context: &Context,
+    ) -> ApiResult<AuthorizedEvent> {
+        AuthorizedEvent::update_metadata(id, metadata, context).await
+    }
+
+    /// Deletes an event in Opencast. The event stays in Tobira, marked as
+    /// pending deletion, until the harvest reports it as deleted (or until
+    /// `sync.deletion_timeout` has passed).
+    async fn delete_event(id: Id, context: &Context) -> ApiResult<AuthorizedEvent> {
+        AuthorizedEvent::delete(id, context).await
+    }
+
+    /// Replaces the ACL of an event, both in Opencast and (until the next
+    /// harvest confirms it) in Tobira. The current user has to have write
+    /// access to the event and has to retain it with the new ACL.
+    async fn update_event_acl(
+        id: Id,
+        acl: Vec<AclInputEntry>,
+        context: &Context,
+    ) -> ApiResult<AuthorizedEvent> {
+        AuthorizedEvent::update_acl(id, acl, context).await
+    }
+
+    /// Like `updateEventAcl`, but for series.
+    async fn update_series_acl(
+        id: Id,
+        acl: Vec<AclInputEntry>,
+        context: &Context,
+    ) -> ApiResult<Series> {
+        Series::update_acl(id, acl, context).await
     }
 
     /// Atomically mount a series into an (empty) realm.
@@ -203,68 +494,90 @@ impl Mutation {
         new_realms: Vec<RealmSpecifier>,
         context: &Context,
     ) -> ApiResult<Realm> {
-        // Note: This is a rather ad hoc, use-case specific compound mutation.
-        // So for the sake of simplicity and being able to change it fast
-        // we just reuse all the mutations we already have.
-        // Once this code stabilizes, we might want to change that,
-        // because doing it like this duplicates some work
-        // like checking moderator rights, input validity, etc.
-
         if context.auth != AuthContext::TrustedExternal {
             return Err(not_authorized!("only trusted external applications can use this mutation"));
         }
 
-        if new_realms.iter().rev().skip(1).any(|r| r.name.is_none()) {
-            return Err(invalid_input!("all new realms except the last need to have a name"));
-        }
-
         let parent_realm = Realm::load_by_path(parent_realm_path, context)
             .await?
             .ok_or_else(|| invalid_input!("`parentRealmPath` does not refer to a valid realm"))?;
 
-        if new_realms.is_empty() {
-            let blocks = BlockValue::load_for_realm(parent_realm.key, context).await?;
-            if !blocks.is_empty() {
-                return Err(invalid_input!("series can only be mounted in empty realms"));
-            }
-        }
-
-        let series = Series::create(series, context).await?;
-
-        let target_realm = {
-            let mut target_realm = parent_realm;
-            for RealmSpecifier { name, path_segment } in new_realms {
-                target_realm = Realm::add(NewRealm {
-                    // The `unwrap_or` case is only potentially used for the
-                    // last realm, which is renamed below anyway. See the check
-                    // above.
-                    name: name.unwrap_or_else(|| "temporary-dummy-name".into()),
-                    path_segment,
-                    parent: Id::realm(target_realm.key),
-                }, context).await?
-            }
-            target_realm
+        // Without new realms, the series is mounted into the existing parent
+        // realm, so that is the realm changed by this mutation.
+        let target = if new_realms.is_empty() {
+            AuditTarget::Realm(parent_realm.id())
+        } else {
+            AuditTarget::NewRealm
         };
+        let mount = mount_series(series, parent_realm, new_realms, context);
+        audited("mountSeries", target, context, mount).await
+    }
+}
 
-        BlockValue::add_series(
-            Id::realm(target_realm.key),
-            0,
-            NewSeriesBlock {
-                series: series.id(),
-                show_title: false,
-                show_metadata: true,
-                order: VideoListOrder::NewToOld,
-                layout: VideoListLayout::Gallery,
-            },
-            context,
-        ).await?;
+/// The actual implementation of `Mutation::mount_series`, which only wraps it
+/// in `audited`. The caller has to check that the user is allowed to use this
+/// mutation.
+async fn mount_series(
+    series: NewSeries,
+    parent_realm: Realm,
+    new_realms: Vec<RealmSpecifier>,
+    context: &Context,
+) -> ApiResult<Realm> {
+    // Note: This is a rather ad hoc, use-case specific compound mutation.
+    // So for the sake of simplicity and being able to change it fast
+    // we just reuse all the mutations we already have.
+    // Once this code stabilizes, we might want to change that,
+    // because doing it like this duplicates some work
+    // like checking moderator rights, input validity, etc.
 
-        let block = &BlockValue::load_for_realm(target_realm.key, context).await?[0];
+    if new_realms.iter().rev().skip(1).any(|r| r.name.is_none()) {
+        return Err(invalid_input!("all new realms except the last need to have a name"));
+    }
 
-        Realm::rename(
-            target_realm.id(),
-            UpdatedRealmName::from_block(block.id()),
-            context,
-        ).await
+    if new_realms.is_empty() {
+        let blocks = BlockValue::load_for_realm(parent_realm.key, context).await?;
+        let has_draft = BlockTable::for_realm(parent_realm.key, context).await?
+            == BlockTable::Draft;
+        if !blocks.is_empty() || has_draft {
+            return Err(invalid_input!("series can only be mounted in empty realms"));
+        }
     }
+
+    let series = Series::create(series, context).await?;
+
+    let target_realm = {
+        let mut target_realm = parent_realm;
+        for RealmSpecifier { name, path_segment } in new_realms {
+            target_realm = Realm::add(NewRealm {
+                // The `unwrap_or` case is only potentially used for the
+                // last realm, which is renamed below anyway. See the check
+                // above.
+                name: name.unwrap_or_else(|| "temporary-dummy-name".into()),
+                path_segment,
+                parent: Id::realm(target_realm.key),
+            }, context).await?
+        }
+        target_realm
+    };
+
+    BlockValue::add_series(
+        Id::realm(target_realm.key),
+        0,
+        NewSeriesBlock {
+            series: series.id(),
+            show_title: false,
+            show_metadata: true,
+            order: VideoListOrder::NewToOld,
+            layout: VideoListLayout::Gallery,
+        },
+        context,
+    ).await?;
+
+    let block = &BlockValue::load_for_realm(target_realm.key, context).await?[0];
+
+    Realm::rename(
+        target_realm.id(),
+        UpdatedRealmName::from_block(block.id()),
+        context,
+    ).await
 }
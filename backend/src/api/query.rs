@@ -1,7 +1,10 @@
 use juniper::graphql_object;
 
 
-use crate::auth::{AuthContext, User};
+use crate::{
+    auth::{AuthContext, User},
+    sync::DEFAULT_SOURCE,
+};
 
 use super::{
     err::ApiResult,
@@ -9,9 +12,11 @@ use super::{
     model::{
         event::{AuthorizedEvent, Event},
         known_roles::{self, KnownGroup, KnownUsersSearchOutcome},
+        playlist::{AuthorizedPlaylist, Playlist},
         realm::Realm,
         search::{self, EventSearchOutcome, Filters, SearchOutcome, SeriesSearchOutcome},
         series::Series,
+        sync::SyncStatus,
     },
     Context,
     Id,
@@ -42,13 +47,26 @@ impl Query {
     /// The paths `""` and `"/"` refer to the root realm. All other paths have
     /// to start with `"/"`. Paths starting with `"/@"` are considered user
     /// root realms.
+    ///
+    /// Previous paths of realms (before they were moved or their path segment
+    /// was changed) also resolve to the realm, as long as no other realm
+    /// reclaimed that path. Compare the returned `path` with the requested one
+    /// to detect that.
     async fn realm_by_path(path: String, context: &Context) -> ApiResult<Option<Realm>> {
-        Realm::load_by_path(path, context).await
+        Realm::load_by_current_or_old_path(path, context).await
     }
 
     /// Returns an event by its Opencast ID.
-    async fn event_by_opencast_id(id: String, context: &Context) -> ApiResult<Option<Event>> {
-        AuthorizedEvent::load_by_opencast_id(id, context).await
+    ///
+    /// As Opencast IDs are only unique per sync source, `source` can be
+    /// specified. It defaults to the default sync source.
+    async fn event_by_opencast_id(
+        id: String,
+        source: Option<String>,
+        context: &Context,
+    ) -> ApiResult<Option<Event>> {
+        let source = source.as_deref().unwrap_or(DEFAULT_SOURCE);
+        AuthorizedEvent::load_by_opencast_id(id, source, context).await
     }
 
     /// Returns an event by its ID.
@@ -57,8 +75,16 @@ impl Query {
     }
 
     /// Returns a series by its Opencast ID.
-    async fn series_by_opencast_id(id: String, context: &Context) -> ApiResult<Option<Series>> {
-        Series::load_by_opencast_id(id, context).await
+    ///
+    /// As Opencast IDs are only unique per sync source, `source` can be
+    /// specified. It defaults to the default sync source.
+    async fn series_by_opencast_id(
+        id: String,
+        source: Option<String>,
+        context: &Context,
+    ) -> ApiResult<Option<Series>> {
+        let source = source.as_deref().unwrap_or(DEFAULT_SOURCE);
+        Series::load_by_opencast_id(id, source, context).await
     }
 
     /// Returns a series by its ID.
@@ -66,6 +92,24 @@ impl Query {
         Series::load_by_id(id, context).await
     }
 
+    /// Returns a playlist by its Opencast ID.
+    ///
+    /// As Opencast IDs are only unique per sync source, `source` can be
+    /// specified. It defaults to the default sync source.
+    async fn playlist_by_opencast_id(
+        id: String,
+        source: Option<String>,
+        context: &Context,
+    ) -> ApiResult<Option<Playlist>> {
+        let source = source.as_deref().unwrap_or(DEFAULT_SOURCE);
+        AuthorizedPlaylist::load_by_opencast_id(id, source, context).await
+    }
+
+    /// Returns a playlist by its ID.
+    async fn playlist_by_id(id: Id, context: &Context) -> ApiResult<Option<Playlist>> {
+        AuthorizedPlaylist::load_by_id(id, context).await
+    }
+
     /// Returns the current user.
     fn current_user(context: &Context) -> Option<&User> {
         match &context.auth {
@@ -87,6 +131,9 @@ impl Query {
             Id::EVENT_KIND => AuthorizedEvent::load_by_id(id, context).await?
                 .map(|e| e.into_result().map(NodeValue::from))
                 .transpose(),
+            Id::PLAYLIST_KIND => AuthorizedPlaylist::load_by_id(id, context).await?
+                .map(|p| p.into_result().map(NodeValue::from))
+                .transpose(),
             _ => Ok(None),
         }
     }
@@ -139,4 +186,10 @@ impl Query {
     async fn known_groups(context: &Context) -> ApiResult<Vec<KnownGroup>> {
         KnownGroup::load_all(context).await
     }
+
+    /// Returns information about the synchronization with Opencast, one
+    /// entry per sync source. Only accessible for Tobira admins.
+    async fn sync_status(context: &Context) -> ApiResult<Vec<SyncStatus>> {
+        SyncStatus::load_all(context).await
+    }
 }
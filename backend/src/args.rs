@@ -66,8 +66,8 @@ pub(crate) enum Command {
     /// Starts a worker/daemon process that performs all tasks that should be
     /// performed regularly.
     ///
-    /// This currently includes: updating the search index and syncing with
-    /// Opencast.
+    /// This currently includes: updating the search index, syncing with
+    /// Opencast and purging expired trash entries.
     Worker {
         #[clap(flatten)]
         shared: Shared,
@@ -106,6 +106,26 @@ pub(crate) enum Command {
         shared: Shared,
     },
 
+    /// Exporting realm trees (including blocks and permissions) to YAML files
+    /// and importing them again, e.g. into another Tobira instance.
+    Realm {
+        #[clap(subcommand)]
+        options: cmd::realm::Args,
+
+        #[clap(flatten)]
+        shared: Shared,
+    },
+
+    /// Exporting the audit log, which records all changes to realms and their
+    /// blocks made via the API.
+    AuditLog {
+        #[clap(subcommand)]
+        options: cmd::audit_log::Args,
+
+        #[clap(flatten)]
+        shared: Shared,
+    },
+
     /// Listing, adding, and removing known groups.
     KnownGroups {
         #[clap(subcommand)]
@@ -366,6 +366,24 @@ pub(crate) struct RoleConfig {
     pub(crate) user_role_prefixes: Vec<String>,
 }
 
+impl RoleConfig {
+    /// Returns `true` if `role` is one of the roles above granting Tobira
+    /// privileges. Those make no sense in the ACL of an event or series.
+    /// `user_realm` is excluded as it is usually a role every user has (e.g.
+    /// `ROLE_USER`), which is commonly used in ACLs.
+    pub(crate) fn is_privilege_role(&self, role: &str) -> bool {
+        [
+            &self.tobira_admin,
+            &self.upload,
+            &self.studio,
+            &self.editor,
+            &self.can_find_unlisted,
+            &self.global_page_admin,
+            &self.global_page_moderator,
+        ].into_iter().any(|privilege_role| privilege_role == role)
+    }
+}
+
 
 pub(super) fn deserialize_callback_headers<'de, D>(
     deserializer: D,
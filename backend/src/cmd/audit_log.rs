@@ -0,0 +1,95 @@
+use std::{io::Write, path::PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::{
+    prelude::*,
+    config::Config,
+    db,
+};
+
+
+#[derive(Debug, clap::Parser)]
+pub(crate) enum Args {
+    /// Exports the audit log (all mutations that changed realms or blocks) as
+    /// JSON, one entry per line, oldest first.
+    Export {
+        /// Only export entries of the realm with this path and its
+        /// descendants, e.g. `/lectures`. Paths at the time of the mutation
+        /// are compared, so entries of removed realms are included.
+        #[clap(long)]
+        realm: Option<String>,
+
+        /// Only export entries newer than this timestamp, e.g.
+        /// `2024-05-01T00:00:00Z`.
+        #[clap(long)]
+        since: Option<DateTime<Utc>>,
+
+        /// File to write the entries to. If not specified, they are written
+        /// to stdout.
+        #[clap(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Serialize)]
+struct Entry {
+    timestamp: DateTime<Utc>,
+    username: Option<String>,
+    mutation: String,
+    realm_path: Option<String>,
+    block: Option<i64>,
+    before: Option<serde_json::Value>,
+    after: Option<serde_json::Value>,
+}
+
+
+pub(crate) async fn run(config: &Config, args: &Args) -> Result<()> {
+    let db = db::create_pool(&config.db).await
+        .context("failed to create database connection pool (database not running?)")?;
+    let conn = db.get().await?;
+
+    match args {
+        Args::Export { realm, since, output } => {
+            let realm = realm.as_deref().map(|path| path.strip_suffix('/').unwrap_or(path));
+            let rows = conn.query(
+                "select timestamp, username, mutation, realm_path, block, \
+                        before, after \
+                    from audit_log \
+                    where ($1::text is null \
+                            or realm_path = $1 \
+                            or starts_with(realm_path, $1 || '/')) \
+                        and ($2::timestamptz is null or timestamp > $2) \
+                    order by timestamp, id",
+                &[&realm, &since],
+            ).await?;
+
+            let mut out: Box<dyn Write> = match output {
+                Some(path) => Box::new(std::fs::File::create(path)
+                    .with_context(|| format!("failed to create '{}'", path.display()))?),
+                None => Box::new(std::io::stdout().lock()),
+            };
+            for row in &rows {
+                let entry = Entry {
+                    timestamp: row.get(0),
+                    username: row.get(1),
+                    mutation: row.get(2),
+                    realm_path: row.get(3),
+                    block: row.get(4),
+                    before: row.get(5),
+                    after: row.get(6),
+                };
+                serde_json::to_writer(&mut out, &entry)?;
+                writeln!(out)?;
+            }
+            out.flush()?;
+
+            if let Some(path) = output {
+                info!("Exported {} audit log entries to '{}'", rows.len(), path.display());
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -11,6 +11,7 @@ use crate::{
     config::Config,
     db,
     prelude::*,
+    sync::DEFAULT_SOURCE,
 };
 
 
@@ -113,10 +114,13 @@ fn for_all_empty_realms(realm: &mut Realm, mut f: impl FnMut(&mut Realm)) {
 async fn add_dummy_blocks(root: &mut Realm, db: &impl GenericClient) -> Result<()> {
     let mut rng = thread_rng();
 
-    // Load all series from the DB and store them in a hashmap that maps from
+    // Load all series (of the default sync source, as series are referenced by
+    // UUID below) from the DB and store them in a hashmap that maps from
     // series title to a list of series UUIDS with that title. We need this data
     // structure below.
-    let series_rows = db.query("select title, opencast_id from series", &[]).await?;
+    let series_rows = db
+        .query("select title, opencast_id from series where sync_source = $1", &[&DEFAULT_SOURCE])
+        .await?;
     let mut series = <HashMap<_, Vec<_>>>::new();
 
     for row in series_rows {
@@ -307,9 +311,9 @@ impl Block {
                         rows[0].get::<_, i64>(0)
                     }
                     Series::ByUuid(uuid) => {
-                        let rows = db
-                            .query("select id from series where opencast_id = $1", &[uuid])
-                            .await?;
+                        let query = "select id from series \
+                            where sync_source = $1 and opencast_id = $2";
+                        let rows = db.query(query, &[&DEFAULT_SOURCE, uuid]).await?;
                         if rows.is_empty() {
                             anyhow::bail!("Series with UUID '{}' not found!", uuid);
                         }
@@ -1,10 +1,12 @@
 use crate::prelude::*;
 
+pub(crate) mod audit_log;
 pub(crate) mod export_api_schema;
 pub(crate) mod import_realm_tree;
 pub(crate) mod check;
 pub(crate) mod known_groups;
 pub(crate) mod known_users;
+pub(crate) mod realm;
 
 
 /// Reads stdin and returns an error if the trimmed input is not exactly "yes".
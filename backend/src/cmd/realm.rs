@@ -0,0 +1,649 @@
+//! CLI command `realm` to export realm trees to YAML files and import them
+//! again. The format is described in `docs/docs/setup/realm-export.md`.
+
+use std::{fs::File, future::Future, io::Write, path::{Path, PathBuf}, pin::Pin};
+
+use chrono::{DateTime, Utc};
+use deadpool_postgres::GenericClient;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::IsolationLevel;
+
+use crate::{
+    api::model::block::BlockType,
+    config::Config,
+    db,
+    prelude::*,
+    sync::DEFAULT_SOURCE,
+};
+
+
+#[derive(Debug, clap::Parser)]
+pub(crate) enum Args {
+    /// Exports a realm and all its descendants (including names, blocks and
+    /// permissions) as YAML.
+    Export {
+        /// Path of the realm to export, e.g. `/lectures`. If not specified,
+        /// the whole realm tree is exported.
+        path: Option<String>,
+
+        /// File to write the YAML to. If not specified, it is written to stdout.
+        #[clap(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Imports a realm tree previously exported with `realm export`. Series,
+    /// events and playlists are matched by their Opencast ID and all of them
+    /// have to exist already.
+    Import {
+        /// The YAML file to import.
+        file: PathBuf,
+
+        /// Path at which the imported tree is created. Defaults to the path it
+        /// was exported from. The realm at that path must not exist yet, but
+        /// its parent has to. An export of the whole tree can only be imported
+        /// into an empty root realm.
+        #[clap(long)]
+        at: Option<String>,
+    },
+}
+
+/// Version of the export format, to be able to change it in the future.
+const FORMAT_VERSION: u32 = 1;
+
+/// The top level structure of an export file.
+#[derive(Debug, Serialize, Deserialize)]
+struct ExportFile {
+    version: u32,
+
+    /// Full path of the exported realm. Empty for the root realm.
+    path: String,
+
+    realm: Realm,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Realm {
+    path_segment: String,
+
+    /// `None` for the root realm and for realms deriving their name from a
+    /// block.
+    name: Option<String>,
+
+    /// Index of the block used as name source.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name_from_block: Option<usize>,
+
+    /// `by_index`, `alphabetic:asc` or `alphabetic:desc`. For `by_index`, the
+    /// order of `children` is used.
+    child_order: String,
+
+    #[serde(default)]
+    admin_roles: Vec<String>,
+
+    #[serde(default)]
+    moderator_roles: Vec<String>,
+
+    #[serde(default)]
+    #[serde(with = "serde_yaml::with::singleton_map_recursive")]
+    blocks: Vec<Block>,
+
+    #[serde(default)]
+    children: Vec<Realm>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Block {
+    #[serde(flatten)]
+    kind: BlockKind,
+
+    /// Start of the time window in which the block is visible, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    visible_from: Option<DateTime<Utc>>,
+
+    /// End of the time window in which the block is visible, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    visible_until: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum BlockKind {
+    Title(String),
+    Text(String),
+    Series {
+        /// `None` if the series was deleted.
+        series: Option<OpencastRef>,
+        order: String,
+        layout: String,
+        show_title: bool,
+        show_metadata: bool,
+    },
+    Playlist {
+        /// `None` if the playlist was deleted.
+        playlist: Option<OpencastRef>,
+        order: String,
+        layout: String,
+        show_title: bool,
+        show_metadata: bool,
+    },
+    Video {
+        /// `None` if the event was deleted.
+        event: Option<OpencastRef>,
+        show_title: bool,
+        show_link: bool,
+    },
+    RealmList {
+        /// Full paths of the listed realms, or `None` if all children are
+        /// listed.
+        realms: Option<Vec<String>>,
+        layout: String,
+    },
+    Embed {
+        url: String,
+    },
+}
+
+/// Refers to a series, event or playlist by Opencast ID.
+#[derive(Debug, Serialize, Deserialize)]
+struct OpencastRef {
+    opencast_id: String,
+
+    /// The sync source the item comes from. Omitted for the default one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    source: Option<String>,
+}
+
+
+pub(crate) async fn run(config: &Config, args: &Args) -> Result<()> {
+    let db = db::create_pool(&config.db).await
+        .context("failed to create database connection pool (database not running?)")?;
+    let mut conn = db.get().await?;
+    let tx = conn.build_transaction()
+        .isolation_level(IsolationLevel::Serializable)
+        .start()
+        .await?;
+
+    match args {
+        Args::Export { path, output } => {
+            export(path.as_deref().unwrap_or(""), output.as_deref(), &tx).await?;
+        }
+        Args::Import { file, at } => {
+            import(file, at.as_deref(), &tx).await?;
+            tx.commit().await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Removes a trailing slash, turning "/" into "" (the root realm's path).
+fn normalize_path(path: &str) -> &str {
+    path.strip_suffix('/').unwrap_or(path)
+}
+
+
+// ===== Export ==================================================================================
+
+async fn export(path: &str, output: Option<&Path>, db: &impl GenericClient) -> Result<()> {
+    let path = normalize_path(path);
+    let row = db.query_opt("select id from realms where full_path = $1", &[&path])
+        .await?
+        .ok_or_else(|| anyhow!("realm '{path}' does not exist"))?;
+
+    let realm = load_realm(db, row.get(0)).await?;
+    let yaml = serde_yaml::to_string(&ExportFile {
+        version: FORMAT_VERSION,
+        path: path.to_owned(),
+        realm,
+    })?;
+
+    match output {
+        Some(output) => {
+            std::fs::write(output, yaml)
+                .with_context(|| format!("failed to write '{}'", output.display()))?;
+            info!("Exported realm tree '{path}' to '{}'", output.display());
+        }
+        None => std::io::stdout().write_all(yaml.as_bytes())?,
+    }
+
+    Ok(())
+}
+
+// Recursive async functions have to be written manually, unfortunately.
+fn load_realm<'a>(
+    db: &'a impl GenericClient,
+    id: i64,
+) -> Pin<Box<dyn 'a + Future<Output = Result<Realm>>>> {
+    Box::pin(async move {
+        let query = "select path_segment, name, name_from_block, child_order::text, \
+            admin_roles, moderator_roles \
+            from realms where id = $1";
+        let row = db.query_one(query, &[&id]).await?;
+        let name_from_block = row.get::<_, Option<i64>>("name_from_block");
+
+        // Load blocks, with the Opencast IDs of all referenced items.
+        let query = "select blocks.id, type, text_content, \
+                series.opencast_id as series_id, series.sync_source as series_source, \
+                events.opencast_id as event_id, events.sync_source as event_source, \
+                playlists.opencast_id as playlist_id, playlists.sync_source as playlist_source, \
+                videolist_order::text, videolist_layout::text, \
+                show_title, show_metadata, show_link, \
+                case when blocks.realms is not null then array( \
+                    select realms.full_path \
+                    from unnest(blocks.realms) with ordinality as listed(id, position) \
+                    inner join realms on realms.id = listed.id \
+                    order by listed.position \
+                ) end as realm_paths, \
+                realmlist_layout::text, embed_url, visible_from, visible_until \
+            from blocks \
+            left join series on series.id = blocks.series \
+            left join events on events.id = blocks.video \
+            left join playlists on playlists.id = blocks.playlist \
+            where realm = $1 \
+            order by index";
+        let mut blocks = vec![];
+        let mut name_block_index = None;
+        for (i, row) in db.query(query, &[&id]).await?.into_iter().enumerate() {
+            if name_from_block == Some(row.get("id")) {
+                name_block_index = Some(i);
+            }
+
+            let oc_ref = |kind: &str| {
+                row.get::<_, Option<String>>(&*format!("{kind}_id")).map(|opencast_id| {
+                    let source = row.get::<_, String>(&*format!("{kind}_source"));
+                    OpencastRef {
+                        opencast_id,
+                        source: (source != DEFAULT_SOURCE).then_some(source),
+                    }
+                })
+            };
+            let kind = match row.get::<_, BlockType>("type") {
+                BlockType::Title => BlockKind::Title(row.get("text_content")),
+                BlockType::Text => BlockKind::Text(row.get("text_content")),
+                BlockType::Series => BlockKind::Series {
+                    series: oc_ref("series"),
+                    order: row.get("videolist_order"),
+                    layout: row.get("videolist_layout"),
+                    show_title: row.get("show_title"),
+                    show_metadata: row.get("show_metadata"),
+                },
+                BlockType::Playlist => BlockKind::Playlist {
+                    playlist: oc_ref("playlist"),
+                    order: row.get("videolist_order"),
+                    layout: row.get("videolist_layout"),
+                    show_title: row.get("show_title"),
+                    show_metadata: row.get("show_metadata"),
+                },
+                BlockType::Video => BlockKind::Video {
+                    event: oc_ref("event"),
+                    show_title: row.get("show_title"),
+                    show_link: row.get("show_link"),
+                },
+                BlockType::RealmList => BlockKind::RealmList {
+                    realms: row.get("realm_paths"),
+                    layout: row.get("realmlist_layout"),
+                },
+                BlockType::Embed => BlockKind::Embed {
+                    url: row.get("embed_url"),
+                },
+            };
+            blocks.push(Block {
+                kind,
+                visible_from: row.get("visible_from"),
+                visible_until: row.get("visible_until"),
+            });
+        }
+
+        // Load children recursively.
+        let query = "select id from realms where parent = $1 order by index, path_segment";
+        let mut children = vec![];
+        for child in db.query(query, &[&id]).await? {
+            children.push(load_realm(db, child.get(0)).await?);
+        }
+
+        Ok(Realm {
+            path_segment: row.get("path_segment"),
+            name: row.get("name"),
+            name_from_block: name_block_index,
+            child_order: row.get("child_order"),
+            admin_roles: row.get("admin_roles"),
+            moderator_roles: row.get("moderator_roles"),
+            blocks,
+            children,
+        })
+    })
+}
+
+
+// ===== Import ==================================================================================
+
+async fn import(file: &Path, at: Option<&str>, db: &impl GenericClient) -> Result<()> {
+    let reader = File::open(file)
+        .with_context(|| format!("failed to open '{}'", file.display()))?;
+    let export: ExportFile = serde_yaml::from_reader(reader)
+        .with_context(|| format!("failed to deserialize '{}'", file.display()))?;
+    if export.version != FORMAT_VERSION {
+        bail!("unsupported export format version {} (expected {FORMAT_VERSION})", export.version);
+    }
+
+    let target = normalize_path(at.unwrap_or(&export.path));
+    let exported_root = normalize_path(&export.path).is_empty();
+    let id = if target.is_empty() {
+        if !exported_root {
+            bail!("only an export of the whole realm tree can be imported as root realm");
+        }
+
+        let query = "select exists(select from realms where parent = 0) \
+            or exists(select from blocks where realm = 0)";
+        if db.query_one(query, &[]).await?.get::<_, bool>(0) {
+            bail!("cannot import into root realm: it already has children or blocks");
+        }
+        0
+    } else {
+        if exported_root {
+            bail!("an export of the whole realm tree can only be imported as root realm");
+        }
+
+        let Some((parent_path, path_segment)) = target.rsplit_once('/') else {
+            bail!("invalid target path '{target}': has to start with '/'");
+        };
+        let parent = db.query_opt("select id from realms where full_path = $1", &[&parent_path])
+            .await?
+            .ok_or_else(|| anyhow!("parent realm '{parent_path}' does not exist"))?;
+        let query = "select exists(select from realms where full_path = $1)";
+        if db.query_one(query, &[&target]).await?.get::<_, bool>(0) {
+            bail!("realm '{target}' already exists");
+        }
+
+        insert_realm(db, parent.get(0), path_segment, None, &export.realm).await?
+    };
+
+    // Realm list blocks can reference realms that are only imported after
+    // them, so their realms are set once the whole tree exists.
+    let realm_lists = import_realm(db, id, &export.realm).await?;
+    let exported_path = normalize_path(&export.path);
+    for (block, paths) in realm_lists {
+        let mut realms = vec![];
+        for path in paths {
+            // Paths inside the exported tree are moved to the target path.
+            let path = match path.strip_prefix(exported_path) {
+                Some(rest) if rest.is_empty() || rest.starts_with('/') => format!("{target}{rest}"),
+                _ => path.clone(),
+            };
+            let row = db.query_opt("select id from realms where full_path = $1", &[&path])
+                .await?
+                .ok_or_else(|| anyhow!("listed realm '{path}' does not exist"))?;
+            realms.push(row.get::<_, i64>(0));
+        }
+        db.execute("update blocks set realms = $2 where id = $1", &[&block, &realms]).await?;
+    }
+    info!("Imported realm tree from '{}' to '{target}'", file.display());
+
+    Ok(())
+}
+
+/// Inserts a new child realm with temporary values, which are properly set
+/// by `import_realm`. Returns the ID of the new realm.
+async fn insert_realm(
+    db: &impl GenericClient,
+    parent: i64,
+    path_segment: &str,
+    index: Option<i32>,
+    realm: &Realm,
+) -> Result<i64> {
+    // Realms deriving their name from a block get a temporary name, as the
+    // block does not exist yet.
+    let name = realm.name.as_deref().unwrap_or(path_segment);
+    let query = "insert into realms (parent, name, path_segment, index) \
+        values ($1, $2, $3, coalesce($4, 2147483647)) \
+        returning id";
+    db.query_one(query, &[&parent, &name, &path_segment, &index])
+        .await
+        .with_context(|| format!("failed to insert realm '{path_segment}'"))?
+        .get::<_, i64>(0)
+        .pipe(Ok)
+}
+
+/// Imports the given realm and its descendants. Returns the IDs of all
+/// inserted realm list blocks with selected realms, together with the paths
+/// of these realms, which still have to be set.
+// Recursive async functions have to be written manually, unfortunately.
+fn import_realm<'a>(
+    db: &'a impl GenericClient,
+    id: i64,
+    realm: &'a Realm,
+) -> Pin<Box<dyn 'a + Future<Output = Result<Vec<(i64, &'a [String])>>>>> {
+    Box::pin(async move {
+        let mut block_ids = vec![];
+        let mut realm_lists = vec![];
+        for (i, block) in realm.blocks.iter().enumerate() {
+            let block_id = block.insert(id, i, db).await?;
+            if let BlockKind::RealmList { realms: Some(paths), .. } = &block.kind {
+                realm_lists.push((block_id, &paths[..]));
+            }
+            block_ids.push(block_id);
+        }
+
+        let name_from_block = realm.name_from_block
+            .map(|i| block_ids.get(i).copied().ok_or_else(|| {
+                anyhow!("'name_from_block' of realm '{}' is out of bounds", realm.path_segment)
+            }))
+            .transpose()?;
+        let query = "update realms set \
+                name = $2, \
+                name_from_block = $3, \
+                child_order = $4::text::realm_order, \
+                admin_roles = $5, \
+                moderator_roles = $6 \
+            where id = $1";
+        let name = if name_from_block.is_some() { None } else { realm.name.as_deref() };
+        db.execute(query, &[
+            &id,
+            &name,
+            &name_from_block,
+            &realm.child_order,
+            &realm.admin_roles,
+            &realm.moderator_roles,
+        ]).await.with_context(|| format!("failed to update realm '{}'", realm.path_segment))?;
+
+        let by_index = realm.child_order == "by_index";
+        for (i, child) in realm.children.iter().enumerate() {
+            let index = by_index.then_some(i as i32);
+            let child_id = insert_realm(db, id, &child.path_segment, index, child).await?;
+            realm_lists.extend(import_realm(db, child_id, child).await?);
+        }
+
+        Ok(realm_lists)
+    })
+}
+
+impl Block {
+    async fn insert(&self, realm: i64, index: usize, db: &impl GenericClient) -> Result<i64> {
+        let index = index as i16;
+        let row = match &self.kind {
+            BlockKind::Title(text) | BlockKind::Text(text) => {
+                let ty = if matches!(self.kind, BlockKind::Title(_)) { "title" } else { "text" };
+                let query = "insert into blocks (realm, type, index, text_content) \
+                    values ($1, $2::text::block_type, $3, $4) \
+                    returning id";
+                db.query_one(query, &[&realm, &ty, &index, text]).await?
+            }
+            BlockKind::Series { series, order, layout, show_title, show_metadata } => {
+                let series = match series {
+                    Some(series) => Some(series.resolve("series", db).await?),
+                    None => None,
+                };
+                let query = "insert into blocks \
+                    (realm, type, index, series, videolist_order, videolist_layout, \
+                        show_title, show_metadata) \
+                    values ($1, 'series', $2, $3, $4::text::video_list_order, \
+                        $5::text::video_list_layout, $6, $7) \
+                    returning id";
+                let args = dbargs![
+                    &realm, &index, &series, order, layout, show_title, show_metadata,
+                ];
+                db.query_one(query, &args).await?
+            }
+            BlockKind::Playlist { playlist, order, layout, show_title, show_metadata } => {
+                let playlist = match playlist {
+                    Some(playlist) => Some(playlist.resolve("playlists", db).await?),
+                    None => None,
+                };
+                let query = "insert into blocks \
+                    (realm, type, index, playlist, videolist_order, videolist_layout, \
+                        show_title, show_metadata) \
+                    values ($1, 'playlist', $2, $3, $4::text::video_list_order, \
+                        $5::text::video_list_layout, $6, $7) \
+                    returning id";
+                let args = dbargs![
+                    &realm, &index, &playlist, order, layout, show_title, show_metadata,
+                ];
+                db.query_one(query, &args).await?
+            }
+            BlockKind::Video { event, show_title, show_link } => {
+                let event = match event {
+                    Some(event) => Some(event.resolve("events", db).await?),
+                    None => None,
+                };
+                let query = "insert into blocks (realm, type, index, video, show_title, show_link) \
+                    values ($1, 'video', $2, $3, $4, $5) \
+                    returning id";
+                db.query_one(query, &[&realm, &index, &event, show_title, show_link]).await?
+            }
+            BlockKind::RealmList { realms, layout } => {
+                // Selected realms are set by `import` later.
+                let realms = realms.as_ref().map(|_| Vec::<i64>::new());
+                let query = "insert into blocks (realm, type, index, realms, realmlist_layout) \
+                    values ($1, 'realm_list', $2, $3, $4::text::realm_list_layout) \
+                    returning id";
+                db.query_one(query, &[&realm, &index, &realms, layout]).await?
+            }
+            BlockKind::Embed { url } => {
+                let query = "insert into blocks (realm, type, index, embed_url) \
+                    values ($1, 'embed', $2, $3) \
+                    returning id";
+                db.query_one(query, &[&realm, &index, url]).await?
+            }
+        };
+        let id: i64 = row.get(0);
+
+        if self.visible_from.is_some() || self.visible_until.is_some() {
+            let query = "update blocks set visible_from = $2, visible_until = $3 where id = $1";
+            db.execute(query, &[&id, &self.visible_from, &self.visible_until]).await?;
+        }
+
+        Ok(id)
+    }
+}
+
+impl OpencastRef {
+    /// Looks up the ID of the referenced item in `table`.
+    async fn resolve(&self, table: &str, db: &impl GenericClient) -> Result<i64> {
+        let source = self.source.as_deref().unwrap_or(DEFAULT_SOURCE);
+        let query = format!("select id from {table} where sync_source = $1 and opencast_id = $2");
+        db.query_opt(&query, &[&source, &self.opencast_id])
+            .await?
+            .map(|row| row.get::<_, i64>(0))
+            .ok_or_else(|| anyhow!(
+                "'{}' (sync source '{source}') does not exist in {table}",
+                self.opencast_id,
+            ))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{BlockKind, ExportFile};
+
+    #[test]
+    fn format_round_trip() {
+        let yaml = "\
+version: 1
+path: /lectures
+realm:
+  path_segment: lectures
+  name: Lectures
+  child_order: by_index
+  admin_roles:
+  - ROLE_STAFF
+  moderator_roles: []
+  blocks:
+  - title: Welcome
+  - series:
+      series:
+        opencast_id: s1
+      order: new_to_old
+      layout: gallery
+      show_title: true
+      show_metadata: false
+  - video:
+      event:
+        opencast_id: e1
+        source: other
+      show_title: false
+      show_link: true
+  - realm_list:
+      realms:
+      - /lectures/math
+      - /archive
+      layout: gallery
+  children:
+  - path_segment: math
+    name: null
+    name_from_block: 0
+    child_order: alphabetic:asc
+    admin_roles: []
+    moderator_roles: []
+    blocks:
+    - video:
+        event: null
+        show_title: true
+        show_link: true
+    - realm_list:
+        realms: null
+        layout: list
+    children: []
+";
+        let export = serde_yaml::from_str::<ExportFile>(yaml).unwrap();
+        assert_eq!(export.realm.blocks.len(), 4);
+        assert!(matches!(
+            &export.realm.blocks[2].kind,
+            BlockKind::Video { event: Some(e), .. } if e.source.as_deref() == Some("other"),
+        ));
+        assert!(matches!(
+            &export.realm.children[0].blocks[1].kind,
+            BlockKind::RealmList { realms: None, .. },
+        ));
+        assert_eq!(export.realm.children[0].name_from_block, Some(0));
+        assert_eq!(serde_yaml::to_string(&export).unwrap(), yaml);
+    }
+
+    #[test]
+    fn block_visibility() {
+        let yaml = "\
+version: 1
+path: /lectures
+realm:
+  path_segment: lectures
+  name: Lectures
+  child_order: by_index
+  blocks:
+  - title: Exam
+    visible_from: 2024-03-01T08:00:00Z
+    visible_until: 2024-04-01T08:00:00Z
+  - text: Always there
+";
+        let export = serde_yaml::from_str::<ExportFile>(yaml).unwrap();
+        let blocks = &export.realm.blocks;
+        assert!(matches!(&blocks[0].kind, BlockKind::Title(t) if t == "Exam"));
+        assert_eq!(blocks[0].visible_from.unwrap().to_rfc3339(), "2024-03-01T08:00:00+00:00");
+        assert_eq!(blocks[0].visible_until.unwrap().to_rfc3339(), "2024-04-01T08:00:00+00:00");
+        assert!(blocks[1].visible_from.is_none() && blocks[1].visible_until.is_none());
+
+        let reexported = serde_yaml::to_string(&export).unwrap();
+        let reimported = serde_yaml::from_str::<ExportFile>(&reexported).unwrap();
+        assert_eq!(reimported.realm.blocks[0].visible_from, blocks[0].visible_from);
+        assert_eq!(reimported.realm.blocks[0].visible_until, blocks[0].visible_until);
+    }
+}
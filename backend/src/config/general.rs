@@ -1,4 +1,6 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Duration};
+
+use hyper::Uri;
 
 use super::{HttpHost, TranslatedString};
 
@@ -111,6 +113,26 @@ pub(crate) struct GeneralConfig {
     /// (partial) name.
     #[config(default = false)]
     pub users_searchable: bool,
+
+    /// How long removed pages and blocks are kept in the trash. Until then,
+    /// page admins can restore them. Afterwards, the worker purges them.
+    #[config(default = "30d", deserialize_with = crate::config::deserialize_duration)]
+    pub trash_retention: Duration,
+
+    /// How long old paths of moved pages (or pages whose path segment
+    /// changed) keep redirecting to the new path. Afterwards, the worker
+    /// removes them.
+    #[config(default = "365d", deserialize_with = crate::config::deserialize_duration)]
+    pub realm_path_history_retention: Duration,
+
+    /// Origins (scheme, host and optionally port) of external content that
+    /// can be embedded in pages via embed blocks, e.g. Moodle quizzes or H5P
+    /// elements. URLs of embed blocks have to belong to one of these origins.
+    /// If empty, embed blocks cannot be added.
+    ///
+    /// Example: ["https://moodle.my-uni.edu", "https://h5p.org"]
+    #[config(default = [])]
+    pub embed_origins: Vec<HttpHost>,
 }
 
 const INTERNAL_RESERVED_PATHS: &[&str] = &["favicon.ico", "robots.txt", ".well-known"];
@@ -123,6 +145,20 @@ impl GeneralConfig {
             .map(|s| s.strip_prefix("/").unwrap_or(s))
             .chain(INTERNAL_RESERVED_PATHS.iter().copied())
     }
+
+    /// Returns whether the given URL belongs to one of the `embed_origins`.
+    pub(crate) fn is_embed_allowed(&self, url: &Uri) -> bool {
+        is_allowed_origin(&self.embed_origins, url)
+    }
+}
+
+/// Returns whether `url` has the same scheme and authority as one of the given
+/// origins. The userinfo is part of the authority, so URLs with userinfo never
+/// match.
+pub(super) fn is_allowed_origin(origins: &[HttpHost], url: &Uri) -> bool {
+    origins.iter().any(|origin| {
+        url.scheme() == Some(&origin.scheme) && url.authority() == Some(&origin.authority)
+    })
 }
 
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
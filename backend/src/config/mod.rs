@@ -141,6 +141,7 @@ impl Config {
         self.auth.validate()?;
         self.opencast.validate()?;
         self.db.validate()?;
+        self.sync.validate()?;
         self.theme.validate()?;
 
         Ok(())
@@ -352,7 +353,7 @@ impl TryFrom<String> for HttpHost {
 
 #[cfg(test)]
 mod tests {
-    use super::HttpHost;
+    use super::{HttpHost, general::is_allowed_origin};
 
     fn parse_http_host(s: &str) -> HttpHost {
         s.parse::<HttpHost>().expect(&format!("could not parse '{s}' as HttpHost"))
@@ -405,4 +406,40 @@ mod tests {
             format!("http://{host}").parse::<HttpHost>().unwrap_err();
         }
     }
+
+    fn is_embed_allowed(url: &str) -> bool {
+        let origins = [
+            parse_http_host("https://h5p.org"),
+            parse_http_host("https://moodle.my-uni.edu:8443"),
+        ];
+        is_allowed_origin(&origins, &url.parse().expect(&format!("could not parse '{url}'")))
+    }
+
+    #[test]
+    fn embed_allowed_origin() {
+        assert!(is_embed_allowed("https://h5p.org/h5p/embed/712"));
+        assert!(is_embed_allowed("https://moodle.my-uni.edu:8443/mod/quiz/view.php?id=3"));
+    }
+
+    #[test]
+    fn embed_scheme_mismatch() {
+        assert!(!is_embed_allowed("http://h5p.org/h5p/embed/712"));
+    }
+
+    #[test]
+    fn embed_different_port() {
+        assert!(!is_embed_allowed("https://h5p.org:8443/h5p/embed/712"));
+        assert!(!is_embed_allowed("https://moodle.my-uni.edu/mod/quiz/view.php?id=3"));
+    }
+
+    #[test]
+    fn embed_userinfo() {
+        assert!(!is_embed_allowed("https://user@h5p.org/h5p/embed/712"));
+        assert!(!is_embed_allowed("https://h5p.org@evil.com/h5p/embed/712"));
+    }
+
+    #[test]
+    fn embed_relative_url() {
+        assert!(!is_embed_allowed("/h5p/embed/712"));
+    }
 }
@@ -365,4 +365,27 @@ static MIGRATIONS: Lazy<BTreeMap<u64, Migration>> = include_migrations![
     30: "realm-permissions",
     31: "series-metadata",
     32: "custom-actions",
+    33: "playlists",
+    34: "playlist-blocks",
+    35: "event-texts",
+    36: "event-segments",
+    37: "sync-notifications",
+    38: "harvest-runs",
+    39: "sync-sources",
+    40: "event-pending-writes",
+    41: "series-pending-writes",
+    42: "event-deletion",
+    43: "realm-path-history",
+    44: "audit-log",
+    45: "trash",
+    46: "realm-drafts",
+    47: "block-visibility",
+    48: "realm-list-block-type",
+    49: "realm-list-blocks",
+    50: "embed-block-type",
+    51: "embed-blocks",
+    52: "playlist-block-search",
+    53: "event-chapters",
+    54: "event-pending-write-check",
+    55: "series-custom-actions",
 ];
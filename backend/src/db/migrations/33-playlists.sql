@@ -0,0 +1,66 @@
+-- Adds playlists, which are harvested from Opencast just like events and
+-- series. A playlist is an ordered list of entries, each referring to some
+-- Opencast item (currently only events) via its Opencast ID. We deliberately
+-- do not use foreign keys for these entries: the referenced events might not
+-- be synced yet or might have been deleted in the meantime. Resolving these
+-- references happens when loading the playlist.
+
+select prepare_randomized_ids('playlist');
+
+create type playlist_entry_type as enum ('event');
+
+create type playlist_entry as (
+    -- The ID of this entry in Opencast. Entries do not have a UUID in
+    -- Opencast, but are identified by this number.
+    entry_id bigint,
+    type playlist_entry_type,
+    content_id text
+);
+
+create table playlists (
+    id bigint primary key default randomized_id('playlist'),
+
+    -- Opencast internal data
+    opencast_id text not null unique,
+
+    -- Meta data
+    title text not null,
+    description text,
+    creator text,
+
+    -- The ordered entries of this playlist.
+    entries playlist_entry[] not null,
+
+    -- Permissions: roles that are allowed to read/write
+    read_roles text[] not null,
+    write_roles text[] not null,
+
+    updated timestamp with time zone not null,
+
+    constraint no_null_entries check (array_position(entries, null) is null),
+    constraint no_null_read_roles check (array_position(read_roles, null) is null),
+    constraint no_null_write_roles check (array_position(write_roles, null) is null)
+);
+
+
+-- Just like for events and series, we remember the IDs of deleted playlists.
+-- See `12-deleted-items.sql` for more information.
+alter type opencast_item_kind add value 'playlist';
+
+create trigger remember_deleted_opencast_playlists
+after delete
+on playlists
+for each row
+execute procedure remember_deleted_opencast_items('playlist');
+
+create trigger reuse_existing_id_on_playlist_insert
+before insert
+on playlists
+for each row
+execute procedure reuse_existing_id_on_insert('playlist');
+
+
+-- Playlists can be shown on realm pages via a new block type. The column and
+-- constraints are added in the next migration, as a new enum value cannot be
+-- used in the same transaction that adds it.
+alter type block_type add value 'playlist';
@@ -0,0 +1,13 @@
+-- Adds the `playlist` column to blocks, used by playlist blocks. These work
+-- just like series blocks and thus also need the videolist fields.
+
+alter table blocks
+    add column playlist bigint references playlists on delete set null,
+    add constraint playlist_block_has_fields check (type <> 'playlist' or (
+        videolist_order is not null and
+        videolist_layout is not null and
+        show_title is not null and
+        show_metadata is not null
+    ));
+
+create index idx_block_playlist on blocks (playlist);
@@ -0,0 +1,101 @@
+-- Adds storage for texts extracted from event assets (currently only
+-- captions), which are used for full-text search. The assets are downloaded
+-- and parsed asynchronously by the worker, so there is also a queue of events
+-- whose texts need to be (re)fetched.
+
+-- A piece of text associated with a timespan of an event.
+create type timespan_text as (
+    span_start bigint, -- in ms
+    span_end bigint,   -- in ms
+    t text
+);
+
+create table event_texts (
+    event_id bigint not null references events on delete cascade,
+
+    -- The URI of the asset the texts were extracted from.
+    uri text not null,
+    texts timespan_text[] not null,
+
+    -- When the asset was downloaded.
+    fetch_time timestamp with time zone not null,
+
+    primary key (event_id, uri),
+    constraint no_null_text_items check (array_position(texts, null) is null)
+);
+
+create table event_texts_queue (
+    event_id bigint primary key references events on delete cascade,
+
+    -- The event should not be processed before this point in time. This is
+    -- used to retry failed fetches with a backoff.
+    fetch_after timestamp with time zone not null default now(),
+
+    -- How often fetching the texts of this event has failed already.
+    retry_count int not null default 0
+);
+
+create index idx_event_texts_queue_fetch_after on event_texts_queue (fetch_after);
+
+
+-- Queue events whenever their captions change.
+create function queue_event_for_text_fetch()
+    returns trigger
+    language plpgsql
+as $$
+begin
+    if tg_op = 'UPDATE' and old.captions is not distinct from new.captions then
+        return null;
+    end if;
+
+    insert into event_texts_queue (event_id)
+        values (new.id)
+        on conflict (event_id) do update set fetch_after = now(), retry_count = 0;
+    return null;
+end;
+$$;
+
+create trigger queue_event_for_text_fetch
+after insert or update of captions
+on events
+for each row
+execute procedure queue_event_for_text_fetch();
+
+-- Queue all existing events that have captions.
+insert into event_texts_queue (event_id)
+    select id from events where array_length(captions, 1) > 0;
+
+
+-- Add caption texts to the search view. This is the same definition as in
+-- `26-more-event-search-data` with the `caption_texts` column added at the end.
+create or replace view search_events as
+    select
+        events.id, events.state,
+        events.series, series.title as series_title,
+        events.title, events.description, events.creators,
+        events.thumbnail, events.duration,
+        events.is_live, events.created, events.start_time, events.end_time,
+        events.read_roles, events.write_roles,
+        coalesce(
+            array_agg(
+                distinct
+                row(search_realms.*)::search_realms
+            ) filter(where search_realms.id is not null),
+            '{}'
+        ) as host_realms,
+        not exists (
+            select from unnest(events.tracks) as t where t.resolution is not null
+        ) as audio_only,
+        array(
+            select texts.t
+            from event_texts, unnest(event_texts.texts) as texts
+            where event_texts.event_id = events.id
+        ) as caption_texts
+    from events
+    left join series on events.series = series.id
+    left join blocks on (
+        type = 'series' and blocks.series = events.series
+        or type = 'video' and blocks.video = events.id
+    )
+    left join search_realms on search_realms.id = blocks.realm
+    group by events.id, series.id;
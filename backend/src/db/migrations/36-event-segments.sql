@@ -0,0 +1,67 @@
+-- Adds a `segments` field to `events`, which stores the segments detected by
+-- Opencast (e.g. slide changes) with their preview image and the text found
+-- on the slide (e.g. via OCR).
+
+create type event_segment as (
+    start_time bigint, -- in ms
+    duration bigint,   -- in ms
+    thumbnail text,
+    text text
+);
+
+alter table events
+    add column segments event_segment[]
+        default '{}'
+        constraint no_null_segment_items check (array_position(segments, null) is null);
+
+alter table events
+    -- The default above was just for all existing records. New records should
+    -- require this to be set.
+    alter column segments drop default,
+    drop constraint ready_event_has_fields,
+    add constraint ready_event_has_fields check (state <> 'ready' or (
+        duration is not null
+        and tracks is not null and array_length(tracks, 1) > 0
+        and captions is not null
+        and segments is not null
+    ));
+
+
+-- Add slide texts to the search view. This is the same definition as in
+-- `35-event-texts` with the `slide_texts` column added at the end.
+create or replace view search_events as
+    select
+        events.id, events.state,
+        events.series, series.title as series_title,
+        events.title, events.description, events.creators,
+        events.thumbnail, events.duration,
+        events.is_live, events.created, events.start_time, events.end_time,
+        events.read_roles, events.write_roles,
+        coalesce(
+            array_agg(
+                distinct
+                row(search_realms.*)::search_realms
+            ) filter(where search_realms.id is not null),
+            '{}'
+        ) as host_realms,
+        not exists (
+            select from unnest(events.tracks) as t where t.resolution is not null
+        ) as audio_only,
+        array(
+            select texts.t
+            from event_texts, unnest(event_texts.texts) as texts
+            where event_texts.event_id = events.id
+        ) as caption_texts,
+        array(
+            select segments.text
+            from unnest(events.segments) as segments
+            where segments.text is not null
+        ) as slide_texts
+    from events
+    left join series on events.series = series.id
+    left join blocks on (
+        type = 'series' and blocks.series = events.series
+        or type = 'video' and blocks.video = events.id
+    )
+    left join search_realms on search_realms.id = blocks.realm
+    group by events.id, series.id;
@@ -0,0 +1,5 @@
+-- Adds a way to request an immediate harvest (via `POST /~sync/notify`). The
+-- web server sets this timestamp, which the harvesting worker watches while
+-- waiting for the next harvest.
+alter table sync_status
+    add column wakeup_requested timestamp with time zone;
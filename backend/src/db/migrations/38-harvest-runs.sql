@@ -0,0 +1,35 @@
+-- Stores the outcome of recent harvest requests, to be able to see whether the
+-- sync with Opencast is working (e.g. via the `syncStatus` API). Only recent
+-- runs are kept, older ones are deleted by the harvesting worker.
+create table harvest_runs (
+    id bigint primary key generated always as identity,
+
+    started timestamp with time zone not null,
+    duration_ms bigint not null,
+
+    -- The `since` parameter sent to Opencast, i.e. `harvested_until` at the
+    -- start of this run.
+    since timestamp with time zone not null,
+
+    -- `includesItemsUntil` of Opencast's response. Null if the run failed.
+    includes_items_until timestamp with time zone,
+
+    upserted_events int not null default 0,
+    removed_events int not null default 0,
+    upserted_series int not null default 0,
+    removed_series int not null default 0,
+    upserted_playlists int not null default 0,
+    removed_playlists int not null default 0,
+
+    -- Error message, if the run failed, and the time waited before the next
+    -- attempt.
+    error text,
+    backoff_ms bigint,
+
+    constraint failed_or_successful check (
+        (error is null) = (includes_items_until is not null)
+        and (error is null) = (backoff_ms is null)
+    )
+);
+
+create index idx_harvest_runs_started on harvest_runs (started);
@@ -0,0 +1,69 @@
+-- Adds support for syncing from multiple Opencast instances ("sync sources").
+-- All Opencast items are tagged with the name of the source they were
+-- harvested from and Opencast IDs are only unique per source. The source
+-- configured via `opencast.sync_node` and `sync.user` is called 'default',
+-- which is what all existing data is assigned to.
+
+-- One sync status per source. Rows for additional sources are inserted by the
+-- harvesting worker.
+alter table sync_status
+    add column source text not null default 'default' primary key;
+alter table sync_status
+    alter column source drop default;
+
+alter table harvest_runs
+    add column source text not null default 'default';
+alter table harvest_runs
+    alter column source drop default;
+
+
+alter table events
+    add column sync_source text not null default 'default',
+    drop constraint events_opencast_id_key,
+    add constraint events_opencast_id_key unique (sync_source, opencast_id);
+
+alter table series
+    add column sync_source text not null default 'default',
+    drop constraint series_opencast_id_key,
+    add constraint series_opencast_id_key unique (sync_source, opencast_id);
+
+alter table playlists
+    add column sync_source text not null default 'default',
+    drop constraint playlists_opencast_id_key,
+    add constraint playlists_opencast_id_key unique (sync_source, opencast_id);
+
+
+-- Remembered IDs of deleted items are also per source.
+alter table deleted_items
+    add column sync_source text not null default 'default',
+    drop constraint deleted_items_pkey,
+    add primary key (sync_source, opencast_id, kind);
+
+create or replace function remember_deleted_opencast_items()
+   returns trigger
+   language plpgsql
+as $$
+begin
+    insert into deleted_items (sync_source, opencast_id, kind, our_id)
+        values (old.sync_source, old.opencast_id, tg_argv[0]::opencast_item_kind, old.id)
+        on conflict do nothing;
+    return null;
+end;
+$$;
+
+create or replace function reuse_existing_id_on_insert()
+   returns trigger
+   language plpgsql
+as $$
+declare
+    remembered_id bigint;
+begin
+    delete from deleted_items
+        where sync_source = new.sync_source
+            and opencast_id = new.opencast_id
+            and kind = tg_argv[0]::opencast_item_kind
+        returning our_id into remembered_id;
+    new.id := coalesce(remembered_id, new.id);
+    return new;
+end;
+$$;
@@ -0,0 +1,24 @@
+-- Tobira can send changes of events (e.g. a new title) to Opencast. These
+-- changes are applied to our DB right away, but are only confirmed once the
+-- harvest returns a newer version of the event. Until then, the event is
+-- marked as having a pending write.
+
+alter table events
+    add column pending_write_since timestamp with time zone;
+
+create function clear_pending_write()
+   returns trigger
+   language plpgsql
+as $$
+begin
+    -- A newer version of the event from Opencast includes our changes.
+    new.pending_write_since := null;
+    return new;
+end;
+$$;
+
+create trigger clear_pending_write_on_harvest
+    before update on events
+    for each row
+    when (old.pending_write_since is not null and new.updated > old.updated)
+    execute procedure clear_pending_write();
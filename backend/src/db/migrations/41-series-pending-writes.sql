@@ -0,0 +1,12 @@
+-- Like events, series can be changed from within Tobira (e.g. their ACL). As
+-- for events, these changes are only confirmed once the harvest returns a
+-- newer version of the series.
+
+alter table series
+    add column pending_write_since timestamp with time zone;
+
+create trigger clear_pending_write_on_harvest
+    before update on series
+    for each row
+    when (old.pending_write_since is not null and new.updated > old.updated)
+    execute procedure clear_pending_write();
@@ -0,0 +1,49 @@
+-- Events can be deleted from within Tobira. Deletion in Opencast can take a
+-- while, so until the harvest returns an `event-deleted` item (which removes
+-- the event from our DB), the event is marked as pending deletion. Such events
+-- are hidden from blocks and the search.
+
+alter table events
+    add column deletion_pending_since timestamp with time zone;
+
+
+-- Add the new column to the search view. This is the same definition as in
+-- `36-event-segments` with the `deletion_pending_since` column added at the
+-- end.
+create or replace view search_events as
+    select
+        events.id, events.state,
+        events.series, series.title as series_title,
+        events.title, events.description, events.creators,
+        events.thumbnail, events.duration,
+        events.is_live, events.created, events.start_time, events.end_time,
+        events.read_roles, events.write_roles,
+        coalesce(
+            array_agg(
+                distinct
+                row(search_realms.*)::search_realms
+            ) filter(where search_realms.id is not null),
+            '{}'
+        ) as host_realms,
+        not exists (
+            select from unnest(events.tracks) as t where t.resolution is not null
+        ) as audio_only,
+        array(
+            select texts.t
+            from event_texts, unnest(event_texts.texts) as texts
+            where event_texts.event_id = events.id
+        ) as caption_texts,
+        array(
+            select segments.text
+            from unnest(events.segments) as segments
+            where segments.text is not null
+        ) as slide_texts,
+        events.deletion_pending_since
+    from events
+    left join series on events.series = series.id
+    left join blocks on (
+        type = 'series' and blocks.series = events.series
+        or type = 'video' and blocks.video = events.id
+    )
+    left join search_realms on search_realms.id = blocks.realm
+    group by events.id, series.id;
@@ -0,0 +1,45 @@
+-- Stores previous paths of realms, so that old links keep working after a
+-- realm was moved or its path segment changed. Entries are maintained by
+-- the triggers below: operations never have to touch this table.
+
+create table realm_path_history (
+    old_path text primary key,
+    realm bigint not null references realms on delete cascade,
+    changed timestamp with time zone not null default now()
+);
+
+create index idx_realm_path_history_realm on realm_path_history (realm);
+
+
+-- Records the old path whenever the full path of a realm changes. As changing
+-- the path of a realm changes the path of all its descendants (via triggers
+-- updating `full_path`), this fires for all of them. Additionally, the current
+-- path of a realm always wins over old paths of other realms, so new realms
+-- can reclaim a freed path.
+create function record_realm_path_history()
+   returns trigger
+   language plpgsql
+as $$
+begin
+    delete from realm_path_history where old_path = new.full_path;
+
+    if tg_op = 'UPDATE' and old.full_path <> '' then
+        insert into realm_path_history (old_path, realm)
+        values (old.full_path, new.id)
+        on conflict (old_path) do update set realm = new.id, changed = now();
+    end if;
+
+    return null;
+end;
+$$;
+
+create trigger record_realm_path_history_on_insert
+    after insert on realms
+    for each row
+    execute procedure record_realm_path_history();
+
+create trigger record_realm_path_history_on_update
+    after update on realms
+    for each row
+    when (old.full_path is distinct from new.full_path)
+    execute procedure record_realm_path_history();
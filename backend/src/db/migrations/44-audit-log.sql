@@ -0,0 +1,42 @@
+-- Persistent log of all mutations changing realms (pages) and their blocks.
+-- Each entry stores snapshots of the affected realm (including its blocks)
+-- from before and after the mutation. `realm` intentionally has no foreign
+-- key so that entries survive the deletion of the realm.
+
+create table audit_log (
+    id bigint primary key generated always as identity,
+    timestamp timestamp with time zone not null default now(),
+
+    -- `null` if the mutation was performed by a trusted external application.
+    username text,
+    -- Name of the GraphQL mutation, e.g. `removeBlock`.
+    mutation text not null,
+
+    realm bigint,
+    realm_path text,
+    block bigint,
+
+    before jsonb,
+    after jsonb
+);
+
+create index idx_audit_log_realm on audit_log (realm, timestamp);
+
+
+-- Returns the realm with the given ID as JSON object, with all its blocks
+-- (ordered by index) in the field `blocks`. Returns `null` if the realm does
+-- not exist.
+create function realm_snapshot(realm_id bigint)
+    returns jsonb
+    language sql
+    stable
+as $$
+    select to_jsonb(realms) || jsonb_build_object('blocks', coalesce(
+        (select jsonb_agg(to_jsonb(blocks) order by blocks.index)
+            from blocks
+            where blocks.realm = realms.id),
+        '[]'::jsonb
+    ))
+    from realms
+    where realms.id = realm_id
+$$;
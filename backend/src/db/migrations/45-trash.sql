@@ -0,0 +1,50 @@
+-- Removed realms and blocks are moved into this table instead of being
+-- deleted right away, so that they can be restored. Entries are purged by
+-- the worker once they are older than `general.trash_retention`.
+
+create table trash (
+    id bigint primary key generated always as identity,
+
+    -- Exactly one of these is set: the ID of the removed realm or block.
+    realm bigint,
+    block bigint,
+
+    -- The parent of the removed realm or the realm of the removed block.
+    -- Intentionally no foreign key, as that realm might be removed later.
+    parent bigint,
+    -- The full path of the removed realm or the realm of the removed block.
+    path text not null,
+
+    -- For realms: array of snapshots (see `realm_snapshot`) of the realm and
+    -- all its descendants, ordered by path. For blocks: the block row.
+    data jsonb not null,
+
+    removed timestamp with time zone not null default now(),
+    -- `null` if removed by a trusted external application.
+    removed_by text,
+
+    constraint realm_xor_block check ((realm is null) != (block is null))
+);
+
+create unique index idx_trash_realm on trash (realm);
+create unique index idx_trash_block on trash (block);
+create index idx_trash_parent on trash (parent);
+create index idx_trash_removed on trash (removed);
+
+
+-- Inserts the given blocks (array of block rows as JSON), keeping their IDs.
+-- References to series, events and playlists that were deleted in the
+-- meantime are set to `null`, just like `on delete set null` would have.
+create function restore_blocks(data jsonb)
+    returns void
+    language sql
+as $$
+    insert into blocks
+    select b.*
+    from jsonb_array_elements(data) as elements,
+        jsonb_populate_record(null::blocks, elements.value || jsonb_build_object(
+            'series', (select id from series where id = (elements.value->>'series')::bigint),
+            'video', (select id from events where id = (elements.value->>'video')::bigint),
+            'playlist', (select id from playlists where id = (elements.value->>'playlist')::bigint)
+        )) as b
+$$;
@@ -0,0 +1,78 @@
+-- Drafts of realm pages: while a realm has a draft, all block mutations of
+-- that realm operate on the draft blocks instead of the live ones. Publishing
+-- the draft replaces the live blocks with the draft blocks.
+
+create table realm_drafts (
+    realm bigint primary key references realms on delete cascade,
+    created timestamp with time zone not null default now(),
+    -- `null` if created by a trusted external application.
+    created_by text
+);
+
+-- Same structure as `blocks`. Blocks are copied from `blocks` when the draft
+-- is created (keeping their IDs) and copied back when publishing. Whenever a
+-- column is added to `blocks`, it has to be added here and to the column list
+-- used for copying (`BLOCK_COLUMNS` in `api/model/realm/draft.rs`) as well.
+create table draft_blocks (like blocks including defaults including constraints);
+
+alter table draft_blocks
+    add primary key (id),
+    add foreign key (realm) references realm_drafts on delete cascade,
+    add foreign key (series) references series on delete set null,
+    add foreign key (video) references events on delete set null,
+    add foreign key (playlist) references playlists on delete set null,
+    add constraint draft_index_unique_in_realm
+        unique (realm, index) deferrable initially immediate;
+
+create index idx_draft_block_realm on draft_blocks (realm);
+
+
+-- Drafts are part of realm snapshots, so that removing a realm to the trash and
+-- restoring it keeps its draft. Same as in `44-audit-log`, with the
+-- additional `draft` field, which is `null` if the realm has no draft.
+create or replace function realm_snapshot(realm_id bigint)
+    returns jsonb
+    language sql
+    stable
+as $$
+    select to_jsonb(realms) || jsonb_build_object(
+        'blocks', coalesce(
+            (select jsonb_agg(to_jsonb(blocks) order by blocks.index)
+                from blocks
+                where blocks.realm = realms.id),
+            '[]'::jsonb
+        ),
+        'draft', (
+            select to_jsonb(realm_drafts) || jsonb_build_object('blocks', coalesce(
+                (select jsonb_agg(to_jsonb(draft_blocks) order by draft_blocks.index)
+                    from draft_blocks
+                    where draft_blocks.realm = realms.id),
+                '[]'::jsonb
+            ))
+            from realm_drafts
+            where realm_drafts.realm = realms.id
+        )
+    )
+    from realms
+    where realms.id = realm_id
+$$;
+
+-- Inserts the given draft (the `draft` field of a realm snapshot) including
+-- its blocks. Like `restore_blocks` (see `45-trash`), references to deleted
+-- series, events and playlists are set to `null`.
+create function restore_draft(data jsonb)
+    returns void
+    language sql
+as $$
+    insert into realm_drafts
+    select d.* from jsonb_populate_record(null::realm_drafts, data - 'blocks') as d;
+
+    insert into draft_blocks
+    select b.*
+    from jsonb_array_elements(data->'blocks') as elements,
+        jsonb_populate_record(null::draft_blocks, elements.value || jsonb_build_object(
+            'series', (select id from series where id = (elements.value->>'series')::bigint),
+            'video', (select id from events where id = (elements.value->>'video')::bigint),
+            'playlist', (select id from playlists where id = (elements.value->>'playlist')::bigint)
+        )) as b
+$$;
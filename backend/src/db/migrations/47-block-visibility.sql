@@ -0,0 +1,110 @@
+-- Blocks can be scheduled to only be visible in a specific time window. Outside
+-- of that window, they are hidden from non-moderators and do not make their
+-- events or series listed in the search.
+
+alter table blocks
+    add column visible_from timestamp with time zone,
+    add column visible_until timestamp with time zone,
+    add constraint visibility_window_not_empty check (visible_from < visible_until);
+
+alter table draft_blocks
+    add column visible_from timestamp with time zone,
+    add column visible_until timestamp with time zone,
+    add constraint draft_visibility_window_not_empty check (visible_from < visible_until);
+
+create index idx_block_visible_from on blocks (visible_from) where visible_from is not null;
+create index idx_block_visible_until on blocks (visible_until) where visible_until is not null;
+
+create function block_visible_now(visible_from timestamptz, visible_until timestamptz)
+    returns boolean
+    language sql
+    stable
+as $$
+    select (visible_from is null or visible_from <= now())
+        and (visible_until is null or visible_until > now())
+$$;
+
+
+-- The search index stores whether an event or series is listed, which depends
+-- on the visibility of blocks. So whenever a visibility window opens or
+-- closes, the affected items have to be queued for reindex. This table stores
+-- the last time we checked for that (see `search::update`).
+create table block_visibility_check (
+    id smallint primary key default 1 check (id = 1),
+    last_check timestamp with time zone not null default now()
+);
+
+insert into block_visibility_check default values;
+
+
+-- Only consider visible blocks for `host_realms`. This is the same definition
+-- as in `42-event-deletion` with the additional condition in the join.
+create or replace view search_events as
+    select
+        events.id, events.state,
+        events.series, series.title as series_title,
+        events.title, events.description, events.creators,
+        events.thumbnail, events.duration,
+        events.is_live, events.created, events.start_time, events.end_time,
+        events.read_roles, events.write_roles,
+        coalesce(
+            array_agg(
+                distinct
+                row(search_realms.*)::search_realms
+            ) filter(where search_realms.id is not null),
+            '{}'
+        ) as host_realms,
+        not exists (
+            select from unnest(events.tracks) as t where t.resolution is not null
+        ) as audio_only,
+        array(
+            select texts.t
+            from event_texts, unnest(event_texts.texts) as texts
+            where event_texts.event_id = events.id
+        ) as caption_texts,
+        array(
+            select segments.text
+            from unnest(events.segments) as segments
+            where segments.text is not null
+        ) as slide_texts,
+        events.deletion_pending_since
+    from events
+    left join series on events.series = series.id
+    left join blocks on (
+        (
+            type = 'series' and blocks.series = events.series
+            or type = 'video' and blocks.video = events.id
+        )
+        and block_visible_now(blocks.visible_from, blocks.visible_until)
+    )
+    left join search_realms on search_realms.id = blocks.realm
+    group by events.id, series.id;
+
+-- Same as in `19-series-search-view`, with the additional conditions for
+-- `listed_via_events` and the join.
+create or replace view search_series as
+    select
+        series.id, series.state, series.opencast_id,
+        series.read_roles, series.write_roles,
+        series.title, series.description,
+        exists (select
+            from blocks
+            inner join events on events.id = blocks.video
+            inner join realms on realms.id = blocks.realm
+            where events.series = series.id
+                and realms.full_path not like '/@%'
+                and block_visible_now(blocks.visible_from, blocks.visible_until)
+        ) as listed_via_events,
+        coalesce(
+            array_agg((
+                select row(search_realms.*)::search_realms
+                from search_realms
+                where search_realms.id = blocks.realm
+            )) filter(where blocks.realm is not null),
+            '{}'
+        ) as host_realms
+    from series
+    left join blocks on type = 'series'
+        and blocks.series = series.id
+        and block_visible_now(blocks.visible_from, blocks.visible_until)
+    group by series.id;
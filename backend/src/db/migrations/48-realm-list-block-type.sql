@@ -0,0 +1,7 @@
+-- Realm pages can show a list of realms (either selected ones or all children
+-- of the realm) via a new block type. The columns and constraints are added
+-- in the next migration, as a new enum value cannot be used in the same
+-- transaction that adds it.
+alter type block_type add value 'realm_list';
+
+create type realm_list_layout as enum ('gallery', 'list');
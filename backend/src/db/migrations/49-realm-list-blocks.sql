@@ -0,0 +1,18 @@
+-- Adds the columns used by realm list blocks. `realms` contains the IDs of the
+-- listed realms in order, or is `null` if all children of the block's realm
+-- are listed. It cannot have a foreign key constraint, so removed realms are
+-- simply skipped when loading the block.
+
+alter table blocks
+    add column realms bigint[],
+    add column realmlist_layout realm_list_layout,
+    add constraint realm_list_block_has_fields check (type <> 'realm_list' or (
+        realmlist_layout is not null
+    ));
+
+alter table draft_blocks
+    add column realms bigint[],
+    add column realmlist_layout realm_list_layout,
+    add constraint draft_realm_list_block_has_fields check (type <> 'realm_list' or (
+        realmlist_layout is not null
+    ));
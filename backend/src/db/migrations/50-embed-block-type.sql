@@ -0,0 +1,4 @@
+-- Realm pages can embed external content (e.g. quizzes or slide decks) via a
+-- new block type. The column and constraints are added in the next migration,
+-- as a new enum value cannot be used in the same transaction that adds it.
+alter type block_type add value 'embed';
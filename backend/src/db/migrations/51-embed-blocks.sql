@@ -0,0 +1,15 @@
+-- Adds the column used by embed blocks. Whether the URL belongs to one of the
+-- allowed origins (`general.embed_origins`) is checked by the API, as the
+-- configuration might change.
+
+alter table blocks
+    add column embed_url text,
+    add constraint embed_block_has_fields check (type <> 'embed' or (
+        embed_url is not null
+    ));
+
+alter table draft_blocks
+    add column embed_url text,
+    add constraint draft_embed_block_has_fields check (type <> 'embed' or (
+        embed_url is not null
+    ));
@@ -0,0 +1,128 @@
+-- Events shown via playlist blocks were neither listed in the search index nor
+-- linked to the realm hosting the block. This adds playlist blocks to the
+-- search view and makes sure the affected events are queued for reindex when
+-- such a block or the playlist changes.
+
+-- Same as in `47-block-visibility`, with playlist blocks added to the join.
+create or replace view search_events as
+    select
+        events.id, events.state,
+        events.series, series.title as series_title,
+        events.title, events.description, events.creators,
+        events.thumbnail, events.duration,
+        events.is_live, events.created, events.start_time, events.end_time,
+        events.read_roles, events.write_roles,
+        coalesce(
+            array_agg(
+                distinct
+                row(search_realms.*)::search_realms
+            ) filter(where search_realms.id is not null),
+            '{}'
+        ) as host_realms,
+        not exists (
+            select from unnest(events.tracks) as t where t.resolution is not null
+        ) as audio_only,
+        array(
+            select texts.t
+            from event_texts, unnest(event_texts.texts) as texts
+            where event_texts.event_id = events.id
+        ) as caption_texts,
+        array(
+            select segments.text
+            from unnest(events.segments) as segments
+            where segments.text is not null
+        ) as slide_texts,
+        events.deletion_pending_since
+    from events
+    left join series on events.series = series.id
+    left join blocks on (
+        (
+            type = 'series' and blocks.series = events.series
+            or type = 'video' and blocks.video = events.id
+            or type = 'playlist' and exists (
+                select
+                from playlists, unnest(playlists.entries) as entry
+                where playlists.id = blocks.playlist
+                    and playlists.sync_source = events.sync_source
+                    and entry.type = 'event'
+                    and entry.content_id = events.opencast_id
+            )
+        )
+        and block_visible_now(blocks.visible_from, blocks.visible_until)
+    )
+    left join search_realms on search_realms.id = blocks.realm
+    group by events.id, series.id;
+
+
+-- Returns the IDs of all events in the given playlist.
+create function playlist_event_ids(playlist playlists)
+    returns setof bigint
+    language sql
+    stable
+as $$
+    select events.id
+    from unnest(playlist.entries) as entry
+    inner join events
+        on events.sync_source = playlist.sync_source
+        and events.opencast_id = entry.content_id
+    where entry.type = 'event'
+$$;
+
+-- Same as in `20-fix-queue-triggers`, but also queues the events of playlist
+-- blocks.
+create or replace function queue_block_for_reindex(block blocks)
+   returns void
+   language sql
+as $$
+    with
+        the_series(id) as (
+            select series from events where id = block.video and series is not null
+            union all select block.series where block.series is not null
+        ),
+        listed_events as (
+            select id from events where id = block.video
+            union all select events.id
+                from the_series
+                inner join events on events.series = the_series.id
+            union all select playlist_event_ids(playlists)
+                from playlists
+                where playlists.id = block.playlist
+        ),
+        new_entries(id, kind) as (
+            select id, 'series'::search_index_item_kind from the_series
+            union all select id, 'event'::search_index_item_kind from listed_events
+        )
+    insert into search_index_queue (item_id, kind)
+    select id, kind from new_entries
+    on conflict do nothing;
+$$;
+
+-- If the entries of a playlist shown in some block change, the old and new
+-- events have to be queued.
+create function queue_playlist_events_for_reindex()
+   returns trigger
+   language plpgsql
+as $$
+begin
+    if exists(select from blocks where playlist = new.id) then
+        insert into search_index_queue (item_id, kind)
+        select id, 'event'
+        from (
+            select playlist_event_ids(old) as id
+            union select playlist_event_ids(new)
+        ) as events
+        on conflict do nothing;
+    end if;
+    return null;
+end;
+$$;
+
+create trigger queue_playlist_events_for_reindex
+after update of entries
+on playlists
+for each row
+when (old.entries is distinct from new.entries)
+execute procedure queue_playlist_events_for_reindex();
+
+-- Queue all events already shown via playlist blocks.
+select queue_block_for_reindex(blocks) from blocks where type = 'playlist';
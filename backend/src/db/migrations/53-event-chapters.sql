@@ -0,0 +1,25 @@
+-- Adds a `chapters` field to `events`, which stores the chapters defined in
+-- Opencast (e.g. via the editor), each with a start time and a title.
+
+create type event_chapter as (
+    start_time bigint, -- in ms
+    title text
+);
+
+alter table events
+    add column chapters event_chapter[]
+        default '{}'
+        constraint no_null_chapter_items check (array_position(chapters, null) is null);
+
+alter table events
+    -- The default above was just for all existing records. New records should
+    -- require this to be set.
+    alter column chapters drop default,
+    drop constraint ready_event_has_fields,
+    add constraint ready_event_has_fields check (state <> 'ready' or (
+        duration is not null
+        and tracks is not null and array_length(tracks, 1) > 0
+        and captions is not null
+        and segments is not null
+        and chapters is not null
+    ));
@@ -0,0 +1,50 @@
+-- The trigger from `40-event-pending-writes` cleared the pending write flag as
+-- soon as the harvest returned any newer version of the event. But a version
+-- that was created in Opencast before our change (e.g. an item that was still
+-- in the harvest queue) does not include it. Such a stale version must neither
+-- clear the flag nor overwrite the fields changed in Tobira.
+--
+-- To tell these apart, we store the `updated` timestamp the event had when the
+-- write was sent to Opencast. Only versions newer than that include our
+-- changes. Comparing with `pending_write_since` instead would compare times of
+-- two different servers.
+
+alter table events
+    add column updated_before_pending_write timestamp with time zone;
+
+-- For writes that are already pending, the current `updated` is our best guess.
+update events
+    set updated_before_pending_write = updated
+    where pending_write_since is not null;
+
+create function check_pending_write()
+   returns trigger
+   language plpgsql
+as $$
+begin
+    if new.pending_write_since is distinct from old.pending_write_since then
+        -- This is not the harvest, but Tobira writing (new) changes.
+        return new;
+    end if;
+
+    if new.updated > old.updated_before_pending_write then
+        -- This version from Opencast includes our changes.
+        new.pending_write_since := null;
+        new.updated_before_pending_write := null;
+    else
+        -- Stale version: keep our changes until Opencast confirms them.
+        new.title := old.title;
+        new.description := old.description;
+    end if;
+    return new;
+end;
+$$;
+
+-- This fires for every update, even if `updated` did not change: re-harvesting
+-- the same version (e.g. after `sync reset`) must not revert our changes either.
+drop trigger clear_pending_write_on_harvest on events;
+create trigger check_pending_write_on_update
+    before update on events
+    for each row
+    when (old.pending_write_since is not null)
+    execute procedure check_pending_write();
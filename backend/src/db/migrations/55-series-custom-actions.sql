@@ -0,0 +1,91 @@
+-- Series ACLs can contain custom actions as well. Like for events (see
+-- `32-custom-actions`), we store them so that changing the ACL of a series in
+-- Tobira does not lose them.
+
+alter table series
+    add column custom_action_roles jsonb;
+
+-- Same as in `32-custom-actions`, but works for both tables.
+create or replace function check_custom_actions_format() returns trigger as $$
+declare
+    col text := tg_table_name || '.custom_action_roles';
+    field record;
+    element jsonb;
+begin
+    if jsonb_typeof(new.custom_action_roles) <> 'object' then
+        raise exception '% is %, but should be a JSON object', col, jsonb_typeof(new.custom_action_roles);
+    end if;
+
+    for field in select * from jsonb_each(new.custom_action_roles) loop
+        if jsonb_typeof(field.value) <> 'array' then
+            raise exception '%: type of field "%" is %, but should be an array',
+                col,
+                field.key,
+                jsonb_typeof(field.value);
+        end if;
+
+        for element in select * from jsonb_array_elements(field.value) loop
+            if jsonb_typeof(element) <> 'string' then
+                raise exception '%: found non-string element "%" in field "%", but that field should be a string array',
+                    col,
+                    element,
+                    field.key;
+            end if;
+        end loop;
+    end loop;
+
+    return new;
+end;
+$$ language plpgsql;
+
+create trigger check_custom_actions_format_on_upsert
+    before insert or update on series
+    for each row
+    execute procedure check_custom_actions_format();
+
+
+-- Like for events (see `54-event-pending-write-check`), stale versions from
+-- the harvest must neither clear the pending write flag nor overwrite the
+-- changes made in Tobira. The same function is used for both tables and now
+-- also covers changed ACLs.
+alter table series
+    add column updated_before_pending_write timestamp with time zone;
+
+update series
+    set updated_before_pending_write = updated
+    where pending_write_since is not null;
+
+create or replace function check_pending_write()
+   returns trigger
+   language plpgsql
+as $$
+begin
+    if new.pending_write_since is distinct from old.pending_write_since then
+        -- This is not the harvest, but Tobira writing (new) changes.
+        return new;
+    end if;
+
+    if new.updated > old.updated_before_pending_write then
+        -- This version from Opencast includes our changes.
+        new.pending_write_since := null;
+        new.updated_before_pending_write := null;
+    else
+        -- Stale version: keep our changes until Opencast confirms them.
+        new.title := old.title;
+        new.description := old.description;
+        new.read_roles := old.read_roles;
+        new.write_roles := old.write_roles;
+        new.custom_action_roles := old.custom_action_roles;
+    end if;
+    return new;
+end;
+$$;
+
+drop trigger clear_pending_write_on_harvest on series;
+create trigger check_pending_write_on_update
+    before update on series
+    for each row
+    when (old.pending_write_since is not null)
+    execute procedure check_pending_write();
+
+drop function clear_pending_write;
@@ -0,0 +1,53 @@
+use std::path::Path;
+
+use chrono::NaiveDateTime;
+
+use crate::{
+    prelude::*,
+    db::types::Key,
+    sync::{harvest::{self, HarvestSource, RecordedPages}, DEFAULT_SOURCE},
+};
+use super::util::TestDb;
+
+
+/// Replays the recording in `harvest-recording` into an empty DB and checks
+/// the resulting state.
+#[tokio::test(flavor = "multi_thread")]
+async fn replay_recording() -> Result<()> {
+    let db = TestDb::with_migrations().await?;
+    let config = crate::Config::load_from("../util/dev-config/config.toml")
+        .context("failed to load config")?;
+
+    let pages = RecordedPages::load(Path::new("src/db/tests/harvest-recording"))?;
+    let conn = db.pool().await?.get().await?;
+    harvest::run(false, &config, DEFAULT_SOURCE, HarvestSource::Recorded(pages), conn).await?;
+
+    // The series and its event were inserted, the other event was deleted
+    // again in the second page.
+    let series = db.query_one(
+        "select id, title from series where opencast_id = 'series-a' and sync_source = $1",
+        &[&DEFAULT_SOURCE],
+    ).await?;
+    assert_eq!(series.get::<_, String>("title"), "Series A");
+
+    let events = db.query("select opencast_id, series from events", &[]).await?;
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].get::<_, String>("opencast_id"), "event-a");
+    assert_eq!(events[0].get::<_, Option<Key>>("series"), Some(series.get::<_, Key>("id")));
+
+    // The playlist entry of unknown type was skipped.
+    let entries = db.query_one(
+        "select array_length(entries, 1) from playlists where opencast_id = 'playlist-a'",
+        &[],
+    ).await?;
+    assert_eq!(entries.get::<_, Option<i32>>(0), Some(1));
+
+    // `harvested_until` is the `includesItemsUntil` of the last page.
+    let status = db.query_one(
+        "select harvested_until from sync_status where source = $1",
+        &[&DEFAULT_SOURCE],
+    ).await?;
+    assert_eq!(status.get::<_, NaiveDateTime>(0).timestamp_millis(), 1700000010000);
+
+    Ok(())
+}
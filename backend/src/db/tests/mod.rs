@@ -4,6 +4,8 @@ use self::util::TestDb;
 
 mod util;
 mod search_queue;
+mod harvest;
+mod realm;
 
 
 #[tokio::test(flavor = "multi_thread")]
@@ -0,0 +1,185 @@
+use std::collections::HashSet;
+
+use crate::{
+    prelude::*,
+    api::{Context, Id, model::realm::{Realm, BLOCK_COLUMNS}},
+    db::types::Key,
+};
+use super::util::TestDb;
+
+
+struct Setup {
+    animals: Key,
+    cat: Key,
+    momo: Key,
+    people: Key,
+}
+
+/// Creates a test DB with the following realm tree:
+///
+/// ```text
+/// - animals
+///   - dog
+///   - cat
+///     - momo
+/// - people
+///   - cat
+/// ```
+async fn setup() -> Result<(TestDb, Setup)> {
+    let db = TestDb::with_migrations().await?;
+
+    let animals = db.add_realm("animals", Key(0), "animals").await?;
+    let people = db.add_realm("people", Key(0), "people").await?;
+    db.add_realm("dog", animals, "dog").await?;
+    let cat = db.add_realm("cat", animals, "cat").await?;
+    let momo = db.add_realm("momo", cat, "momo").await?;
+    db.add_realm("cat person", people, "cat").await?;
+
+    Ok((db, Setup { animals, cat, momo, people }))
+}
+
+async fn move_realm(
+    realm: Key,
+    new_parent: Key,
+    index: i32,
+    context: &Context,
+) -> Result<Realm, Option<&'static str>> {
+    Realm::move_to(Id::realm(realm), Id::realm(new_parent), index, context)
+        .await
+        .map_err(|e| e.key)
+}
+
+async fn full_path(realm: Key, context: &Context) -> Result<String> {
+    let row = context.db.query_one("select full_path from realms where id = $1", &[&realm])
+        .await?;
+    Ok(row.get(0))
+}
+
+async fn resolve_path(path: &str, context: &Context) -> Result<Option<Key>> {
+    Realm::load_by_current_or_old_path(path.into(), context)
+        .await
+        .map(|realm| realm.map(|realm| realm.key))
+        .map_err(|e| anyhow!(e.msg))
+}
+
+
+#[tokio::test(flavor = "multi_thread")]
+async fn move_into_own_subtree() -> Result<()> {
+    let (db, Setup { animals, cat, momo, .. }) = setup().await?;
+
+    db.with_api_context(|context| async move {
+        for (realm, new_parent) in [(cat, cat), (cat, momo), (animals, momo)] {
+            let res = move_realm(realm, new_parent, 0, context).await;
+            assert_eq!(res.err(), Some(Some("realm.move-into-own-subtree")));
+        }
+
+        assert_eq!(full_path(cat, context).await?, "/animals/cat");
+        assert_eq!(full_path(momo, context).await?, "/animals/cat/momo");
+        Ok::<_, anyhow::Error>(())
+    }.boxed_local()).await
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn move_to_root() -> Result<()> {
+    let (db, Setup { animals, cat, momo, people }) = setup().await?;
+    db.execute("update realms set child_order = 'by_index' where id = 0", &[]).await?;
+    db.execute("update realms set index = 0 where id = $1", &[&animals]).await?;
+    db.execute("update realms set index = 1 where id = $1", &[&people]).await?;
+
+    db.with_api_context(|context| async move {
+        let moved = move_realm(cat, Key(0), 1, context).await.ok().expect("move failed");
+        assert_eq!(moved.full_path, "/cat");
+        assert_eq!(full_path(momo, context).await?, "/cat/momo");
+
+        // The siblings are renumbered with the moved realm at the given index.
+        let order = context.db
+            .query_mapped(
+                "select id from realms where parent = 0 order by index",
+                dbargs![],
+                |row| row.get::<_, Key>(0),
+            )
+            .await?;
+        assert_eq!(order, [animals, cat, people]);
+
+        // Old paths of the moved realm and its descendants redirect to them.
+        assert_eq!(resolve_path("/animals/cat", context).await?, Some(cat));
+        assert_eq!(resolve_path("/animals/cat/momo", context).await?, Some(momo));
+
+        // Moving back: the current paths win over the old ones again, and the
+        // paths used while at the root now redirect.
+        move_realm(cat, animals, 0, context).await.ok().expect("move failed");
+        assert_eq!(resolve_path("/animals/cat/momo", context).await?, Some(momo));
+        assert_eq!(resolve_path("/cat/momo", context).await?, Some(momo));
+        let stale_entries = context.db
+            .query_one(
+                "select count(*) from realm_path_history \
+                    where old_path in ('/animals/cat', '/animals/cat/momo')",
+                &[],
+            )
+            .await?
+            .get::<_, i64>(0);
+        assert_eq!(stale_entries, 0);
+        Ok::<_, anyhow::Error>(())
+    }.boxed_local()).await
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn move_to_taken_path() -> Result<()> {
+    let (db, Setup { cat, people, .. }) = setup().await?;
+
+    db.with_api_context(|context| async move {
+        let res = move_realm(cat, people, 0, context).await;
+        assert_eq!(res.err(), Some(Some("realm.path-collision")));
+        assert_eq!(full_path(cat, context).await?, "/animals/cat");
+        Ok::<_, anyhow::Error>(())
+    }.boxed_local()).await
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn block_columns_complete() -> Result<()> {
+    let db = TestDb::with_migrations().await?;
+    let listed = BLOCK_COLUMNS.split(',').map(|c| c.trim().to_owned()).collect::<HashSet<_>>();
+
+    for table in ["blocks", "draft_blocks"] {
+        let columns = db
+            .query(
+                "select column_name::text from information_schema.columns \
+                    where table_schema = current_schema() and table_name = $1",
+                &[&table],
+            )
+            .await?
+            .into_iter()
+            .map(|row| row.get::<_, String>(0))
+            .collect::<HashSet<_>>();
+        assert_eq!(columns, listed, "`BLOCK_COLUMNS` does not match columns of `{table}`");
+    }
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn restore_keeps_draft() -> Result<()> {
+    let (db, Setup { cat, momo, .. }) = setup().await?;
+    db.execute("insert into realm_drafts (realm) values ($1)", &[&momo]).await?;
+    db.execute(
+        "insert into draft_blocks (realm, type, index, text_content) \
+            values ($1, 'text', 0, 'Meow')",
+        &[&momo],
+    ).await?;
+
+    db.with_api_context(|context| async move {
+        Realm::remove(Id::realm(cat), context).await.ok().expect("remove failed");
+        Realm::restore(Id::realm(cat), context).await.ok().expect("restore failed");
+
+        let draft_blocks = context.db
+            .query_mapped(
+                "select text_content from draft_blocks \
+                    where realm = $1 and exists(select from realm_drafts where realm = $1)",
+                dbargs![&momo],
+                |row| row.get::<_, String>(0),
+            )
+            .await?;
+        assert_eq!(draft_blocks, ["Meow"]);
+        Ok::<_, anyhow::Error>(())
+    }.boxed_local()).await
+}
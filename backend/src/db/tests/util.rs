@@ -1,8 +1,16 @@
-use std::{ops::Deref, collections::HashSet};
+use std::{ops::Deref, collections::HashSet, mem, sync::Arc};
+use futures::future::LocalBoxFuture;
 use secrecy::ExposeSecret;
 use tokio_postgres::{Client, NoTls};
 
-use crate::{prelude::*, db::types::Key, search::IndexItemKind};
+use crate::{
+    prelude::*,
+    api,
+    auth::{AuthContext, JwtContext, User},
+    db::{types::Key, Transaction},
+    search::{self, IndexItemKind},
+    sync::OcClient,
+};
 use super::DbConfig;
 
 
@@ -67,6 +75,57 @@ impl TestDb {
         Ok(out)
     }
 
+    /// Creates a connection pool for this temporary database, for code that
+    /// requires a `DbConnection` instead of a plain client.
+    pub(super) async fn pool(&self) -> Result<deadpool_postgres::Pool> {
+        let config = crate::Config::load_from("../util/dev-config/config.toml")
+            .context("failed to load config")?;
+        crate::db::create_pool(&DbConfig { database: self.db_name.clone(), ..config.db }).await
+    }
+
+    /// Runs `f` with an API context for this database in which the user is
+    /// a Tobira admin. The API transaction is rolled back afterwards, so `f`
+    /// has to check the DB state via `context.db`.
+    pub(super) async fn with_api_context<T>(
+        &self,
+        f: impl for<'a> FnOnce(&'a api::Context) -> LocalBoxFuture<'a, Result<T>>,
+    ) -> Result<T> {
+        let config = crate::Config::load_from("../util/dev-config/config.toml")
+            .context("failed to load config")?;
+        let pool = self.pool().await?;
+        let mut connection = pool.get().await?;
+        let tx = connection.transaction().await?;
+
+        // Like in `handle_api_request`: `Context` requires a `'static`
+        // transaction. The `Arc` is unwrapped again below, before
+        // `connection` is dropped (also when `f` panics, as `tx` is declared
+        // after `connection`).
+        type PgTx<'a> = deadpool_postgres::Transaction<'a>;
+        let tx = unsafe { Arc::new(mem::transmute::<PgTx<'_>, PgTx<'static>>(tx)) };
+
+        let context = api::Context {
+            db: Transaction::new(tx.clone()),
+            auth: AuthContext::User(User {
+                username: "admin".into(),
+                display_name: "Administrator".into(),
+                email: None,
+                roles: HashSet::from([crate::auth::ROLE_ADMIN.into()]),
+                user_role: "ROLE_USER_ADMIN".into(),
+            }),
+            jwt: Arc::new(JwtContext::new(&config.auth.jwt)?),
+            search: Arc::new(search::Client::new(config.meili.clone())),
+            oc_client: Arc::new(OcClient::new(&config)?),
+            config: Arc::new(config),
+        };
+        let out = f(&context).await;
+
+        drop(context);
+        let tx = Arc::try_unwrap(tx)
+            .unwrap_or_else(|_| panic!("test kept reference to API transaction"));
+        tx.rollback().await?;
+        out
+    }
+
     pub(super) async fn add_realm(
         &self,
         name: &str,
@@ -89,7 +148,7 @@ impl TestDb {
     ) -> Result<Key> {
         let sql = "insert into events
             (state, opencast_id, title, series, is_live, read_roles, write_roles, created,
-                updated, metadata, duration, tracks, captions)
+                updated, metadata, duration, tracks, captions, segments, chapters)
             values
             ('ready', $1, $2, $3, false, '{ROLE_ANONYMOUS}', '{ROLE_ANONYMOUS}',
                 now(), now(), '{}', $4,
@@ -100,6 +159,8 @@ impl TestDb {
                     '{1280, 720}',
                     true
                 )]::event_track[],
+             '{}',
+             '{}',
              '{}'
             )
             returning id";
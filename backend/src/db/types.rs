@@ -68,6 +68,56 @@ pub struct EventCaption {
     pub lang: Option<String>,
 }
 
+/// Represents the `playlist_entry` type defined in `33-playlists.sql`.
+#[derive(Debug, FromSql, ToSql)]
+#[postgres(name = "playlist_entry")]
+pub struct PlaylistEntry {
+    pub entry_id: i64,
+    #[postgres(name = "type")]
+    pub ty: PlaylistEntryType,
+    pub content_id: String,
+}
+
+/// Represents the `playlist_entry_type` type defined in `33-playlists.sql`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromSql, ToSql)]
+#[postgres(name = "playlist_entry_type")]
+pub enum PlaylistEntryType {
+    #[postgres(name = "event")]
+    Event,
+}
+
+/// Represents the `event_segment` type defined in `36-event-segments.sql`.
+#[derive(Debug, FromSql, ToSql)]
+#[postgres(name = "event_segment")]
+pub struct EventSegment {
+    /// Start of the segment in ms.
+    pub start_time: i64,
+    /// Duration of the segment in ms.
+    pub duration: i64,
+    pub thumbnail: Option<String>,
+    pub text: Option<String>,
+}
+
+/// Represents the `event_chapter` type defined in `53-event-chapters.sql`.
+#[derive(Debug, FromSql, ToSql)]
+#[postgres(name = "event_chapter")]
+pub struct EventChapter {
+    /// Start of the chapter in ms.
+    pub start_time: i64,
+    pub title: String,
+}
+
+/// Represents the `timespan_text` type defined in `35-event-texts.sql`.
+#[derive(Debug, FromSql, ToSql)]
+#[postgres(name = "timespan_text")]
+pub struct TimespanText {
+    /// Start of the timespan in ms.
+    pub span_start: i64,
+    /// End of the timespan in ms.
+    pub span_end: i64,
+    pub t: String,
+}
+
 /// Represents the `event_state` type defined in `05-events.sql`.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, FromSql, ToSql)]
 #[postgres(name = "event_state")]
@@ -21,7 +21,8 @@ use crate::{
     metrics::HttpReqCategory,
     prelude::*,
     rss,
-    util::{download_body, ByteBody},
+    sync,
+    util::{download_body, ByteBody, FullBodyExt},
     Config,
 };
 use super::{Context, Response, response};
@@ -75,6 +76,10 @@ pub(super) async fn handle(req: Request<Incoming>, ctx: Arc<Context>) -> Respons
             register_req!(HttpReqCategory::Logout);
             auth::handle_delete_session(req, &ctx).await
         },
+        "/~sync/notify" if method == Method::POST => {
+            register_req!(HttpReqCategory::Other);
+            sync::handle_notify(req, &ctx).await
+        },
 
         // From this point on, we only support GET and HEAD requests. All others
         // will result in 404.
@@ -158,10 +163,13 @@ pub(super) async fn handle(req: Request<Incoming>, ctx: Arc<Context>) -> Respons
             let noindex = path.starts_with("/!")
                 || (path.starts_with("/~") && !path.starts_with("/~about"));
 
-            ctx.assets
-                .serve_index(StatusCode::OK, &ctx.config)
-                .await
-                .make_noindex(noindex)
+            match redirect_old_realm_path(req.uri(), path, &ctx).await {
+                Some(response) => response,
+                None => ctx.assets
+                    .serve_index(StatusCode::OK, &ctx.config)
+                    .await
+                    .make_noindex(noindex),
+            }
         }
     };
     
@@ -170,6 +178,67 @@ pub(super) async fn handle(req: Request<Incoming>, ctx: Arc<Context>) -> Respons
     response
 }
 
+/// If `path` is or starts with a previous path of a realm (see
+/// `realm_path_history`), returns a permanent redirect to the corresponding
+/// current path. Paths of existing realms are never in that table, so this
+/// does not interfere with regular pages.
+async fn redirect_old_realm_path(uri: &Uri, path: &str, ctx: &Context) -> Option<Response> {
+    /// Characters we have to encode when building the new path. `/` has to be
+    /// kept as is.
+    const PATH_ENCODE_SET: &percent_encoding::AsciiSet = &percent_encoding::CONTROLS
+        .add(b' ').add(b'"').add(b'#').add(b'%').add(b'<').add(b'>')
+        .add(b'?').add(b'`').add(b'{').add(b'}');
+
+    // Only realm paths can have been moved.
+    if path.is_empty() || path.starts_with("/~") || path.starts_with("/!") {
+        return None;
+    }
+    let path = percent_encoding::percent_decode_str(path).decode_utf8().ok()?;
+
+    // All paths that might have been an old realm path, i.e. `path` itself
+    // and all its ancestors. This way, the lookup can use the index.
+    let candidates = path.match_indices('/')
+        .skip(1)
+        .map(|(i, _)| &path[..i])
+        .chain([&*path])
+        .collect::<Vec<_>>();
+
+    let db = db::get_conn_or_service_unavailable(&ctx.db_pool).await.ok()?;
+    let query = "select old_path, realms.full_path \
+        from realm_path_history \
+        inner join realms on realms.id = realm_path_history.realm \
+        where old_path = any($1) \
+        order by length(old_path) desc \
+        limit 1";
+    let row = match db.query_opt(query, &[&candidates]).await {
+        Ok(row) => row?,
+        Err(e) => {
+            error!("DB error when checking for old realm path: {e}");
+            return None;
+        }
+    };
+
+    let old_path = row.get::<_, String>(0);
+    let current_path = row.get::<_, String>(1);
+    let new_path = format!("{current_path}{}", &path[old_path.len()..]);
+    let mut location = percent_encoding::utf8_percent_encode(&new_path, PATH_ENCODE_SET)
+        .to_string();
+    if location.is_empty() {
+        location.push('/');
+    }
+    if let Some(query) = uri.query() {
+        location.push('?');
+        location.push_str(query);
+    }
+
+    debug!(%path, %location, "Redirecting old realm path");
+    Some(Response::builder()
+        .status(StatusCode::MOVED_PERMANENTLY)
+        .header(header::LOCATION, location)
+        .body(ByteBody::empty())
+        .unwrap())
+}
+
 async fn handle_rss_request(path: &str, ctx: &Arc<Context>) -> Result<Response, Response> {
     let Some(series_id) = path.strip_prefix("/~rss/series/") else {
         return Ok(response::not_found());
@@ -303,6 +372,7 @@ async fn handle_api(req: Request<Incoming>, ctx: &Context) -> Result<Response, R
         config: ctx.config.clone(),
         jwt: ctx.jwt.clone(),
         search: ctx.search.clone(),
+        oc_client: ctx.oc_client.clone(),
     });
     let gql_result = juniper::execute(
         &gql_request.query,
@@ -464,6 +534,15 @@ impl CommonHeadersExt for hyper::http::response::Builder {
             "'none'".into()
         };
 
+        let frame_sources = if config.general.embed_origins.is_empty() {
+            "'none'".into()
+        } else {
+            config.general.embed_origins.iter()
+                .map(|origin| origin.to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
         // TODO: when this is fixed, use `format_args!` to avoid the useless
         // space in the None case below.
         // https://github.com/rust-lang/rust/issues/92698
@@ -497,6 +576,9 @@ impl CommonHeadersExt for hyper::http::response::Builder {
         //
         // - `form-actions` are needed for the JWT-based pre-authentication to work.
         //
+        // - `frame-src` is needed for embed blocks, which can only embed
+        //   content from the configured `general.embed_origins`.
+        //
         // TODO: check if configuring allowed hosts for `img-src`, `media-src`
         // and `font-src` is an option. Then again, admins can also
         // set/override those headers in their nginx?
@@ -510,6 +592,7 @@ impl CommonHeadersExt for hyper::http::response::Builder {
             connect-src *; \
             worker-src blob: 'self'; \
             form-action {redirect_actions}; \
+            frame-src {frame_sources}; \
         ");
 
         self.header("Content-Security-Policy", value)
@@ -27,6 +27,7 @@ use crate::{
     metrics,
     prelude::*,
     search,
+    sync::OcClient,
     util::{self, ByteBody, HttpsClient, UdxHttpClient},
 };
 use self::{
@@ -78,6 +79,7 @@ pub(crate) struct Context {
     pub(crate) auth_caches: auth::Caches,
     pub(crate) http_client: HttpsClient<ByteBody>,
     pub(crate) uds_http_client: UdxHttpClient<ByteBody>,
+    pub(crate) oc_client: Arc<OcClient>,
 }
 
 
@@ -91,6 +93,7 @@ pub(crate) async fn serve(
 ) -> Result<()> {
     let assets = Assets::init(&config).await.context("failed to initialize assets")?;
     let http_config = config.http.clone();
+    let oc_client = OcClient::new(&config).context("failed to create Opencast client")?;
     let ctx = Arc::new(Context {
         api_root: Arc::new(api_root),
         db_pool: db,
@@ -102,6 +105,7 @@ pub(crate) async fn serve(
         auth_caches: auth::Caches::new(),
         http_client: util::http_client().context("failed to create HTTP client")?,
         uds_http_client: UdxHttpClient::unix(),
+        oc_client: Arc::new(oc_client),
     });
 
     let ctx_clone = ctx.clone();
@@ -114,6 +114,14 @@ async fn run() -> Result<()> {
             let config = load_config_and_init_logger(shared, &args, "cli")?;
             cmd::import_realm_tree::run(options, &config).await?;
         }
+        Command::Realm { options, shared } => {
+            let config = load_config_and_init_logger(shared, &args, "cli")?;
+            cmd::realm::run(&config, options).await?;
+        }
+        Command::AuditLog { options, shared } => {
+            let config = load_config_and_init_logger(shared, &args, "cli")?;
+            cmd::audit_log::run(&config, options).await?;
+        }
         Command::KnownGroups { options, shared } => {
             let config = load_config_and_init_logger(shared, &args, "cli")?;
             cmd::known_groups::run(config, options).await?;
@@ -151,9 +159,11 @@ async fn start_worker(config: Config) -> Result<Never> {
     let search = config.meili.connect().await.context("failed to connect to MeiliSearch")?;
 
     let mut search_conn = db.get().await?;
-    let sync_conn = db.get().await?;
     let db_maintenance_conn = db.get().await?;
     let stats_conn = db.get().await?;
+    let text_conn = db.get().await?;
+    let trash_conn = db.get().await?;
+    let path_history_conn = db.get().await?;
     let auth_config = config.auth.clone();
 
     default_enable_backtraces();
@@ -161,11 +171,16 @@ async fn start_worker(config: Config) -> Result<Never> {
         res = search::update_index_daemon(&search, &mut search_conn) => {
             res.context("error updating the search index")
         }
-        res = sync::run(true, sync_conn, &config) => {
+        res = sync::run(true, &db, &config) => {
             res.map(|()| unreachable!("sync task unexpectedly stopped"))
                 .context("error synchronizing with Opencast")
         }
         never = sync::stats::run_daemon(stats_conn, &config) => { never }
+        never = sync::text::run_daemon(text_conn, &config) => { never }
+        never = api::model::trash::run_purge_daemon(trash_conn, &config) => { never }
+        never = api::model::realm::run_path_history_purge_daemon(path_history_conn, &config) => {
+            never
+        }
         never = auth::db_maintenance(&db_maintenance_conn, &auth_config) => { never }
     }
 }
@@ -20,6 +20,16 @@ const SYNC_LAG: MetricDesc = MetricDesc {
     help: "Number of seconds which the Tobira database is behind the Opencast data",
     unit: Some(Unit::Seconds),
 };
+const SYNC_LAST_HARVEST_AGE: MetricDesc = MetricDesc {
+    name: "sync_last_harvest_age",
+    help: "Number of seconds since the last successful harvest request",
+    unit: Some(Unit::Seconds),
+};
+const SYNC_FAILED_HARVESTS: MetricDesc = MetricDesc {
+    name: "sync_failed_harvests",
+    help: "Number of consecutive failed harvest requests",
+    unit: None,
+};
 const PROCESS_MEMORY: MetricDesc = MetricDesc {
     name: "process_memory",
     help: "Memory usage of the Tobira process. pss = proportional set size, \
@@ -121,12 +131,43 @@ impl Metrics {
         // Information from the DB.
         // TODO: Do all of that in parallel?
         if let Ok(db) = ctx.db_pool.get().await {
-            // Sync lag
-            let sql = "select extract(epoch from now() at time zone 'UTC' \
+            // Sync information, labelled by sync source.
+            let sync_lag = <Family<Vec<(String, String)>, Gauge>>::default();
+            let sql = "select source, extract(epoch from now() at time zone 'UTC' \
                 - harvested_until)::double precision from sync_status";
-            if let Ok(row) = db.query_one(sql, &[]).await {
-                add_gauge(&mut reg, SYNC_LAG, row.get::<_, f64>(0) as i64);
+            if let Ok(rows) = db.query(sql, &[]).await {
+                for row in rows {
+                    sync_lag.get_or_create(&vec![("source".into(), row.get(0))])
+                        .set(row.get::<_, f64>(1) as i64);
+                }
+            }
+            add_any(&mut reg, SYNC_LAG, sync_lag);
+
+            let last_harvest_age = <Family<Vec<(String, String)>, Gauge>>::default();
+            let sql = "select source, extract(epoch from now() - max(started))::double precision \
+                from harvest_runs where error is null group by source";
+            if let Ok(rows) = db.query(sql, &[]).await {
+                for row in rows {
+                    last_harvest_age.get_or_create(&vec![("source".into(), row.get(0))])
+                        .set(row.get::<_, f64>(1) as i64);
+                }
+            }
+            add_any(&mut reg, SYNC_LAST_HARVEST_AGE, last_harvest_age);
+
+            let failed_harvests = <Family<Vec<(String, String)>, Gauge>>::default();
+            let sql = "select source, count(*) filter (where error is not null and started > \
+                    coalesce((select max(started) from harvest_runs inner_runs \
+                        where error is null and inner_runs.source = sync_status.source), \
+                    '-infinity')) \
+                from sync_status left join harvest_runs using (source) \
+                group by source";
+            if let Ok(rows) = db.query(sql, &[]).await {
+                for row in rows {
+                    failed_harvests.get_or_create(&vec![("source".into(), row.get(0))])
+                        .set(row.get::<_, i64>(1));
+                }
             }
+            add_any(&mut reg, SYNC_FAILED_HARVESTS, failed_harvests);
 
             // Search index queue length
             if let Ok(row) = db.query_one("select count(*) from search_index_queue", &[]).await {
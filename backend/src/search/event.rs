@@ -47,6 +47,39 @@ pub(crate) struct Event {
     // store it explicitly to filter for this condition in Meili.
     pub(crate) listed: bool,
     pub(crate) host_realms: Vec<Realm>,
+
+    // Texts of all caption cues. These can get large, so they are only
+    // searchable and never returned by Meili (see `prepare_index`).
+    #[serde(default, skip_deserializing)]
+    pub(crate) caption_texts: Vec<String>,
+
+    // Texts of all segments (e.g. slide texts). Like `caption_texts`, these
+    // are only searchable.
+    #[serde(default, skip_deserializing)]
+    pub(crate) slide_texts: Vec<String>,
+
+    // Cues matching the search query. Not stored in Meili, but filled in by
+    // the search API after querying Meili.
+    #[serde(skip)]
+    pub(crate) text_matches: Vec<TextMatch>,
+}
+
+/// A caption cue or segment of an event that matches a search query.
+#[derive(Debug)]
+pub(crate) struct TextMatch {
+    /// Start of the cue/segment in ms.
+    pub(crate) start: i64,
+    /// Duration of the cue/segment in ms.
+    pub(crate) duration: i64,
+    pub(crate) text: String,
+    pub(crate) ty: TextMatchType,
+}
+
+/// Where the text of a `TextMatch` comes from.
+#[derive(Debug, Clone, Copy, juniper::GraphQLEnum)]
+pub(crate) enum TextMatchType {
+    Caption,
+    SlideText,
 }
 
 impl IndexItem for Event {
@@ -62,7 +95,7 @@ impl_from_db!(
         search_events.{
             id, series, series_title, title, description, creators, thumbnail,
             duration, is_live, created, start_time, end_time, audio_only,
-            read_roles, write_roles, host_realms,
+            read_roles, write_roles, host_realms, caption_texts, slide_texts,
         },
     },
     |row| {
@@ -89,6 +122,9 @@ impl_from_db!(
             write_roles: util::encode_acl(&row.write_roles::<Vec<String>>()),
             listed: host_realms.iter().any(|realm| !realm.is_user_realm()),
             host_realms,
+            caption_texts: row.caption_texts(),
+            slide_texts: row.slide_texts(),
+            text_matches: vec![],
         }
     }
 );
@@ -97,7 +133,7 @@ impl Event {
     pub(crate) async fn load_by_ids(db: &impl GenericClient, ids: &[Key]) -> Result<Vec<Self>> {
         let selection = Self::select();
         let query = format!("select {selection} from search_events \
-            where id = any($1) and state <> 'waiting'");
+            where id = any($1) and state <> 'waiting' and deletion_pending_since is null");
         let rows = db.query_raw(&query, dbargs![&ids]);
         collect_rows_mapped(rows, |row| Self::from_row_start(&row))
             .await
@@ -106,7 +142,8 @@ impl Event {
 
     pub(crate) async fn load_all(db: &impl GenericClient) -> Result<Vec<Self>> {
         let selection = Self::select();
-        let query = format!("select {selection} from search_events where state <> 'waiting'");
+        let query = format!("select {selection} from search_events \
+            where state <> 'waiting' and deletion_pending_since is null");
         let rows = db.query_raw(&query, dbargs![]);
         collect_rows_mapped(rows, |row| Self::from_row_start(&row))
             .await
@@ -118,7 +155,22 @@ pub(super) async fn prepare_index(index: &Index) -> Result<()> {
     util::lazy_set_special_attributes(
         index,
         "event",
-        &["title", "creators", "description", "series_title"],
+        &["title", "creators", "description", "series_title", "slide_texts", "caption_texts"],
         &["listed", "read_roles", "write_roles", "is_live", "end_time_timestamp", "created_timestamp"],
-    ).await
+    ).await?;
+
+    // Caption and slide texts are never needed in search results, so we
+    // exclude them from the returned documents to not transfer huge amounts of data.
+    let displayed_attrs = [
+        "id", "series_id", "series_title", "title", "description", "creators",
+        "thumbnail", "duration", "created", "created_timestamp", "start_time",
+        "end_time", "end_time_timestamp", "is_live", "audio_only", "read_roles",
+        "write_roles", "listed", "host_realms",
+    ];
+    if index.get_displayed_attributes().await? != displayed_attrs {
+        debug!("Updating `displayed_attributes` of event index");
+        index.set_displayed_attributes(displayed_attrs).await?;
+    }
+
+    Ok(())
 }
@@ -29,7 +29,7 @@ mod util;
 
 use self::writer::MeiliWriter;
 pub(crate) use self::{
-    event::Event,
+    event::{Event, TextMatch, TextMatchType},
     meta::IndexState,
     realm::Realm,
     series::Series,
@@ -40,7 +40,7 @@ pub(crate) use self::{
 
 /// The version of search index schema. Increase whenever there is a change that
 /// requires an index rebuild.
-const VERSION: u32 = 4;
+const VERSION: u32 = 6;
 
 
 // ===== Configuration ============================================================================
@@ -23,6 +23,7 @@ pub(crate) async fn update_index_daemon(meili: &Client, db: &mut DbConnection) -
     loop {
         let loop_started_at = Instant::now();
 
+        queue_blocks_with_changed_visibility(db).await?;
         update_index(meili, db).await?;
 
         let next_update_in = meili.config.update_interval.saturating_sub(loop_started_at.elapsed());
@@ -31,6 +32,31 @@ pub(crate) async fn update_index_daemon(meili: &Client, db: &mut DbConnection) -
     }
 }
 
+/// Queues all blocks for reindex whose visibility window opened or closed
+/// since the last call, as that changes whether their events and series are
+/// listed.
+async fn queue_blocks_with_changed_visibility(db: &mut DbConnection) -> Result<()> {
+    let tx = db.transaction().await?;
+
+    // `now()` is the start time of the transaction, so both statements use the
+    // same timestamp.
+    let affected = tx.execute(
+        "select queue_block_for_reindex(blocks) \
+            from blocks, block_visibility_check as c \
+            where visible_from > c.last_check and visible_from <= now() \
+                or visible_until > c.last_check and visible_until <= now()",
+        &[],
+    ).await.context("failed to queue blocks with changed visibility")?;
+    tx.execute("update block_visibility_check set last_check = now()", &[]).await?;
+    tx.commit().await?;
+
+    if affected > 0 {
+        debug!("Queued {affected} blocks with changed visibility for reindex");
+    }
+
+    Ok(())
+}
+
 /// Processes the "search index queue" in the DB by dequeuing some items and
 /// sending them to the search index. Stops once the queue is empty.
 pub(crate) async fn update_index(meili: &Client, db: &mut DbConnection) -> Result<()> {
@@ -0,0 +1,260 @@
+//! Comparing the data in Tobira's DB with what Opencast's harvest API returns.
+
+use std::collections::{HashMap, HashSet};
+
+use chrono::{DateTime, TimeZone, Utc};
+use serde::Serialize;
+
+use crate::{config::Config, db::DbConnection, prelude::*};
+use super::{harvest::{self, HarvestItem}, OcClient};
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum ItemKind {
+    // The order is important: it's the order in which items are repaired.
+    Series,
+    Event,
+    Playlist,
+}
+
+impl ItemKind {
+    const ALL: [Self; 3] = [Self::Series, Self::Event, Self::Playlist];
+
+    /// Query to load Opencast ID and `updated` of all items of the sync source
+    /// `$1`. `updated` is null for items that are still waiting to be synced.
+    fn db_query(self) -> &'static str {
+        match self {
+            Self::Series => "select opencast_id, case when state = 'ready' then updated end \
+                from series where sync_source = $1",
+            Self::Event => "select opencast_id, case when state = 'ready' then updated end \
+                from events where sync_source = $1",
+            Self::Playlist => "select opencast_id, updated from playlists where sync_source = $1",
+        }
+    }
+
+    fn deleted_item(self, opencast_id: String, updated: DateTime<Utc>) -> HarvestItem {
+        match self {
+            Self::Series => HarvestItem::SeriesDeleted { id: opencast_id, updated },
+            Self::Event => HarvestItem::EventDeleted { id: opencast_id, updated },
+            Self::Playlist => HarvestItem::PlaylistDeleted { id: opencast_id, updated },
+        }
+    }
+}
+
+/// An item that differs between Tobira and Opencast.
+#[derive(Debug, Serialize)]
+struct Finding {
+    kind: ItemKind,
+    opencast_id: String,
+    tobira_updated: Option<DateTime<Utc>>,
+    opencast_updated: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct Report {
+    /// Items that exist in Opencast, but not in Tobira.
+    missing: Vec<Finding>,
+    /// Items that exist in both, but with different `updated` timestamps.
+    stale: Vec<Finding>,
+    /// Items that exist in Tobira, but not in Opencast.
+    orphaned: Vec<Finding>,
+    /// Items that were created in Tobira and are still waiting to be synced.
+    /// These are not repaired, as the regular harvest takes care of them.
+    waiting: Vec<Finding>,
+}
+
+impl Report {
+    /// Whether there is anything to repair. Waiting items are not considered.
+    fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.stale.is_empty() && self.orphaned.is_empty()
+    }
+}
+
+
+/// Walks through the whole harvest API of the sync source `source` and
+/// compares all items with our DB. Only writes to the DB if `repair` is set.
+pub(crate) async fn run(
+    client: &OcClient,
+    source: &str,
+    config: &Config,
+    json: bool,
+    repair: bool,
+    mut db: DbConnection,
+) -> Result<()> {
+    // Load all items we have in the DB. Waiting items are kept separately,
+    // together with their `updated` timestamp in Opencast, if they exist there.
+    let mut in_db = HashMap::new();
+    let mut waiting = HashMap::new();
+    for kind in ItemKind::ALL {
+        let rows = db.query(kind.db_query(), &[&source]).await?;
+        for row in rows {
+            let key = (kind, row.get::<_, String>(0));
+            match row.get::<_, Option<DateTime<Utc>>>(1) {
+                Some(updated) => { in_db.insert(key, updated); }
+                None => { waiting.insert(key, None); }
+            }
+        }
+    }
+    info!(
+        "Loaded {} items ({} of which are waiting) from the DB, starting to walk the \
+            harvest API...",
+        in_db.len() + waiting.len(),
+        waiting.len(),
+    );
+
+    // Walk the harvest API from the beginning. We remember all items existing
+    // in Opencast, and the latest version of all items which differ from our
+    // DB, in case we want to repair them.
+    let mut in_opencast = HashSet::new();
+    let mut differing = HashMap::new();
+    let mut since = Utc.timestamp_opt(0, 0).unwrap();
+    let preferred_amount: u64 = config.sync.preferred_harvest_size.into();
+    let max_amount: u64 = config.sync.max_harvest_size.into();
+    let mut amount = preferred_amount;
+    loop {
+        let resp = client.send_harvest(since, amount).await?;
+        if resp.includes_items_until == since {
+            amount = harvest::increased_amount(amount, max_amount).ok_or_else(|| anyhow!(
+                "Harvest API makes no progress at {since:?}, even with {amount} items. \
+                    Try increasing 'sync.max_harvest_size'."
+            ))?;
+            continue;
+        }
+        amount = preferred_amount;
+
+        let last_updated = resp.items.last().map(|item| item.updated());
+        for item in resp.items {
+            let (kind, opencast_id, exists) = match &item {
+                HarvestItem::Event { id, .. } => (ItemKind::Event, id, true),
+                HarvestItem::EventDeleted { id, .. } => (ItemKind::Event, id, false),
+                HarvestItem::Series { id, .. } => (ItemKind::Series, id, true),
+                HarvestItem::SeriesDeleted { id, .. } => (ItemKind::Series, id, false),
+                HarvestItem::Playlist { id, .. } => (ItemKind::Playlist, id, true),
+                HarvestItem::PlaylistDeleted { id, .. } => (ItemKind::Playlist, id, false),
+                HarvestItem::Unknown { .. } => continue,
+            };
+
+            let key = (kind, opencast_id.clone());
+            if let Some(opencast_updated) = waiting.get_mut(&key) {
+                *opencast_updated = exists.then(|| item.updated());
+                continue;
+            }
+
+            differing.remove(&key);
+            if !exists {
+                in_opencast.remove(&key);
+                continue;
+            }
+
+            if in_db.get(&key) != Some(&item.updated()) {
+                differing.insert(key.clone(), item);
+            }
+            in_opencast.insert(key);
+        }
+
+        since = resp.includes_items_until;
+
+        // If Opencast returned items newer than `includesItemsUntil`, it
+        // capped the response due to its time buffer. We already received
+        // the newest items then, so we can stop.
+        let capped = last_updated.map_or(false, |last| last > resp.includes_items_until);
+        if !resp.has_more || capped {
+            break;
+        }
+    }
+    info!("Finished walking the harvest API ({} items exist in Opencast)", in_opencast.len());
+
+    // Assemble report.
+    let mut report = Report::default();
+    for (key, item) in &differing {
+        let finding = Finding {
+            kind: key.0,
+            opencast_id: key.1.clone(),
+            tobira_updated: in_db.get(key).copied(),
+            opencast_updated: Some(item.updated()),
+        };
+        match finding.tobira_updated {
+            None => report.missing.push(finding),
+            Some(_) => report.stale.push(finding),
+        }
+    }
+    for (key, updated) in &in_db {
+        if !in_opencast.contains(key) {
+            report.orphaned.push(Finding {
+                kind: key.0,
+                opencast_id: key.1.clone(),
+                tobira_updated: Some(*updated),
+                opencast_updated: None,
+            });
+        }
+    }
+    for ((kind, opencast_id), opencast_updated) in waiting {
+        report.waiting.push(Finding {
+            kind,
+            opencast_id,
+            tobira_updated: None,
+            opencast_updated,
+        });
+    }
+    let lists = [&mut report.missing, &mut report.stale, &mut report.orphaned, &mut report.waiting];
+    for list in lists {
+        list.sort_by(|a, b| (a.kind, &a.opencast_id).cmp(&(b.kind, &b.opencast_id)));
+    }
+
+    print_report(&report, json)?;
+
+    // Deletions are stored with the timestamp up to which the harvest API
+    // included all items, as the items were deleted at some point before that.
+    let harvested_until = since;
+    if repair && !report.is_empty() {
+        let mut items = differing.into_iter().collect::<Vec<_>>();
+        items.sort_by_key(|(key, _)| key.0);
+        let mut items = items.into_iter().map(|(_, item)| item).collect::<Vec<_>>();
+        items.extend(report.orphaned.into_iter().map(|f| {
+            f.kind.deleted_item(f.opencast_id, harvested_until)
+        }));
+
+        let num_items = items.len();
+        let mut transaction = db.transaction().await?;
+        harvest::store_in_db(items, None, source, &mut transaction).await?;
+        transaction.commit().await?;
+        info!("Repaired {num_items} items");
+    }
+
+    Ok(())
+}
+
+fn print_report(report: &Report, json: bool) -> Result<()> {
+    if json {
+        println!("{}", serde_json::to_string_pretty(report)?);
+        return Ok(());
+    }
+
+    if report.is_empty() && report.waiting.is_empty() {
+        println!("Tobira and Opencast are in sync: no differences found.");
+        return Ok(());
+    }
+
+    let sections = [
+        ("Missing in Tobira", &report.missing),
+        ("Stale in Tobira", &report.stale),
+        ("Orphaned in Tobira (not in Opencast)", &report.orphaned),
+        ("Waiting in Tobira (not synced yet, not repaired)", &report.waiting),
+    ];
+    for (title, findings) in sections {
+        println!("{title}: {}", findings.len());
+        for f in findings {
+            let fmt_ts = |ts: Option<DateTime<Utc>>| ts.map_or("-".into(), |ts| ts.to_rfc3339());
+            println!(
+                "    {:?} {} (Tobira: {}, Opencast: {})",
+                f.kind,
+                f.opencast_id,
+                fmt_ts(f.tobira_updated),
+                fmt_ts(f.opencast_updated),
+            );
+        }
+    }
+
+    Ok(())
+}
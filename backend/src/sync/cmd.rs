@@ -1,9 +1,13 @@
-use std::time::Instant;
+use std::{path::PathBuf, time::Instant};
 
 use crate::{
     config::Config,
     prelude::*, db::DbConnection,
 };
+use super::{
+    OcClient,
+    harvest::{self, HarvestSource, Recorder, RecordedPages},
+};
 
 
 #[derive(Debug, clap::Args)]
@@ -23,6 +27,69 @@ pub(crate) enum SyncCommand {
         daemon: bool,
     },
 
+    /// Like `run` (without `--daemon`), but additionally writes all raw
+    /// harvest responses into the given directory. These can later be
+    /// replayed with `replay`.
+    Record {
+        /// Directory to write the responses into. Created if it does not
+        /// exist, but must not contain any JSON files.
+        dir: PathBuf,
+
+        /// Name of the sync source (see `sync.additional_sources`).
+        #[clap(long, default_value = super::DEFAULT_SOURCE)]
+        source: String,
+    },
+
+    /// Harvests from responses previously recorded with `record` instead of
+    /// talking to Opencast. To get the same result as during recording, the
+    /// sync status has to be the same as when the recording started (e.g.
+    /// run `reset` first if the recording started from scratch).
+    Replay {
+        /// Directory containing the recorded responses.
+        dir: PathBuf,
+
+        /// Name of the sync source (see `sync.additional_sources`).
+        #[clap(long, default_value = super::DEFAULT_SOURCE)]
+        source: String,
+    },
+
+    /// Fetches the given events and series from Opencast and updates them in
+    /// Tobira's database. Useful to fix individual items without resyncing
+    /// everything via `reset`.
+    Resync {
+        /// Opencast ID of an event to resync. Can be specified multiple times.
+        #[clap(long = "event")]
+        events: Vec<String>,
+
+        /// Opencast ID of a series to resync. Can be specified multiple times.
+        #[clap(long = "series")]
+        series: Vec<String>,
+
+        /// Name of the sync source (see `sync.additional_sources`).
+        #[clap(long, default_value = super::DEFAULT_SOURCE)]
+        source: String,
+    },
+
+    /// Walks through all data of the harvest API (from the beginning) and
+    /// compares it with Tobira's database, reporting items missing in Tobira,
+    /// stale items (different `updated` timestamp) and orphaned items (that
+    /// don't exist in Opencast). Does not write anything unless `--repair` is
+    /// specified.
+    Audit {
+        /// Output the report as JSON.
+        #[clap(long)]
+        json: bool,
+
+        /// Fixes all found differences by upserting missing & stale items and
+        /// removing orphaned ones.
+        #[clap(long)]
+        repair: bool,
+
+        /// Name of the sync source (see `sync.additional_sources`).
+        #[clap(long, default_value = super::DEFAULT_SOURCE)]
+        source: String,
+    },
+
     /// Resets the "harvested until" timestamp, causing all data to be
     /// re-synchronized when the sync process is next started. Does *not*
     /// delete any data from the DB.
@@ -30,6 +97,10 @@ pub(crate) enum SyncCommand {
         /// If specified, skips the "Are you sure?" question.
         #[clap(long)]
         yes_absolutely_reset: bool,
+
+        /// Only resets the given sync source instead of all of them.
+        #[clap(long)]
+        source: Option<String>,
     },
 }
 
@@ -47,18 +118,64 @@ pub(crate) async fn run(args: &Args, config: &Config) -> Result<()> {
     let db = crate::connect_and_migrate_db(config).await?;
     let conn = db.get().await?;
 
-    match args.cmd {
+    match &args.cmd {
         SyncCommand::Run { daemon } => {
             let before = Instant::now();
-            super::run(daemon, conn, config).await?;
+            super::run(*daemon, &db, config).await?;
             info!("Finished harvest in {:.2?}", before.elapsed());
             Ok(())
         }
-        SyncCommand::Reset { yes_absolutely_reset: yes } => reset(conn, yes).await,
+        SyncCommand::Record { dir, source: source_name } => {
+            let client = OcClient::for_source(&config.sync.source(source_name, config)?)?;
+            super::check_compatibility(&client).await?;
+            let recorder = Recorder::new(dir)?;
+
+            let before = Instant::now();
+            let source = HarvestSource::Opencast { client, recorder: Some(recorder) };
+            harvest::run(false, config, source_name, source, conn).await?;
+            info!(
+                "Finished harvest in {:.2?}, responses written to '{}'",
+                before.elapsed(),
+                dir.display(),
+            );
+            Ok(())
+        }
+        SyncCommand::Replay { dir, source: source_name } => {
+            // Only to make sure the source exists.
+            config.sync.source(source_name, config)?;
+
+            let source = HarvestSource::Recorded(RecordedPages::load(dir)?);
+            let before = Instant::now();
+            harvest::run(false, config, source_name, source, conn).await?;
+            info!("Finished replaying harvest in {:.2?}", before.elapsed());
+            Ok(())
+        }
+        SyncCommand::Resync { events, series, source } => {
+            if events.is_empty() && series.is_empty() {
+                bail!("no items to resync specified: use `--event` and/or `--series`");
+            }
+
+            let client = OcClient::for_source(&config.sync.source(source, config)?)?;
+            super::check_resync_support(&client).await?;
+            harvest::resync(&client, source, events, series, conn).await?;
+            info!("Resynced {} event(s) and {} series", events.len(), series.len());
+            Ok(())
+        }
+        SyncCommand::Audit { json, repair, source } => {
+            let client = OcClient::for_source(&config.sync.source(source, config)?)?;
+            super::check_compatibility(&client).await?;
+            super::audit::run(&client, source, config, *json, *repair, conn).await
+        }
+        SyncCommand::Reset { yes_absolutely_reset: yes, source } => {
+            if let Some(source) = source {
+                config.sync.source(source, config)?;
+            }
+            reset(conn, source.as_deref(), *yes).await
+        }
     }
 }
 
-async fn reset(db: DbConnection, yes: bool) -> Result<()> {
+async fn reset(db: DbConnection, source: Option<&str>, yes: bool) -> Result<()> {
     if !yes {
         bunt::println!(
             "\n{$bold+red+intense}Are you sure you want to reset the sync status?{/$}\n\
@@ -71,10 +188,15 @@ async fn reset(db: DbConnection, yes: bool) -> Result<()> {
     }
 
     db.execute(
-        "update sync_status set harvested_until = (timestamp '1970-01-01 00:00:00')",
-        &dbargs![],
+        "update sync_status set harvested_until = (timestamp '1970-01-01 00:00:00') \
+            where $1::text is null or source = $1",
+        &[&source],
     ).await?;
-    info!("Sync status was reset -> all Opencast items will be resynced");
+    match source {
+        Some(source) => info!("Sync status of source '{source}' was reset \
+            -> all its Opencast items will be resynced"),
+        None => info!("Sync status was reset -> all Opencast items will be resynced"),
+    }
 
     Ok(())
 }
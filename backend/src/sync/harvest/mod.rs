@@ -3,58 +3,100 @@ use std::{
     time::{Duration, Instant},
 };
 
+use chrono::{DateTime, Utc};
 use tokio_postgres::types::ToSql;
 
 use crate::{
     auth::ROLE_ADMIN,
     config::Config,
-    db::{types::{EventTrack, EventState, SeriesState, EventCaption}, DbConnection},
+    db::{
+        types::{
+            EventTrack, EventState, SeriesState, EventCaption, EventSegment, EventChapter,
+            PlaylistEntry,
+        },
+        DbConnection,
+    },
     prelude::*,
 };
-use super::{status::SyncStatus, OcClient};
+use super::{
+    status::{HarvestOutcome, HarvestRun, SyncStatus},
+    OcClient,
+};
 
-pub(crate) use self::response::{HarvestItem, HarvestResponse};
+pub(crate) use self::{
+    response::{HarvestItem, HarvestResponse},
+    source::{HarvestSource, Recorder, RecordedPages},
+};
 
 
 mod response;
+mod source;
 
 
 // TODO: make (some of) this stuff configurable.
 
-const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
-const MAX_BACKOFF: Duration = Duration::from_secs(5 * 60);
+pub(super) const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+pub(super) const MAX_BACKOFF: Duration = Duration::from_secs(5 * 60);
+
+/// How often to check for sync notifications while waiting for the next
+/// harvest. Only relevant if `sync.notify_key` is set.
+const NOTIFY_CHECK_PERIOD: Duration = Duration::from_secs(1);
 
 
-/// Continuiously fetches from the harvesting API and writes new data into our
-/// database.
+/// Continuiously fetches from the given source (usually the harvesting API)
+/// and writes new data into our database. All items are stored as belonging
+/// to the sync source `source_name`.
 pub(crate) async fn run(
     daemon: bool,
     config: &Config,
-    client: &OcClient,
+    source_name: &str,
+    mut source: HarvestSource,
     mut db: DbConnection,
 ) -> Result<()> {
     // Some duration to wait before the next attempt. Is only set to non-zero in
     // case of an error.
     let mut backoff = INITIAL_BACKOFF;
 
-    let preferred_amount = config.sync.preferred_harvest_size.into();
+    let preferred_amount: u64 = config.sync.preferred_harvest_size.into();
+    let max_amount: u64 = config.sync.max_harvest_size.into();
+
+    // The number of items to request. Usually `preferred_amount`, but
+    // temporarily increased if a harvest request does not make progress.
+    let mut amount = preferred_amount;
 
     if daemon {
-        info!("Starting harvesting daemon");
+        info!("Starting harvesting daemon for source '{source_name}'");
     } else {
-        info!("Starting to harvest all data that's available now");
+        info!("Starting to harvest all data of source '{source_name}' that's available now");
     }
 
+    SyncStatus::init(source_name, &**db).await
+        .context("failed to initialize sync status")?;
+
     loop {
-        let sync_status = SyncStatus::fetch(&**db).await
+        let sync_status = SyncStatus::fetch(source_name, &**db).await
             .context("failed to fetch sync status from DB")?;
 
         // Send request to API and deserialize data.
-        let resp = client.send_harvest(sync_status.harvested_until, preferred_amount).await;
+        let started = Utc::now();
+        let before = Instant::now();
+        let resp = source.fetch(sync_status.harvested_until, amount).await;
         let harvest_data = match resp {
             Ok(v) => v,
+            Err(e) if !source.retry_on_error() => return Err(e),
             Err(e) => {
-                error!("Harvest request failed: {:?}", e);
+                error!("Harvest request for source '{source_name}' failed: {:?}", e);
+                let run = HarvestRun {
+                    source: source_name.to_owned(),
+                    started,
+                    duration: before.elapsed(),
+                    since: sync_status.harvested_until,
+                    outcome: HarvestOutcome::Failure { error: format!("{e:#}"), backoff },
+                };
+                // Not being able to store the run should not stop the harvest.
+                if let Err(e) = run.store(&**db).await {
+                    error!("Failed to store failed harvest run: {:?}", e);
+                }
 
                 // We increase the backoff duration exponentially until we hit the
                 // defined maximum.
@@ -71,12 +113,30 @@ pub(crate) async fn run(
 
 
         if harvest_data.includes_items_until == sync_status.harvested_until {
-            bail!("Opencast's harvest response has 'includesItemsUntil' == 'since'. This means \
-                harvesting would not make any progress! This problem occurs when the number of \
-                events or series with exactly the same modification date is larger than the \
-                configured 'preferredHarvestSize'. Increasing 'preferredHarvestSize' might fix \
-                this problem. However, be aware of the potential problems with a large harvest \
-                size.");
+            // This happens when the number of items with exactly the same
+            // modification date is larger than the number of items we
+            // requested. We simply request more items until we make progress.
+            let Some(new_amount) = increased_amount(amount, max_amount) else {
+                bail!("Opencast's harvest response has 'includesItemsUntil' == 'since', even \
+                    when requesting {amount} items. This means harvesting would not make any \
+                    progress! This problem occurs when the number of events or series with \
+                    exactly the same modification date is larger than the configured \
+                    'max_harvest_size'. Increasing 'max_harvest_size' might fix this problem. \
+                    However, be aware of the potential problems with a large harvest size.");
+            };
+
+            warn!(
+                "Harvest request made no progress (more than {amount} items with the same \
+                    modification date {:?}). Temporarily requesting {new_amount} items.",
+                sync_status.harvested_until,
+            );
+            amount = new_amount;
+            continue;
+        }
+
+        if amount != preferred_amount {
+            info!("Harvest made progress again: requesting {preferred_amount} items again");
+            amount = preferred_amount;
         }
 
 
@@ -84,8 +144,29 @@ pub(crate) async fn run(
         // everything worked out alright.
         let last_updated = harvest_data.items.last().map(|item| item.updated());
         let mut transaction = db.transaction().await?;
-        store_in_db(harvest_data.items, &sync_status, &mut transaction).await?;
-        SyncStatus::update_harvested_until(harvest_data.includes_items_until, &*transaction).await?;
+        let stats = store_in_db(
+            harvest_data.items,
+            Some(sync_status.harvested_until),
+            source_name,
+            &mut transaction,
+        ).await?;
+        clear_expired_deletions(config, source_name, &transaction).await?;
+        SyncStatus::update_harvested_until(
+            harvest_data.includes_items_until,
+            source_name,
+            &*transaction,
+        ).await?;
+        let run = HarvestRun {
+            source: source_name.to_owned(),
+            started,
+            duration: before.elapsed(),
+            since: sync_status.harvested_until,
+            outcome: HarvestOutcome::Success {
+                includes_items_until: harvest_data.includes_items_until,
+                stats,
+            },
+        };
+        run.store(&*transaction).await.context("failed to store harvest run")?;
         transaction.commit().await?;
 
 
@@ -115,7 +196,8 @@ pub(crate) async fn run(
                     config.sync.poll_period,
                 );
 
-                tokio::time::sleep(config.sync.poll_period).await;
+                wait_for_next_harvest(config, source_name, sync_status.wakeup_requested, &db)
+                    .await?;
             } else {
                 info!("Harvested all available data: exiting now.");
                 return Ok(());
@@ -124,22 +206,135 @@ pub(crate) async fn run(
     }
 }
 
-async fn store_in_db(
+/// Returns the number of items to request after a harvest request for `amount`
+/// items made no progress, or `None` if `max_amount` was already reached.
+/// `amount` has to be greater than 0.
+pub(super) fn increased_amount(amount: u64, max_amount: u64) -> Option<u64> {
+    (amount < max_amount).then(|| min(max_amount, amount.saturating_mul(2)))
+}
+
+/// Clears the pending deletion mark of all events of `source` whose deletion
+/// was requested more than `sync.deletion_timeout` ago. Opencast still not
+/// having reported them as deleted most likely means that the deletion failed,
+/// so these events are shown again instead of being hidden forever.
+async fn clear_expired_deletions(
+    config: &Config,
+    source: &str,
+    db: &deadpool_postgres::Transaction<'_>,
+) -> Result<()> {
+    let timeout = config.sync.deletion_timeout;
+    let Some(cutoff) = chrono::Duration::from_std(timeout)
+        .ok()
+        .and_then(|timeout| Utc::now().checked_sub_signed(timeout))
+    else {
+        return Ok(());
+    };
+
+    let query = "update events set deletion_pending_since = null \
+        where sync_source = $1 and deletion_pending_since < $2 \
+        returning opencast_id";
+    for row in db.query(query, &[&source, &cutoff]).await? {
+        let opencast_id: String = row.get(0);
+        warn!(
+            "Deletion of event '{opencast_id}' was requested more than {timeout:?} ago, \
+                but Opencast has not reported it as deleted. Showing it again.",
+        );
+    }
+
+    Ok(())
+}
+
+/// Waits `sync.poll_period` before the next harvest. If sync notifications are
+/// enabled, returns early as soon as `wakeup_requested` differs from
+/// `last_wakeup`, the value read before the last harvest request. That way,
+/// notifications arriving during a harvest request are not lost.
+async fn wait_for_next_harvest(
+    config: &Config,
+    source_name: &str,
+    last_wakeup: Option<DateTime<Utc>>,
+    db: &DbConnection,
+) -> Result<()> {
+    if config.sync.notify_key.is_none() {
+        tokio::time::sleep(config.sync.poll_period).await;
+        return Ok(());
+    }
+
+    let deadline = Instant::now() + config.sync.poll_period;
+    while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+        tokio::time::sleep(min(remaining, NOTIFY_CHECK_PERIOD)).await;
+        let sync_status = SyncStatus::fetch(source_name, &***db).await
+            .context("failed to fetch sync status from DB")?;
+        if sync_status.wakeup_requested != last_wakeup {
+            debug!("Received sync notification: starting next harvest immediately");
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Fetches the given events and series from Opencast and upserts them, just
+/// like the harvest would. This is independent of the sync status and does
+/// not change it.
+pub(crate) async fn resync(
+    client: &OcClient,
+    source_name: &str,
+    events: &[String],
+    series: &[String],
+    mut db: DbConnection,
+) -> Result<()> {
+    // Series are stored first so that the foreign keys of resynced events
+    // referencing them are set correctly.
+    let mut items = Vec::new();
+    for opencast_id in series {
+        items.push(client.get_series(opencast_id).await?);
+    }
+    for opencast_id in events {
+        items.push(client.get_event(opencast_id).await?);
+    }
+
+    let mut transaction = db.transaction().await?;
+    store_in_db(items, None, source_name, &mut transaction).await?;
+    transaction.commit().await?;
+
+    Ok(())
+}
+
+/// Number of items changed by `store_in_db`.
+#[derive(Debug, Default)]
+pub(super) struct HarvestStats {
+    pub(super) upserted_events: i32,
+    pub(super) removed_events: i32,
+    pub(super) upserted_series: i32,
+    pub(super) removed_series: i32,
+    pub(super) upserted_playlists: i32,
+    pub(super) removed_playlists: i32,
+}
+
+impl HarvestStats {
+    fn num_changes(&self) -> i32 {
+        self.upserted_events + self.upserted_series + self.upserted_playlists
+            + self.removed_events + self.removed_series + self.removed_playlists
+    }
+}
+
+/// Writes the given items into the DB as belonging to the sync source
+/// `source`. Items that were updated before `skip_before` (usually
+/// `harvested_until`) are ignored.
+pub(super) async fn store_in_db(
     items: Vec<HarvestItem>,
-    sync_status: &SyncStatus,
+    skip_before: Option<DateTime<Utc>>,
+    source: &str,
     db: &mut deadpool_postgres::Transaction<'_>,
-) -> Result<()> {
+) -> Result<HarvestStats> {
     let before = Instant::now();
-    let mut upserted_events = 0;
-    let mut removed_events = 0;
-    let mut upserted_series = 0;
-    let mut removed_series = 0;
+    let mut stats = HarvestStats::default();
 
     for item in items {
         // Make sure we haven't received this update yet. The code below can
         // handle duplicate items alright, but this way we can save on some DB
         // accesses and the logged statistics are more correct.
-        if item.updated() < sync_status.harvested_until {
+        if skip_before.map_or(false, |skip_before| item.updated() < skip_before) {
             debug!("Skipping item which `updated` value is earlier than `harvested_until`");
             continue;
         }
@@ -152,6 +347,8 @@ async fn store_in_db(
                 part_of,
                 tracks,
                 captions,
+                segments,
+                chapters,
                 created,
                 start_time,
                 end_time,
@@ -166,7 +363,9 @@ async fn store_in_db(
                 let series_id = match &part_of {
                     None => None,
                     Some(part_of) => {
-                        db.query_opt("select id from series where opencast_id = $1", &[part_of])
+                        let query = "select id from series \
+                            where sync_source = $1 and opencast_id = $2";
+                        db.query_opt(query, &[&source, part_of])
                             .await?
                             .map(|row| row.get::<_, i64>(0))
                     },
@@ -183,9 +382,18 @@ async fn store_in_db(
 
                 let tracks = tracks.into_iter().map(Into::into).collect::<Vec<EventTrack>>();
                 let captions = captions.into_iter().map(Into::into).collect::<Vec<EventCaption>>();
+                let mut segments = segments.into_iter()
+                    .map(Into::into)
+                    .collect::<Vec<EventSegment>>();
+                segments.sort_by_key(|segment| segment.start_time);
+                let mut chapters = chapters.into_iter()
+                    .map(Into::into)
+                    .collect::<Vec<EventChapter>>();
+                chapters.sort_by_key(|chapter| chapter.start_time);
 
                 // We upsert the event data.
-                upsert(db, "events", "opencast_id", &[
+                upsert(db, "events", &[
+                    ("sync_source", &source),
                     ("opencast_id", &opencast_id),
                     ("state", &EventState::Ready),
                     ("series", &series_id),
@@ -206,18 +414,23 @@ async fn store_in_db(
                     ("custom_action_roles", &acl.custom_actions),
                     ("tracks", &tracks),
                     ("captions", &captions),
+                    ("segments", &segments),
+                    ("chapters", &chapters),
                 ]).await?;
 
                 trace!("Inserted or updated event {} ({})", opencast_id, title);
-                upserted_events += 1;
+                stats.upserted_events += 1;
             }
 
             HarvestItem::EventDeleted { id: opencast_id, .. } => {
                 let rows_affected = db
-                    .execute("delete from events where opencast_id = $1", &[&opencast_id])
+                    .execute(
+                        "delete from events where sync_source = $1 and opencast_id = $2",
+                        &[&source, &opencast_id],
+                    )
                     .await?;
                 check_affected_rows_removed(rows_affected, "event", &opencast_id);
-                removed_events += 1;
+                stats.removed_events += 1;
             }
 
             HarvestItem::Series {
@@ -230,13 +443,15 @@ async fn store_in_db(
                 metadata
             } => {
                 // We first simply upsert the series.
-                let new_id = upsert(db, "series", "opencast_id", &[
+                let new_id = upsert(db, "series", &[
+                    ("sync_source", &source),
                     ("opencast_id", &opencast_id),
                     ("state", &SeriesState::Ready),
                     ("title", &title),
                     ("description", &description),
                     ("read_roles", &acl.read),
                     ("write_roles", &acl.write),
+                    ("custom_action_roles", &acl.custom_actions),
                     ("updated", &updated),
                     ("created", &created),
                     ("metadata", &metadata),
@@ -245,8 +460,9 @@ async fn store_in_db(
                 // But now we have to fix the foreign key for any events that
                 // previously referenced this series (via the Opencast UUID)
                 // but did not have the correct foreign key yet.
-                let query = "update events set series = $1 where part_of = $2 and series <> $1";
-                let updated_events = db.execute(query, &[&new_id, &opencast_id]).await?;
+                let query = "update events set series = $1 \
+                    where sync_source = $2 and part_of = $3 and series <> $1";
+                let updated_events = db.execute(query, &[&new_id, &source, &opencast_id]).await?;
 
                 trace!("Inserted or updated series {} ({})", opencast_id, title);
                 if updated_events != 0 {
@@ -257,7 +473,7 @@ async fn store_in_db(
                         title,
                     );
                 }
-                upserted_series += 1;
+                stats.upserted_series += 1;
             },
 
             HarvestItem::SeriesDeleted { id: opencast_id, .. } => {
@@ -266,10 +482,64 @@ async fn store_in_db(
                 // what we want: treat it as if the event has no series
                 // attached to it. Also see the comment on the migration.
                 let rows_affected = db
-                    .execute("delete from series where opencast_id = $1", &[&opencast_id])
+                    .execute(
+                        "delete from series where sync_source = $1 and opencast_id = $2",
+                        &[&source, &opencast_id],
+                    )
                     .await?;
                 check_affected_rows_removed(rows_affected, "series", &opencast_id);
-                removed_series += 1;
+                stats.removed_series += 1;
+            }
+
+            HarvestItem::Playlist {
+                id: opencast_id,
+                title,
+                description,
+                creator,
+                entries,
+                mut acl,
+                updated,
+            } => {
+                acl.read.retain(|role| role != ROLE_ADMIN);
+                acl.write.retain(|role| role != ROLE_ADMIN);
+
+                let num_entries = entries.len();
+                let entries = entries.into_iter()
+                    .filter_map(|entry| entry.into_db())
+                    .collect::<Vec<PlaylistEntry>>();
+                if entries.len() != num_entries {
+                    warn!(
+                        "Skipped {} entries of unknown type in playlist {}",
+                        num_entries - entries.len(),
+                        opencast_id,
+                    );
+                }
+
+                upsert(db, "playlists", &[
+                    ("sync_source", &source),
+                    ("opencast_id", &opencast_id),
+                    ("title", &title),
+                    ("description", &description),
+                    ("creator", &creator),
+                    ("entries", &entries),
+                    ("read_roles", &acl.read),
+                    ("write_roles", &acl.write),
+                    ("updated", &updated),
+                ]).await?;
+
+                trace!("Inserted or updated playlist {} ({})", opencast_id, title);
+                stats.upserted_playlists += 1;
+            }
+
+            HarvestItem::PlaylistDeleted { id: opencast_id, .. } => {
+                let rows_affected = db
+                    .execute(
+                        "delete from playlists where sync_source = $1 and opencast_id = $2",
+                        &[&source, &opencast_id],
+                    )
+                    .await?;
+                check_affected_rows_removed(rows_affected, "playlist", &opencast_id);
+                stats.removed_playlists += 1;
             }
 
             HarvestItem::Unknown { kind, .. } => {
@@ -279,21 +549,23 @@ async fn store_in_db(
         }
     }
 
-    if upserted_events == 0 && upserted_series == 0 && removed_events == 0 && removed_series == 0 {
+    if stats.num_changes() == 0 {
         trace!("Harvest outcome: nothing changed!");
     } else {
         info!(
-            "Harvest outcome: upserted {} events, upserted {} series, \
-                removed {} events, removed {} series (in {:.2?})",
-            upserted_events,
-            upserted_series,
-            removed_events,
-            removed_series,
+            "Harvest outcome: upserted {} events, upserted {} series, upserted {} playlists, \
+                removed {} events, removed {} series, removed {} playlists (in {:.2?})",
+            stats.upserted_events,
+            stats.upserted_series,
+            stats.upserted_playlists,
+            stats.removed_events,
+            stats.removed_series,
+            stats.removed_playlists,
             before.elapsed(),
         );
     }
 
-    Ok(())
+    Ok(stats)
 }
 
 fn check_affected_rows_removed(rows_affected: u64, entity: &str, opencast_id: &str) {
@@ -306,15 +578,17 @@ fn check_affected_rows_removed(rows_affected: u64, entity: &str, opencast_id: &s
     }
 }
 
-/// Inserts a new row or updates an existing one if the value in `unique_col`
-/// already exists. Returns the value of the `id` column, which is assumed to
-/// be `i64`.
+/// Inserts a new row or updates an existing one if the combination of
+/// `sync_source` and `opencast_id` already exists. Both columns have to be
+/// contained in `cols`. Returns the value of the `id` column, which is
+/// assumed to be `i64`.
 async fn upsert(
     db: &deadpool_postgres::Transaction<'_>,
     table_name: &str,
-    unique_col: &str,
     cols: &[(&str, &(dyn ToSql + Sync))],
 ) -> Result<i64> {
+    const UNIQUE_COLS: &str = "sync_source, opencast_id";
+
     let mut query_col_names = String::new();
     let mut query_col_values = String::new();
     let mut query_update = String::new();
@@ -330,10 +604,10 @@ async fn upsert(
         }
         query_col_values += &format!("${}", i + 1);
 
-        if !query_update.is_empty() {
-            query_update += ", ";
-        }
-        if name != unique_col {
+        if name != "sync_source" && name != "opencast_id" {
+            if !query_update.is_empty() {
+                query_update += ", ";
+            }
             query_update += &format!("{0} = excluded.{0}", name);
         }
 
@@ -345,7 +619,7 @@ async fn upsert(
         table_name,
         query_col_names,
         query_col_values,
-        unique_col,
+        UNIQUE_COLS,
         query_update,
     );
 
@@ -1,7 +1,10 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-use crate::db::types::{CustomActions, EventCaption, EventTrack, ExtraMetadata};
+use crate::db::types::{
+    CustomActions, EventCaption, EventChapter, EventSegment, EventTrack, ExtraMetadata,
+    PlaylistEntry as DbPlaylistEntry, PlaylistEntryType as DbPlaylistEntryType,
+};
 
 
 /// What the harvesting API returns.
@@ -31,6 +34,10 @@ pub(crate) enum HarvestItem {
         tracks: Vec<Track>,
         #[serde(default)] // For backwards compatibility
         captions: Vec<Caption>,
+        #[serde(default)] // For backwards compatibility
+        segments: Vec<Segment>,
+        #[serde(default)] // For backwards compatibility
+        chapters: Vec<Chapter>,
         thumbnail: Option<String>,
         acl: Acl,
         is_live: bool,
@@ -70,6 +77,25 @@ pub(crate) enum HarvestItem {
         updated: DateTime<Utc>,
     },
 
+    #[serde(rename_all = "camelCase")]
+    Playlist {
+        id: String,
+        title: String,
+        description: Option<String>,
+        creator: Option<String>,
+        entries: Vec<PlaylistEntry>,
+        acl: Acl,
+        #[serde(with = "chrono::serde::ts_milliseconds")]
+        updated: DateTime<Utc>,
+    },
+
+    #[serde(rename_all = "camelCase")]
+    PlaylistDeleted {
+        id: String,
+        #[serde(with = "chrono::serde::ts_milliseconds")]
+        updated: DateTime<Utc>,
+    },
+
     #[serde(untagged)]
     Unknown {
         kind: String,
@@ -85,6 +111,8 @@ impl HarvestItem {
             Self::EventDeleted { updated, .. } =>  updated,
             Self::Series { updated, .. } => updated,
             Self::SeriesDeleted { updated, .. } => updated,
+            Self::Playlist { updated, .. } => updated,
+            Self::PlaylistDeleted { updated, .. } => updated,
             Self::Unknown { updated, .. } => updated,
         }
     }
@@ -128,6 +156,80 @@ impl Into<EventCaption> for Caption {
     }
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Segment {
+    start_time: i64,
+    duration: i64,
+    thumbnail: Option<String>,
+    text: Option<String>,
+}
+
+impl Into<EventSegment> for Segment {
+    fn into(self) -> EventSegment {
+        EventSegment {
+            start_time: self.start_time,
+            duration: self.duration,
+            thumbnail: self.thumbnail,
+            // Empty texts are as good as no text.
+            text: self.text.filter(|t| !t.trim().is_empty()),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Chapter {
+    start_time: i64,
+    title: String,
+}
+
+impl Into<EventChapter> for Chapter {
+    fn into(self) -> EventChapter {
+        EventChapter {
+            start_time: self.start_time,
+            title: self.title,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PlaylistEntry {
+    id: i64,
+    #[serde(rename = "type")]
+    ty: PlaylistEntryType,
+    content_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) enum PlaylistEntryType {
+    #[serde(rename = "event", alias = "E")]
+    Event,
+
+    /// Entry types we do not know (yet). Such entries are skipped instead of
+    /// failing the whole harvest page.
+    #[serde(other)]
+    Unknown,
+}
+
+impl PlaylistEntry {
+    /// Converts this into the DB type. Returns `None` for entries of unknown
+    /// type.
+    pub(crate) fn into_db(self) -> Option<DbPlaylistEntry> {
+        let ty = match self.ty {
+            PlaylistEntryType::Event => DbPlaylistEntryType::Event,
+            PlaylistEntryType::Unknown => return None,
+        };
+
+        Some(DbPlaylistEntry {
+            entry_id: self.id,
+            ty,
+            content_id: self.content_id,
+        })
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub(crate) struct Acl {
     #[serde(default)]
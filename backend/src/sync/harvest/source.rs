@@ -0,0 +1,153 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Utc};
+
+use crate::{prelude::*, sync::OcClient};
+use super::HarvestResponse;
+
+
+/// Where harvest responses come from.
+pub(crate) enum HarvestSource {
+    /// The harvest API of the configured Opencast instance. If a recorder is
+    /// given, all raw responses are additionally written to disk.
+    Opencast {
+        client: OcClient,
+        recorder: Option<Recorder>,
+    },
+
+    /// Previously recorded responses, read from a directory.
+    Recorded(RecordedPages),
+}
+
+impl HarvestSource {
+    /// Fetches the next harvest response.
+    pub(crate) async fn fetch(
+        &mut self,
+        since: DateTime<Utc>,
+        preferred_amount: u64,
+    ) -> Result<HarvestResponse> {
+        match self {
+            Self::Opencast { client, recorder: None } => {
+                client.send_harvest(since, preferred_amount).await
+            }
+            Self::Opencast { client, recorder: Some(recorder) } => {
+                let (response, body) = client.send_harvest_raw(since, preferred_amount).await?;
+                recorder.save(since, &body)?;
+                Ok(response)
+            }
+            Self::Recorded(pages) => pages.next(since),
+        }
+    }
+
+    /// Whether failed fetches are worth retrying. Only true for network
+    /// sources, as reading the same broken file again won't help.
+    pub(crate) fn retry_on_error(&self) -> bool {
+        matches!(self, Self::Opencast { .. })
+    }
+}
+
+
+/// Writes raw harvest responses into a directory, one file per response.
+///
+/// Files are named `<index>-<since>.json` where `index` is a zero padded
+/// running number (making the alphabetical order the harvest order) and
+/// `since` is the timestamp (in ms) the page was requested with.
+pub(crate) struct Recorder {
+    dir: PathBuf,
+    next_index: u32,
+}
+
+impl Recorder {
+    /// Creates a recorder writing into `dir`, which is created if it does not
+    /// exist yet. Refuses to record into a directory that already contains
+    /// recorded pages, as mixing two recordings would make them useless.
+    pub(crate) fn new(dir: &Path) -> Result<Self> {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("failed to create directory '{}'", dir.display()))?;
+        if !json_files_in(dir)?.is_empty() {
+            bail!("directory '{}' already contains JSON files, refusing to record", dir.display());
+        }
+
+        Ok(Self {
+            dir: dir.to_owned(),
+            next_index: 0,
+        })
+    }
+
+    fn save(&mut self, since: DateTime<Utc>, body: &[u8]) -> Result<()> {
+        let filename = format!("{:06}-{}.json", self.next_index, since.timestamp_millis());
+        let path = self.dir.join(filename);
+        fs::write(&path, body)
+            .with_context(|| format!("failed to write harvest response to '{}'", path.display()))?;
+        trace!("Recorded harvest response to '{}'", path.display());
+        self.next_index += 1;
+
+        Ok(())
+    }
+}
+
+
+/// Harvest responses previously written by `Recorder`, which are returned in
+/// order, regardless of the requested `since` timestamp.
+pub(crate) struct RecordedPages {
+    pages: std::vec::IntoIter<PathBuf>,
+}
+
+impl RecordedPages {
+    pub(crate) fn load(dir: &Path) -> Result<Self> {
+        let pages = json_files_in(dir)?;
+        if pages.is_empty() {
+            bail!("no recorded harvest responses (JSON files) found in '{}'", dir.display());
+        }
+        info!("Replaying {} recorded harvest responses from '{}'", pages.len(), dir.display());
+
+        Ok(Self { pages: pages.into_iter() })
+    }
+
+    fn next(&mut self, since: DateTime<Utc>) -> Result<HarvestResponse> {
+        let Some(path) = self.pages.next() else {
+            bail!("ran out of recorded harvest responses");
+        };
+
+        // The requested `since` only matches the recorded one if the replay
+        // started with the same sync status as the recording. A mismatch is
+        // not fatal, but the replay might diverge from what really happened.
+        let recorded_since = path.file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.split_once('-'))
+            .and_then(|(_, since)| since.parse::<i64>().ok());
+        if recorded_since != Some(since.timestamp_millis()) {
+            warn!(
+                "Recorded harvest response '{}' was requested with a different `since` \
+                    (now: {}). The database state likely differs from the recording.",
+                path.display(),
+                since.timestamp_millis(),
+            );
+        }
+
+        trace!("Replaying harvest response '{}'", path.display());
+        let body = fs::read(&path)
+            .with_context(|| format!("failed to read '{}'", path.display()))?;
+        serde_json::from_slice(&body)
+            .with_context(|| format!("failed to deserialize harvest response '{}'", path.display()))
+    }
+}
+
+/// Returns all `.json` files in `dir`, sorted by name.
+fn json_files_in(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut out = Vec::new();
+    let entries = fs::read_dir(dir)
+        .with_context(|| format!("failed to read directory '{}'", dir.display()))?;
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_file() && path.extension().map_or(false, |ext| ext == "json") {
+            out.push(path);
+        }
+    }
+    out.sort();
+
+    Ok(out)
+}
@@ -1,39 +1,117 @@
 use secrecy::Secret;
+use serde::Deserialize;
 use core::fmt;
-use std::time::Duration;
+use std::{collections::HashSet, time::Duration};
 
-use crate::{config::Config, db::DbConnection, prelude::*};
+use crate::{
+    config::{Config, HttpHost},
+    prelude::*,
+};
 
 
+pub(crate) mod audit;
 pub(crate) mod cmd;
 pub(crate) mod harvest;
 pub(crate) mod stats;
+pub(crate) mod text;
 mod client;
+mod notify;
 mod status;
 
-pub(crate) use self::client::OcClient;
+pub(crate) use self::{client::OcClient, notify::handle_notify};
 
 
 /// The minimum API version this Tobira requires from the Tobira-module API.
 const MIN_REQUIRED_API_VERSION: ApiVersion = ApiVersion::new(1, 0);
 
+/// The API version that added the endpoints to fetch single events and series
+/// (`/tobira/event/{id}` and `/tobira/series/{id}`), which `sync resync`
+/// requires.
+const RESYNC_REQUIRED_API_VERSION: ApiVersion = ApiVersion::new(1, 1);
 
-pub(crate) async fn run(daemon: bool, db: DbConnection, config: &Config) -> Result<()> {
-    let client = OcClient::new(config)?;
-    check_compatibility(&client).await?;
-    harvest::run(daemon, config, &client, db).await
+
+/// Name of the sync source defined by `opencast.sync_node` and `sync.user`.
+pub(crate) const DEFAULT_SOURCE: &str = "default";
+
+
+/// Harvests from all configured sync sources concurrently. Each source uses
+/// its own DB connection from `db` and is independent of the others: if
+/// harvesting one source fails, the others continue. In daemon mode, a failed
+/// source is restarted after some backoff. Otherwise, an error is returned
+/// once all sources are done.
+pub(crate) async fn run(daemon: bool, db: &deadpool_postgres::Pool, config: &Config) -> Result<()> {
+    let tasks = config.sync.sources(config).map(|source| async move {
+        let mut backoff = harvest::INITIAL_BACKOFF;
+        loop {
+            let res = run_source(daemon, db, config, &source).await;
+            match res {
+                Ok(()) => return Ok(()),
+                Err(e) if !daemon => {
+                    error!("Failed to harvest from sync source '{}': {:?}", source.name, e);
+                    return Err(());
+                }
+                Err(e) => {
+                    error!(
+                        "Failed to harvest from sync source '{}' (retrying in {:.1?}): {:?}",
+                        source.name,
+                        backoff,
+                        e,
+                    );
+                    tokio::time::sleep(backoff).await;
+                    backoff = std::cmp::min(harvest::MAX_BACKOFF, backoff * 2);
+                }
+            }
+        }
+    });
+
+    let num_failed = futures::future::join_all(tasks).await
+        .into_iter()
+        .filter(|res| res.is_err())
+        .count();
+    if num_failed > 0 {
+        bail!("harvesting failed for {num_failed} sync source(s), see errors above");
+    }
+
+    Ok(())
+}
+
+async fn run_source(
+    daemon: bool,
+    db: &deadpool_postgres::Pool,
+    config: &Config,
+    source: &SyncSource<'_>,
+) -> Result<()> {
+    let conn = db.get().await?;
+    let client = OcClient::for_source(source)?;
+    check_compatibility(&client).await
+        .with_context(|| format!("incompatible sync source '{}'", source.name))?;
+    let harvest_source = harvest::HarvestSource::Opencast { client, recorder: None };
+    harvest::run(daemon, config, source.name, harvest_source, conn).await
 }
 
 pub(crate) async fn check_compatibility(client: &OcClient) -> Result<()> {
+    check_api_version(client, &MIN_REQUIRED_API_VERSION).await
+}
+
+/// Like `check_compatibility`, but additionally requires the endpoints used by
+/// `sync resync`.
+pub(crate) async fn check_resync_support(client: &OcClient) -> Result<()> {
+    check_api_version(client, &RESYNC_REQUIRED_API_VERSION).await
+        .context("the Tobira module of your Opencast does not support fetching single \
+            items, which is required for resyncing individual items. Try updating Opencast \
+            or use `tobira sync reset` instead.")
+}
+
+async fn check_api_version(client: &OcClient, required: &ApiVersion) -> Result<()> {
     let response = client.get_version().await.context("failed to fetch API version")?;
     let version = response.version();
-    if !version.is_compatible() {
+    if !version.is_compatible_with(required) {
         bail!("Tobira-module API version incompatible! Required: \
-            `^{MIN_REQUIRED_API_VERSION}`, but actual version is: {version}");
+            `^{required}`, but actual version is: {version}");
     }
 
     info!("Tobira-module API version is compatible. Required: \
-        `^{MIN_REQUIRED_API_VERSION}`, actual version: {version}");
+        `^{required}`, actual version: {version}");
     Ok(())
 }
 
@@ -41,12 +119,20 @@ pub(crate) async fn check_compatibility(client: &OcClient) -> Result<()> {
 pub(crate) struct SyncConfig {
     /// Username of the user used to communicate with Opencast for data syncing.
     /// This user has to have access to all events and series. Currently, that
-    /// user has to be admin.
+    /// user has to be admin. Changes made in Tobira (e.g. editing the title of
+    /// an event) are sent to Opencast's External API as this user as well.
     user: String,
 
     /// Password of the user used to communicate with Opencast.
     password: Secret<String>,
 
+    /// ID of the workflow definition that Tobira starts on an event after
+    /// changing its metadata or ACL in Opencast. That workflow has to
+    /// republish the event, as otherwise the change is not picked up by the
+    /// harvest and the event stays marked as having a pending write.
+    #[config(default = "republish-metadata")]
+    pub(crate) republish_workflow: String,
+
     /// A rough estimate of how many items (events & series) are transferred in
     /// each HTTP request while harvesting (syncing) with the Opencast
     /// instance.
@@ -55,20 +141,134 @@ pub(crate) struct SyncConfig {
     /// node having to hold that many items in memory, or due to network
     /// request size restrictions. Too small of a number means that the
     /// overhead of each request will become more significant, slowing down
-    /// harvesting. But more importantly: if your Opencast instance has more
-    /// items with exactly the same `updated` timestamp than the configured
-    /// `preferred_harvest_size`, Tobira is unable to harvest. The `updated`
-    /// timestamp is has millisecond precision, so this situation is highly
-    /// unlikely to occur naturally. However, this can easily occur with
-    /// artificial timestamps, like when you migrate old Opencast data
-    /// (without an `updated` timestamp). Be aware of that.
+    /// harvesting. Also note that if your Opencast instance has more items
+    /// with exactly the same `updated` timestamp than the configured
+    /// `preferred_harvest_size`, Tobira temporarily requests more items (up
+    /// to `max_harvest_size`), see below.
     #[config(default = 500)]
     preferred_harvest_size: u32,
 
+    /// Upper limit for the number of items requested in one harvest request.
+    /// If Opencast has more items with exactly the same `updated` timestamp
+    /// than requested, harvesting cannot make progress. In that case, Tobira
+    /// temporarily increases the number of requested items until progress is
+    /// made, but never beyond this value. The `updated` timestamp has
+    /// millisecond precision, so this situation is unlikely to occur
+    /// naturally. However, this can easily occur with artificial timestamps,
+    /// like when you migrate old Opencast data (without an `updated`
+    /// timestamp) or with mass edits.
+    #[config(default = 10_000)]
+    max_harvest_size: u32,
+
     /// The duration to wait after a "no new data" reply from Opencast. Only
     /// relevant in `--daemon` mode.
     #[config(default = "30s", deserialize_with = crate::config::deserialize_duration)]
     poll_period: Duration,
+
+    /// How long an event deleted in Tobira stays hidden while waiting for
+    /// Opencast to report it as deleted. If that does not happen in time,
+    /// the deletion most likely failed in Opencast, so the event is shown
+    /// again and a warning is logged.
+    #[config(default = "1d", deserialize_with = crate::config::deserialize_duration)]
+    deletion_timeout: Duration,
+
+    /// A shared secret enabling the `POST /~sync/notify` endpoint. Opencast
+    /// (or some middleware) can call that endpoint, sending this value as
+    /// `x-tobira-sync-notify-key` header, to signal that something changed.
+    /// The harvesting daemon then starts the next harvest immediately instead
+    /// of waiting for `poll_period`. If unset, the endpoint is disabled. Like
+    /// `auth.trusted_external_key`, this should be hard to guess and kept
+    /// secret.
+    notify_key: Option<Secret<String>>,
+
+    /// Additional Opencast instances to sync from, besides the one configured
+    /// via `opencast.sync_node`, `sync.user` and `sync.password` (which is
+    /// called "default"). Each source is harvested independently and all
+    /// items are tagged with the name of their source, so Opencast IDs only
+    /// need to be unique per source. Names may only contain lowercase ASCII
+    /// letters, digits and `-`, and must not be changed later. Uploading, the
+    /// editor and Studio always use the default Opencast. Example:
+    ///
+    /// ```
+    /// additional_sources = [
+    ///     { name = "medicine", node = "https://oc.med.example.org", user = "tobira", password = "secret" },
+    /// ]
+    /// ```
+    #[config(default = [])]
+    additional_sources: Vec<AdditionalSyncSource>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AdditionalSyncSource {
+    name: String,
+    node: HttpHost,
+    user: String,
+    password: Secret<String>,
+}
+
+/// An Opencast instance to sync from, i.e. the default one or one defined in
+/// `sync.additional_sources`.
+pub(crate) struct SyncSource<'a> {
+    pub(crate) name: &'a str,
+    pub(crate) node: &'a HttpHost,
+    pub(crate) user: &'a str,
+    pub(crate) password: &'a Secret<String>,
+}
+
+impl SyncConfig {
+    pub(crate) fn validate(&self) -> Result<()> {
+        if self.preferred_harvest_size == 0 {
+            bail!("`sync.preferred_harvest_size` must be greater than 0");
+        }
+        if self.max_harvest_size < self.preferred_harvest_size {
+            bail!("`sync.max_harvest_size` must not be smaller than `sync.preferred_harvest_size`");
+        }
+
+        let mut names = HashSet::from([DEFAULT_SOURCE]);
+        for source in &self.additional_sources {
+            let valid = !source.name.is_empty() && source.name.bytes()
+                .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-');
+            if !valid {
+                bail!("invalid name '{}' in `sync.additional_sources`: only lowercase ASCII \
+                    letters, digits and '-' are allowed", source.name);
+            }
+            if !names.insert(source.name.as_str()) {
+                bail!("duplicate name '{}' in `sync.additional_sources` (note: '{}' is \
+                    reserved)", source.name, DEFAULT_SOURCE);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns all sync sources, starting with the default one.
+    pub(crate) fn sources<'a>(
+        &'a self,
+        config: &'a Config,
+    ) -> impl Iterator<Item = SyncSource<'a>> {
+        let default = SyncSource {
+            name: DEFAULT_SOURCE,
+            node: config.opencast.sync_node(),
+            user: &self.user,
+            password: &self.password,
+        };
+        let additional = self.additional_sources.iter().map(|source| SyncSource {
+            name: &source.name,
+            node: &source.node,
+            user: &source.user,
+            password: &source.password,
+        });
+
+        std::iter::once(default).chain(additional)
+    }
+
+    /// Returns the sync source with the given name.
+    pub(crate) fn source<'a>(&'a self, name: &str, config: &'a Config) -> Result<SyncSource<'a>> {
+        self.sources(config)
+            .find(|source| source.name == name)
+            .ok_or_else(|| anyhow!("sync source '{name}' does not exist (check \
+                `sync.additional_sources`)"))
+    }
 }
 
 
@@ -83,11 +283,10 @@ impl ApiVersion {
         Self { major, minor }
     }
 
-    /// Returns `true` if the API version `self` can be used with this Tobira,
-    /// according to `MIN_REQUIRED_API_VERSION`.
-    fn is_compatible(&self) -> bool {
-        self.major == MIN_REQUIRED_API_VERSION.major
-            && self.minor >= MIN_REQUIRED_API_VERSION.minor
+    /// Returns `true` if the API version `self` can be used where `required`
+    /// is required, according to semantic versioning.
+    fn is_compatible_with(&self, required: &ApiVersion) -> bool {
+        self.major == required.major && self.minor >= required.minor
     }
 }
 
@@ -0,0 +1,43 @@
+use hyper::{body::Incoming, Request, StatusCode};
+use secrecy::ExposeSecret;
+
+use crate::{
+    db,
+    http::{response, Context, Response},
+    prelude::*,
+    util::ByteBody,
+};
+use super::status::SyncStatus;
+
+
+/// Handles `POST /~sync/notify`: requests an immediate harvest. Only enabled
+/// if `sync.notify_key` is set, and requires that key in the
+/// `x-tobira-sync-notify-key` header.
+pub(crate) async fn handle_notify(req: Request<Incoming>, ctx: &Context) -> Response {
+    let Some(notify_key) = &ctx.config.sync.notify_key else {
+        return response::not_found();
+    };
+
+    let authenticated = req.headers()
+        .get("x-tobira-sync-notify-key")
+        .map_or(false, |given_key| notify_key.expose_secret() == given_key);
+    if !authenticated {
+        warn!("Sync notification with missing or wrong key => responding 401");
+        return response::unauthorized();
+    }
+
+    let db = match db::get_conn_or_service_unavailable(&ctx.db_pool).await {
+        Ok(db) => db,
+        Err(r) => return r,
+    };
+    if let Err(e) = SyncStatus::request_wakeup(&**db).await {
+        error!("Failed to request immediate harvest: {e:?}");
+        return response::internal_server_error();
+    }
+
+    debug!("Received sync notification: requested immediate harvest");
+    Response::builder()
+        .status(StatusCode::NO_CONTENT)
+        .body(ByteBody::empty())
+        .unwrap()
+}
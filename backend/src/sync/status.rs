@@ -1,35 +1,136 @@
+use std::time::Duration;
+
 use chrono::{DateTime, NaiveDateTime, Utc, TimeZone};
 use tokio_postgres::GenericClient;
 
 use crate::prelude::*;
+use super::harvest::HarvestStats;
 
 
 /// Stored in the database to keep track of the Opencast <-> Tobira sync. For
 /// more information, see the DB migration script.
 pub(super) struct SyncStatus {
     pub(super) harvested_until: DateTime<Utc>,
+    /// When an immediate harvest was last requested via `POST /~sync/notify`.
+    pub(super) wakeup_requested: Option<DateTime<Utc>>,
 }
 
 impl SyncStatus {
-    /// Fetches that information from the DB.
-    pub(super) async fn fetch(db: &impl GenericClient) -> Result<Self> {
-        let row = db.query_one("select harvested_until from sync_status", &[]).await?;
+    /// Inserts the initial sync status for the given source, if it does not
+    /// exist yet.
+    pub(super) async fn init(source: &str, db: &impl GenericClient) -> Result<()> {
+        db.execute(
+            "insert into sync_status (source, harvested_until) \
+                values ($1, timestamp '1970-01-01 00:00:00') \
+                on conflict do nothing",
+            &[&source],
+        ).await?;
+
+        Ok(())
+    }
+
+    /// Fetches that information for the given source from the DB.
+    pub(super) async fn fetch(source: &str, db: &impl GenericClient) -> Result<Self> {
+        let row = db.query_one(
+            "select harvested_until, wakeup_requested from sync_status where source = $1",
+            &[&source],
+        ).await?;
 
         Ok(Self {
             harvested_until: Utc.from_utc_datetime(&row.get::<_, NaiveDateTime>(0)),
+            wakeup_requested: row.get(1),
         })
     }
 
-    /// Write a new value for `harvested_until` into the database.
+    /// Write a new value for `harvested_until` of the given source into the
+    /// database.
     pub(super) async fn update_harvested_until(
         new_value: DateTime<Utc>,
+        source: &str,
         db: &impl GenericClient,
     ) -> Result<()> {
         db.execute(
-            "update sync_status set harvested_until = $1",
-            &[&new_value.naive_utc()],
+            "update sync_status set harvested_until = $1 where source = $2",
+            &[&new_value.naive_utc(), &source],
+        ).await?;
+
+        Ok(())
+    }
+
+    /// Requests an immediate harvest of all sources, which wakes up the
+    /// harvesting daemons if they are currently waiting.
+    pub(super) async fn request_wakeup(db: &impl GenericClient) -> Result<()> {
+        db.execute("update sync_status set wakeup_requested = now()", &[]).await?;
+        Ok(())
+    }
+}
+
+
+/// One harvest request and its outcome, stored in the `harvest_runs` table.
+pub(super) struct HarvestRun {
+    pub(super) source: String,
+    pub(super) started: DateTime<Utc>,
+    pub(super) duration: Duration,
+    pub(super) since: DateTime<Utc>,
+    pub(super) outcome: HarvestOutcome,
+}
+
+pub(super) enum HarvestOutcome {
+    Success {
+        includes_items_until: DateTime<Utc>,
+        stats: HarvestStats,
+    },
+    Failure {
+        error: String,
+        backoff: Duration,
+    },
+}
+
+impl HarvestRun {
+    /// Runs older than this are removed from the DB.
+    const RETENTION: &'static str = "7 days";
+
+    /// Inserts this run into the DB and removes old runs.
+    pub(super) async fn store(&self, db: &impl GenericClient) -> Result<()> {
+        let no_stats = HarvestStats::default();
+        let (includes_items_until, stats, error, backoff_ms) = match &self.outcome {
+            HarvestOutcome::Success { includes_items_until, stats } => {
+                (Some(*includes_items_until), stats, None, None)
+            }
+            HarvestOutcome::Failure { error, backoff } => {
+                (None, &no_stats, Some(error), Some(backoff.as_millis() as i64))
+            }
+        };
+
+        db.execute(
+            "insert into harvest_runs ( \
+                source, started, duration_ms, since, includes_items_until, \
+                upserted_events, removed_events, upserted_series, removed_series, \
+                upserted_playlists, removed_playlists, error, backoff_ms \
+            ) values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)",
+            &[
+                &self.source,
+                &self.started,
+                &(self.duration.as_millis() as i64),
+                &self.since,
+                &includes_items_until,
+                &stats.upserted_events,
+                &stats.removed_events,
+                &stats.upserted_series,
+                &stats.removed_series,
+                &stats.upserted_playlists,
+                &stats.removed_playlists,
+                &error,
+                &backoff_ms,
+            ],
         ).await?;
 
+        let query = format!(
+            "delete from harvest_runs where started < now() - interval '{}'",
+            Self::RETENTION,
+        );
+        db.execute(&query, &[]).await?;
+
         Ok(())
     }
 }
@@ -0,0 +1,174 @@
+//! Fetching texts (currently only captions) of events, which are stored in
+//! the DB and added to the search index.
+
+use std::{collections::HashMap, time::Duration};
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    config::Config,
+    db::{types::{EventCaption, Key, TimespanText}, DbConnection},
+    prelude::*,
+    util::Never,
+};
+use super::OcClient;
+
+mod vtt;
+
+
+/// How long to wait before checking the queue again once it is empty.
+const POLL_PERIOD: Duration = Duration::from_secs(30);
+
+/// How many events are processed per DB query.
+const BATCH_SIZE: i64 = 20;
+
+/// After this many failed attempts, we give up fetching the texts of an event.
+/// They are only fetched again once the event's captions change.
+const MAX_RETRIES: i32 = 6;
+
+
+/// Continuously processes `event_texts_queue`: downloads and parses the
+/// captions of all queued events and stores their texts in `event_texts`.
+pub(crate) async fn run_daemon(mut db: DbConnection, config: &Config) -> Result<Never> {
+    // Let the other more important worker processes do stuff first.
+    tokio::time::sleep(Duration::from_secs(5)).await;
+
+    // Caption URIs of events are fetched with the client of the sync source
+    // the event belongs to.
+    let clients = config.sync.sources(config)
+        .map(|source| Ok((source.name.to_owned(), OcClient::for_source(&source)?)))
+        .collect::<Result<HashMap<_, _>>>()?;
+
+    loop {
+        match process_batch(&clients, &mut db).await {
+            Ok(0) => tokio::time::sleep(POLL_PERIOD).await,
+            Ok(_) => {}
+            Err(e) => {
+                warn!("Failed to process event text queue: {e:?}");
+                tokio::time::sleep(POLL_PERIOD).await;
+            }
+        }
+    }
+}
+
+/// Processes up to `BATCH_SIZE` queued events and returns how many were
+/// processed.
+async fn process_batch(
+    clients: &HashMap<String, OcClient>,
+    db: &mut DbConnection,
+) -> Result<usize> {
+    let query = format!("select events.id, events.opencast_id, events.captions, \
+            event_texts_queue.fetch_after, event_texts_queue.retry_count, events.sync_source \
+        from event_texts_queue \
+        inner join events on events.id = event_texts_queue.event_id \
+        where event_texts_queue.fetch_after <= now() \
+        order by event_texts_queue.fetch_after \
+        limit {BATCH_SIZE}");
+    let rows = db.query(&query, &[]).await?;
+
+    for row in &rows {
+        let event_id: Key = row.get(0);
+        let opencast_id: String = row.get(1);
+        let captions: Vec<EventCaption> = row.get(2);
+        let fetch_after: DateTime<Utc> = row.get(3);
+        let retry_count: i32 = row.get(4);
+        let sync_source: String = row.get(5);
+
+        // Events of sync sources that were removed from the config are
+        // treated like any other failure.
+        let texts = match clients.get(&sync_source) {
+            Some(client) => fetch_texts(client, &captions).await,
+            None => Err(anyhow!("sync source '{sync_source}' is not configured")),
+        };
+        match texts {
+            Ok(texts) => {
+                store_texts(db, event_id, texts, fetch_after).await?;
+                debug!("Stored texts of {} captions for event '{opencast_id}'", captions.len());
+            }
+            Err(e) if retry_count + 1 >= MAX_RETRIES => {
+                warn!("Failed to fetch texts of event '{opencast_id}' (giving up): {e:?}");
+                db.execute(
+                    "delete from event_texts_queue where event_id = $1 and fetch_after = $2",
+                    &[&event_id, &fetch_after],
+                ).await?;
+            }
+            Err(e) => {
+                // Exponential backoff, starting with one minute.
+                let backoff = chrono::Duration::minutes(1 << retry_count);
+                warn!("Failed to fetch texts of event '{opencast_id}' (retrying in {}min): {e:?}",
+                    backoff.num_minutes());
+                db.execute(
+                    "update event_texts_queue \
+                        set retry_count = retry_count + 1, fetch_after = $3 \
+                        where event_id = $1 and fetch_after = $2",
+                    &[&event_id, &fetch_after, &(Utc::now() + backoff)],
+                ).await?;
+            }
+        }
+    }
+
+    Ok(rows.len())
+}
+
+/// Downloads and parses all given captions. Returns the URI and texts of each
+/// caption. Captions that cannot be parsed are skipped with a warning, as
+/// retrying won't help in that case.
+async fn fetch_texts(
+    client: &OcClient,
+    captions: &[EventCaption],
+) -> Result<Vec<(String, Vec<TimespanText>)>> {
+    let mut out = Vec::new();
+    for caption in captions {
+        let body = client.get_text_asset(&caption.uri).await?;
+        let texts = std::str::from_utf8(&body)
+            .map_err(anyhow::Error::from)
+            .and_then(vtt::parse);
+        match texts {
+            Ok(texts) => out.push((caption.uri.clone(), texts)),
+            Err(e) => warn!("Failed to parse caption '{}' as WebVTT, ignoring it: {e:?}", caption.uri),
+        }
+    }
+
+    Ok(out)
+}
+
+/// Replaces all stored texts of the given event, removes it from the queue
+/// and queues it for reindexing.
+async fn store_texts(
+    db: &mut DbConnection,
+    event_id: Key,
+    texts: Vec<(String, Vec<TimespanText>)>,
+    fetch_after: DateTime<Utc>,
+) -> Result<()> {
+    let tx = db.transaction().await?;
+
+    // If the queue entry was changed in the meantime (i.e. the captions
+    // changed while we were downloading), we leave it in the queue and don't
+    // store anything, as the data is outdated already.
+    let removed = tx.execute(
+        "delete from event_texts_queue where event_id = $1 and fetch_after = $2",
+        &[&event_id, &fetch_after],
+    ).await?;
+    if removed == 0 {
+        return Ok(());
+    }
+
+    tx.execute("delete from event_texts where event_id = $1", &[&event_id]).await?;
+    for (uri, texts) in texts {
+        tx.execute(
+            "insert into event_texts (event_id, uri, texts, fetch_time) \
+                values ($1, $2, $3, now()) \
+                on conflict (event_id, uri) do update set \
+                    texts = excluded.texts, fetch_time = excluded.fetch_time",
+            &[&event_id, &uri, &texts],
+        ).await?;
+    }
+    tx.execute(
+        "insert into search_index_queue (item_id, kind) values ($1, 'event') \
+            on conflict do nothing",
+        &[&event_id],
+    ).await?;
+
+    tx.commit().await?;
+    Ok(())
+}
@@ -0,0 +1,152 @@
+//! A minimal WebVTT parser, only extracting the cue timings and texts.
+//!
+//! See <https://www.w3.org/TR/webvtt1/>. Everything not needed for search
+//! (cue settings, styles, regions, comments) is ignored.
+
+use crate::{db::types::TimespanText, prelude::*};
+
+
+/// Parses the given WebVTT file into a list of timespan texts, one per cue.
+/// Markup inside cue texts (e.g. `<v Speaker>` or `<b>`) is removed and the
+/// lines of a cue are joined with spaces. Cues without text are skipped.
+pub(super) fn parse(input: &str) -> Result<Vec<TimespanText>> {
+    let input = input.strip_prefix('\u{feff}').unwrap_or(input);
+    let mut blocks = input.split("\n\n")
+        .flat_map(|s| s.split("\r\n\r\n"))
+        .map(|block| block.trim_matches(|c| c == '\n' || c == '\r'))
+        .filter(|block| !block.is_empty());
+
+    let header = blocks.next().unwrap_or("");
+    if !header.starts_with("WEBVTT") {
+        bail!("not a WebVTT file (missing 'WEBVTT' header)");
+    }
+
+    let mut out = Vec::new();
+    for block in blocks {
+        let mut lines = block.lines().map(|l| l.trim_end_matches('\r'));
+
+        // The timing line is either the first line or, if the cue has an
+        // identifier, the second one. Blocks without timing line are
+        // comments, styles, regions or garbage, which we ignore.
+        let Some(timing) = lines.by_ref().take(2).find(|l| l.contains("-->")) else {
+            continue;
+        };
+        let (start, end) = parse_timing(timing)
+            .with_context(|| format!("invalid cue timing '{timing}'"))?;
+
+        let text = lines
+            .map(strip_markup)
+            .map(|l| l.trim().to_owned())
+            .filter(|l| !l.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        if !text.is_empty() {
+            out.push(TimespanText { span_start: start, span_end: end, t: text });
+        }
+    }
+
+    Ok(out)
+}
+
+/// Parses a timing line like `00:01:02.500 --> 00:01:04.000 align:start`,
+/// returning start and end in ms.
+fn parse_timing(line: &str) -> Option<(i64, i64)> {
+    let (start, rest) = line.split_once("-->")?;
+    let end = rest.split_whitespace().next()?;
+    Some((parse_timestamp(start.trim())?, parse_timestamp(end)?))
+}
+
+/// Parses `hh:mm:ss.ttt` or `mm:ss.ttt` into ms.
+fn parse_timestamp(s: &str) -> Option<i64> {
+    let (rest, millis) = s.split_once('.')?;
+    if millis.len() != 3 {
+        return None;
+    }
+    let millis: i64 = millis.parse().ok()?;
+
+    let parts = rest.split(':').map(|p| p.parse::<i64>().ok()).collect::<Option<Vec<_>>>()?;
+    let (hours, minutes, seconds) = match *parts {
+        [m, s] => (0, m, s),
+        [h, m, s] => (h, m, s),
+        _ => return None,
+    };
+    if minutes >= 60 || seconds >= 60 {
+        return None;
+    }
+
+    Some(((hours * 60 + minutes) * 60 + seconds) * 1000 + millis)
+}
+
+/// Removes all tags (`<...>`) from a cue text line and decodes the most
+/// common character references.
+fn strip_markup(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut in_tag = false;
+    for c in line.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            c if !in_tag => out.push(c),
+            _ => {}
+        }
+    }
+
+    out.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_simple(input: &str) -> Vec<(i64, i64, String)> {
+        parse(input).unwrap().into_iter().map(|t| (t.span_start, t.span_end, t.t)).collect()
+    }
+
+    #[test]
+    fn simple() {
+        let input = "WEBVTT\n\
+            \n\
+            00:00.000 --> 00:02.500\n\
+            Hello everyone.\n\
+            \n\
+            1\n\
+            00:00:02.500 --> 00:01:05.000 align:start\n\
+            Today we talk about\n\
+            <v Prof>linear algebra</v>.\n";
+
+        assert_eq!(parse_simple(input), [
+            (0, 2500, "Hello everyone.".into()),
+            (2500, 65_000, "Today we talk about linear algebra.".into()),
+        ]);
+    }
+
+    #[test]
+    fn ignores_non_cue_blocks() {
+        let input = "\u{feff}WEBVTT - some title\r\n\
+            \r\n\
+            NOTE this is a comment\r\n\
+            \r\n\
+            STYLE\r\n\
+            ::cue { color: red }\r\n\
+            \r\n\
+            01:00:00.000 --> 01:00:01.000\r\n\
+            a &amp; b\r\n\
+            \r\n\
+            01:00:01.000 --> 01:00:02.000\r\n\
+            <c></c>\r\n";
+
+        assert_eq!(parse_simple(input), [(3_600_000, 3_601_000, "a & b".into())]);
+    }
+
+    #[test]
+    fn errors() {
+        assert!(parse("").is_err());
+        assert!(parse("00:00.000 --> 00:01.000\nfoo").is_err());
+        assert!(parse("WEBVTT\n\n00:00.00 --> 00:01.000\nfoo").is_err());
+        assert!(parse("WEBVTT\n\n00:61.000 --> 01:00.000\nfoo").is_err());
+    }
+}